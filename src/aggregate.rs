@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+
+use crate::{
+    decimal::Decimal,
+    expression::{arithmetic, BoundExpression, DataType, Scope},
+    query,
+    sql_parser::{AggregateFunction, ArithmeticOperator, Expression},
+    table::Value,
+};
+
+#[derive(Clone)]
+pub enum Accumulator {
+    Count(u64),
+    Sum(Option<Value>),
+    Min(Option<Value>),
+    Max(Option<Value>),
+    Avg { sum: Option<Value>, count: u64 },
+}
+
+impl Accumulator {
+    pub fn new(function: AggregateFunction) -> Accumulator {
+        match function {
+            AggregateFunction::Count => Accumulator::Count(0),
+            AggregateFunction::Sum => Accumulator::Sum(None),
+            AggregateFunction::Min => Accumulator::Min(None),
+            AggregateFunction::Max => Accumulator::Max(None),
+            AggregateFunction::Avg => Accumulator::Avg { sum: None, count: 0 },
+        }
+    }
+
+    /// Adds the aggregate's argument for one row. `count(*)` has no
+    /// argument, so `value` is `None` and every row counts. Nulls are
+    /// skipped.
+    pub fn add(&mut self, value: Option<Value>) -> Result<(), String> {
+        if value == Some(Value::Null) {
+            return Ok(());
+        }
+
+        // Adds numbers of any type the way `+` does.
+        let add = |sum: &Option<Value>, value: Value| match (sum, value.is_number()) {
+            (_, false) => Err(format!("Cannot sum or average {}", value)),
+            (None, true) => Ok(value),
+            (Some(sum), true) => {
+                arithmetic(sum, ArithmeticOperator::Add, &value).map_err(|_| "Aggregate is out of range".to_string())
+            }
+        };
+
+        match (self, value) {
+            (Accumulator::Count(count), _) => *count += 1,
+            (Accumulator::Sum(sum), Some(value)) => {
+                *sum = Some(add(sum, value)?);
+            }
+            (Accumulator::Min(min), Some(value)) => {
+                if min.as_ref().is_none_or(|min| value < *min) {
+                    *min = Some(value);
+                }
+            }
+            (Accumulator::Max(max), Some(value)) => {
+                if max.as_ref().is_none_or(|max| value > *max) {
+                    *max = Some(value);
+                }
+            }
+            (Accumulator::Avg { sum, count }, Some(value)) => {
+                *sum = Some(add(sum, value)?);
+                *count += 1;
+            }
+            (_, None) => unreachable!("only count takes no argument"),
+        }
+        Ok(())
+    }
+
+    /// The aggregate's value. Everything but `count` is null over no rows.
+    /// `avg` of whole numbers is a decimal, so it keeps its fraction rather
+    /// than dropping it like `/` does. An average can be out of range when
+    /// its division needs more places than the sum's type holds.
+    pub fn finish(self) -> Result<Value, String> {
+        let value = match self {
+            Accumulator::Count(count) => Value::Number { value: count },
+            Accumulator::Sum(sum) => sum.unwrap_or(Value::Null),
+            Accumulator::Min(value) | Accumulator::Max(value) => value.unwrap_or(Value::Null),
+            Accumulator::Avg { sum: None, .. } => Value::Null,
+            Accumulator::Avg { sum: Some(sum), count } => {
+                let sum = match sum {
+                    Value::Number { value } => Value::Decimal {
+                        value: Decimal::from_whole(value as i128),
+                    },
+                    Value::Int { value } => Value::Decimal {
+                        value: Decimal::from_whole(value as i128),
+                    },
+                    sum => sum,
+                };
+                arithmetic(&sum, ArithmeticOperator::Divide, &Value::Number { value: count })
+                    .map_err(|_| "Aggregate is out of range".to_string())?
+            }
+        };
+        Ok(value)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+struct BoundAggregate {
+    function: AggregateFunction,
+    argument: Option<BoundExpression<'static>>,
+}
+
+/// Hash aggregation. Rows are grouped on the values of the `group by`
+/// expressions, and every aggregate used by the query is accumulated per
+/// group. Each group comes out as one row holding its `group by` values
+/// followed by its aggregates, and `bind` binds the rest of the query
+/// against that layout.
+pub struct Aggregation {
+    scope: Scope,
+    group_by: Vec<BoundExpression<'static>>,
+    aggregates: Vec<BoundAggregate>,
+}
+
+impl Aggregation {
+    /// Plans the aggregation of every aggregate found in `expressions`.
+    pub fn new(group_by: &[Expression], expressions: &[&Expression], scope: &Scope) -> Result<Aggregation, String> {
+        let group_by = group_by
+            .iter()
+            .map(|e| BoundExpression::bind(e, scope))
+            .collect::<Result<Vec<BoundExpression>, String>>()?;
+
+        let mut aggregates = Vec::new();
+        for expression in expressions {
+            for aggregate in find_aggregates(expression) {
+                let aggregate = Aggregation::bind_aggregate(aggregate, scope)?;
+                if !aggregates.contains(&aggregate) {
+                    aggregates.push(aggregate);
+                }
+            }
+        }
+
+        Ok(Aggregation {
+            scope: scope.clone(),
+            group_by,
+            aggregates,
+        })
+    }
+
+    fn bind_aggregate(aggregate: &Expression, scope: &Scope) -> Result<BoundAggregate, String> {
+        match aggregate {
+            Expression::Aggregate { function, argument } => Ok(BoundAggregate {
+                function: *function,
+                argument: argument
+                    .as_ref()
+                    .map(|a| BoundExpression::bind(a, scope))
+                    .transpose()?,
+            }),
+            _ => Err(format!("{} is not an aggregate", aggregate)),
+        }
+    }
+
+    /// Binds an expression evaluated once per group. It can only use
+    /// columns through the `group by` expressions or inside aggregates.
+    pub fn bind(&self, expression: &Expression) -> Result<BoundExpression<'static>, String> {
+        BoundExpression::bind_with(expression, &|e| self.resolve(e))
+    }
+
+    pub fn resolve(&self, expression: &Expression) -> Result<Option<BoundExpression<'static>>, String> {
+        if let Expression::Aggregate { .. } = expression {
+            let aggregate = Aggregation::bind_aggregate(expression, &self.scope)?;
+            return match self.aggregates.iter().position(|a| *a == aggregate) {
+                Some(index) => Ok(Some(BoundExpression::Column {
+                    index: self.group_by.len() + index,
+                    data_type: result_type(aggregate.function, aggregate.argument.as_ref()),
+                })),
+                None => Err(format!("Aggregate {} was not planned", expression)),
+            };
+        }
+
+        if let Ok(bound) = BoundExpression::bind(expression, &self.scope) {
+            if let Some(index) = self.group_by.iter().position(|g| *g == bound) {
+                return Ok(Some(BoundExpression::Column {
+                    index,
+                    data_type: bound.data_type(),
+                }));
+            }
+        }
+
+        match expression {
+            Expression::Column { .. } => Err(format!(
+                "Column '{}' must appear in the group by clause or be used in an aggregate function",
+                expression
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    /// How many values each group's row holds.
+    pub fn width(&self) -> usize {
+        self.group_by.len() + self.aggregates.len()
+    }
+
+    pub fn run<I>(&self, rows: I) -> Result<Vec<Vec<Value>>, String>
+    where
+        I: Iterator<Item = Result<Vec<Value>, String>>,
+    {
+        let mut group_indices: HashMap<Vec<Value>, usize> = HashMap::new();
+        let mut groups: Vec<(Vec<Value>, Vec<Accumulator>)> = Vec::new();
+
+        // Without a group by, the whole input is one group, even when empty.
+        if self.group_by.is_empty() {
+            group_indices.insert(Vec::new(), 0);
+            groups.push((Vec::new(), self.accumulators()));
+        }
+
+        for row in rows {
+            let row = row?;
+            let key = self
+                .group_by
+                .iter()
+                .map(|g| g.evaluate(&row))
+                .collect::<Result<Vec<Value>, String>>()?;
+
+            // A group keeps the values of its first row.
+            let hash_key = query::row_key(&key);
+            let index = match group_indices.get(&hash_key) {
+                Some(index) => *index,
+                None => {
+                    group_indices.insert(hash_key, groups.len());
+                    groups.push((key, self.accumulators()));
+                    groups.len() - 1
+                }
+            };
+
+            for (aggregate, accumulator) in self.aggregates.iter().zip(groups[index].1.iter_mut()) {
+                let value = aggregate.argument.as_ref().map(|a| a.evaluate(&row)).transpose()?;
+                accumulator.add(value)?;
+            }
+        }
+
+        groups
+            .into_iter()
+            .map(|(mut key, accumulators)| {
+                for accumulator in accumulators {
+                    key.push(accumulator.finish()?);
+                }
+                Ok(key)
+            })
+            .collect()
+    }
+
+    fn accumulators(&self) -> Vec<Accumulator> {
+        self.aggregates
+            .iter()
+            .map(|a| Accumulator::new(a.function))
+            .collect()
+    }
+}
+
+/// The type of an aggregate's value, given its argument.
+pub fn result_type(function: AggregateFunction, argument: Option<&BoundExpression>) -> Option<DataType> {
+    match function {
+        AggregateFunction::Count | AggregateFunction::Sum | AggregateFunction::Avg => Some(DataType::Number),
+        AggregateFunction::Min | AggregateFunction::Max => argument.and_then(BoundExpression::data_type),
+    }
+}
+
+/// The aggregate calls within an expression.
+pub fn find_aggregates(expression: &Expression) -> Vec<&Expression> {
+    match expression {
+        Expression::Aggregate { .. } => vec![expression],
+        Expression::Column { .. } | Expression::Literal { .. } => Vec::new(),
+        Expression::Comparison { left, right, .. }
+        | Expression::Arithmetic { left, right, .. }
+        | Expression::And { left, right }
+        | Expression::Or { left, right } => {
+            let mut aggregates = find_aggregates(left);
+            aggregates.extend(find_aggregates(right));
+            aggregates
+        }
+        Expression::Not { expression } | Expression::IsNull { expression, .. } => find_aggregates(expression),
+        Expression::InList { expression, list, .. } => std::iter::once(expression.as_ref())
+            .chain(list.iter())
+            .flat_map(find_aggregates)
+            .collect(),
+        Expression::Between {
+            expression, low, high, ..
+        } => [expression, low, high].into_iter().flat_map(|e| find_aggregates(e)).collect(),
+        Expression::PatternMatch { expression, pattern, .. } => {
+            let mut aggregates = find_aggregates(expression);
+            aggregates.extend(find_aggregates(pattern));
+            aggregates
+        }
+        Expression::Case {
+            operand,
+            branches,
+            otherwise,
+        } => operand
+            .iter()
+            .chain(otherwise.iter())
+            .map(Box::as_ref)
+            .chain(branches.iter().flat_map(|(when, then)| [when, then]))
+            .flat_map(find_aggregates)
+            .collect(),
+        Expression::Function { arguments, .. } => arguments.iter().flat_map(find_aggregates).collect(),
+        Expression::InSubquery { expression, .. } => find_aggregates(expression),
+        // The window function itself isn't a group's aggregate, but what it
+        // works on can be.
+        Expression::Window {
+            arguments,
+            partition_by,
+            order_by,
+            ..
+        } => arguments
+            .iter()
+            .chain(partition_by.iter())
+            .chain(order_by.iter().map(|o| &o.expression))
+            .flat_map(find_aggregates)
+            .collect(),
+        // A subquery's aggregates belong to the subquery.
+        Expression::Exists { .. } => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aggregate(function: AggregateFunction, values: &[Value]) -> Value {
+        let mut accumulator = Accumulator::new(function);
+        for value in values {
+            accumulator.add(Some(value.clone())).unwrap();
+        }
+        accumulator.finish().unwrap()
+    }
+
+    #[test]
+    fn test_accumulators() {
+        let numbers = [
+            Value::Number { value: 3 },
+            Value::Null,
+            Value::Number { value: 1 },
+            Value::Number { value: 4 },
+        ];
+        let number = |value| Value::Number { value };
+
+        assert_eq!(number(3), aggregate(AggregateFunction::Count, &numbers));
+        assert_eq!(number(8), aggregate(AggregateFunction::Sum, &numbers));
+        assert_eq!(number(1), aggregate(AggregateFunction::Min, &numbers));
+        assert_eq!(number(4), aggregate(AggregateFunction::Max, &numbers));
+        assert_eq!("2.666667", aggregate(AggregateFunction::Avg, &numbers).to_string());
+
+        assert_eq!(number(0), aggregate(AggregateFunction::Count, &[]));
+        assert_eq!(Value::Null, aggregate(AggregateFunction::Sum, &[]));
+        assert_eq!(Value::Null, aggregate(AggregateFunction::Max, &[]));
+        assert_eq!(Value::Null, aggregate(AggregateFunction::Avg, &[]));
+
+        let mut sum = Accumulator::new(AggregateFunction::Sum);
+        assert!(sum.add(Some(Value::Boolean { value: true })).is_err());
+
+        let ints = [Value::Int { value: -3 }, Value::Int { value: 1 }, Value::Int { value: -2 }];
+        assert_eq!(Value::Int { value: -4 }, aggregate(AggregateFunction::Sum, &ints));
+        assert_eq!("-1.333333", aggregate(AggregateFunction::Avg, &ints).to_string());
+        assert_eq!(Value::Int { value: -3 }, aggregate(AggregateFunction::Min, &ints));
+
+        let doubles = [Value::Double { value: 0.5 }, Value::Int { value: 2 }];
+        assert_eq!(Value::Double { value: 2.5 }, aggregate(AggregateFunction::Sum, &doubles));
+        assert_eq!(Value::Double { value: 1.25 }, aggregate(AggregateFunction::Avg, &doubles));
+        assert_eq!(Value::Int { value: 2 }, aggregate(AggregateFunction::Max, &doubles));
+
+        let decimal = |text| Value::Decimal {
+            value: Decimal::parse(text).unwrap(),
+        };
+        let decimals = [decimal("0.10"), decimal("0.2"), Value::Number { value: 1 }];
+        assert_eq!(decimal("1.3"), aggregate(AggregateFunction::Sum, &decimals));
+        assert_eq!("0.433333", aggregate(AggregateFunction::Avg, &decimals).to_string());
+
+        // Dividing at six places doesn't fit a sum this large.
+        let mut avg = Accumulator::new(AggregateFunction::Avg);
+        avg.add(Some(decimal("99999999999999999999999999999999999"))).unwrap();
+        assert_eq!(Err("Aggregate is out of range".to_string()), avg.finish());
+    }
+}
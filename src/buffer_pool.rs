@@ -0,0 +1,389 @@
+use std::{cell::RefCell, collections::HashMap, io, rc::Rc};
+
+use crate::{
+    pager::Pager,
+    wal::{LogRecord, Wal},
+};
+
+pub type FileId = usize;
+pub type FrameId = usize;
+
+pub type SharedBufferPool = Rc<RefCell<BufferPool>>;
+
+struct PoolFile {
+    file_name: String,
+    pager: Pager,
+}
+
+struct Frame {
+    key: Option<(FileId, usize)>,
+    page: Vec<u8>,
+    pin_count: usize,
+    dirty: bool,
+    referenced: bool,
+}
+
+/// Caches up to `capacity` pages across every open file. A page is pinned by
+/// `fetch` and must be released with `unpin`; only unpinned pages can be
+/// evicted, which is done with the clock algorithm.
+///
+/// The pool also owns the write-ahead log, because evicting a page modified
+/// by the open transaction writes it to its data file before the transaction
+/// commits. The page's previous contents are logged first, so the eviction
+/// can be undone by `rollback` or by recovery.
+pub struct BufferPool {
+    capacity: usize,
+    files: Vec<PoolFile>,
+    frames: Vec<Frame>,
+    page_table: HashMap<(FileId, usize), FrameId>,
+    clock_hand: usize,
+    wal: Wal,
+    tx_id: Option<u64>,
+}
+
+impl BufferPool {
+    pub fn new(capacity: usize, wal: Wal) -> BufferPool {
+        assert!(capacity > 0);
+        BufferPool {
+            capacity,
+            files: Vec::new(),
+            frames: Vec::new(),
+            page_table: HashMap::new(),
+            clock_hand: 0,
+            wal,
+            tx_id: None,
+        }
+    }
+
+    pub fn shared(self) -> SharedBufferPool {
+        Rc::new(RefCell::new(self))
+    }
+
+    /// Registers a file with the pool. `file_name` is how the write-ahead log
+    /// refers to it, relative to the data directory.
+    pub fn add_file(&mut self, file_name: &str, pager: Pager) -> FileId {
+        self.files.push(PoolFile {
+            file_name: file_name.to_string(),
+            pager,
+        });
+        self.files.len() - 1
+    }
+
+    /// Pins the page, reading it from disk if it isn't already cached.
+    pub fn fetch(&mut self, file_id: FileId, page_no: usize) -> io::Result<FrameId> {
+        let frame_id = match self.page_table.get(&(file_id, page_no)) {
+            Some(frame_id) => *frame_id,
+            None => {
+                let page = self.files[file_id].pager.read_page(page_no)?;
+                let frame_id = self.free_frame()?;
+                let frame = &mut self.frames[frame_id];
+                frame.key = Some((file_id, page_no));
+                frame.page = page;
+                frame.dirty = false;
+                self.page_table.insert((file_id, page_no), frame_id);
+                frame_id
+            }
+        };
+
+        let frame = &mut self.frames[frame_id];
+        frame.pin_count += 1;
+        frame.referenced = true;
+        Ok(frame_id)
+    }
+
+    pub fn unpin(&mut self, frame_id: FrameId) {
+        let frame = &mut self.frames[frame_id];
+        assert!(frame.pin_count > 0);
+        frame.pin_count -= 1;
+    }
+
+    pub fn page(&self, frame_id: FrameId) -> &[u8] {
+        &self.frames[frame_id].page
+    }
+
+    pub fn page_mut(&mut self, frame_id: FrameId) -> &mut [u8] {
+        let frame = &mut self.frames[frame_id];
+        frame.dirty = true;
+        &mut frame.page
+    }
+
+    fn free_frame(&mut self) -> io::Result<FrameId> {
+        if self.frames.len() < self.capacity {
+            self.frames.push(Frame {
+                key: None,
+                page: Vec::new(),
+                pin_count: 0,
+                dirty: false,
+                referenced: false,
+            });
+            return Ok(self.frames.len() - 1);
+        }
+
+        // Two sweeps clear every reference bit, so a third finding nothing
+        // means every page is pinned.
+        for _ in 0..self.frames.len() * 3 {
+            let frame_id = self.clock_hand;
+            self.clock_hand = (self.clock_hand + 1) % self.frames.len();
+
+            let frame = &mut self.frames[frame_id];
+            if frame.pin_count > 0 {
+                continue;
+            }
+            if frame.referenced {
+                frame.referenced = false;
+                continue;
+            }
+
+            self.evict(frame_id)?;
+            return Ok(frame_id);
+        }
+
+        Err(io::Error::other(format!(
+            "Buffer pool is full. All {} pages are pinned.",
+            self.capacity
+        )))
+    }
+
+    fn evict(&mut self, frame_id: FrameId) -> io::Result<()> {
+        let (file_id, page_no) = match self.frames[frame_id].key {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+
+        if self.frames[frame_id].dirty {
+            if let Some(tx_id) = self.tx_id {
+                let file = &mut self.files[file_id];
+                let file_name = file.file_name.clone();
+                self.wal.append(&LogRecord::PageUndo {
+                    tx_id,
+                    file_name: file_name.clone(),
+                    page_no,
+                    page: file.pager.read_page(page_no)?,
+                })?;
+                self.wal.append(&LogRecord::PageWrite {
+                    tx_id,
+                    file_name,
+                    page_no,
+                    page: self.frames[frame_id].page.clone(),
+                })?;
+                self.wal.sync()?;
+            }
+            self.files[file_id]
+                .pager
+                .write_page(page_no, &self.frames[frame_id].page)?;
+        }
+
+        self.page_table.remove(&(file_id, page_no));
+        let frame = &mut self.frames[frame_id];
+        frame.key = None;
+        frame.dirty = false;
+        Ok(())
+    }
+
+    pub fn begin(&mut self) -> io::Result<()> {
+        assert!(self.tx_id.is_none());
+        self.tx_id = Some(self.wal.begin()?);
+        Ok(())
+    }
+
+    /// Logs a page that is written outside the pool as part of the open
+    /// transaction.
+    pub fn log_page(&mut self, file_name: &str, page_no: usize, page: Vec<u8>) -> io::Result<()> {
+        self.wal.append(&LogRecord::PageWrite {
+            tx_id: self.tx_id.unwrap(),
+            file_name: file_name.to_string(),
+            page_no,
+            page,
+        })
+    }
+
+    /// Logs every dirty page and then the commit record. The pages still
+    /// have to be flushed before the log can be checkpointed.
+    pub fn log_commit(&mut self) -> io::Result<()> {
+        let tx_id = self.tx_id.unwrap();
+
+        let mut dirty: Vec<&Frame> = self.frames.iter().filter(|f| f.dirty).collect();
+        dirty.sort_by_key(|f| f.key);
+        for frame in dirty {
+            let (file_id, page_no) = frame.key.unwrap();
+            self.wal.append(&LogRecord::PageWrite {
+                tx_id,
+                file_name: self.files[file_id].file_name.clone(),
+                page_no,
+                page: frame.page.clone(),
+            })?;
+        }
+
+        self.wal.commit(tx_id)?;
+        self.tx_id = None;
+        Ok(())
+    }
+
+    /// Writes the file's dirty pages and syncs it.
+    pub fn flush_file(&mut self, file_id: FileId) -> io::Result<()> {
+        for frame in self.frames.iter_mut() {
+            if frame.dirty && frame.key.map(|(f, _)| f) == Some(file_id) {
+                let (_, page_no) = frame.key.unwrap();
+                self.files[file_id].pager.write_page(page_no, &frame.page)?;
+                frame.dirty = false;
+            }
+        }
+        self.files[file_id].pager.sync()
+    }
+
+    pub fn checkpoint(&mut self) -> io::Result<()> {
+        self.wal.checkpoint()
+    }
+
+    /// Abandons the open transaction. Dirty pages are dropped, and any page
+    /// the transaction already wrote to disk is restored from the log. Only
+    /// the transaction's own records are then removed from the log.
+    pub fn rollback(&mut self) -> io::Result<()> {
+        let tx_id = match self.tx_id.take() {
+            Some(tx_id) => tx_id,
+            None => return Ok(()),
+        };
+
+        let records = self.wal.records()?;
+        let mut restored_files = Vec::new();
+        for (file_name, page_no, page) in Wal::undo_records(&records, |t| t == tx_id) {
+            let file_id = self
+                .files
+                .iter()
+                .position(|f| f.file_name == *file_name)
+                .unwrap();
+            self.files[file_id].pager.write_page(page_no, page)?;
+            restored_files.push(file_id);
+            if let Some(frame_id) = self.page_table.remove(&(file_id, page_no)) {
+                self.frames[frame_id].key = None;
+                self.frames[frame_id].dirty = false;
+            }
+        }
+        for file_id in restored_files {
+            self.files[file_id].pager.sync()?;
+        }
+
+        for frame in self.frames.iter_mut().filter(|f| f.dirty) {
+            self.page_table.remove(&frame.key.unwrap());
+            frame.key = None;
+            frame.dirty = false;
+        }
+
+        self.wal.discard(tx_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pager::PAGE_SIZE;
+
+    fn page_of(b: u8) -> Vec<u8> {
+        vec![b; PAGE_SIZE]
+    }
+
+    fn pool_with_file(capacity: usize) -> (tempfile::TempDir, BufferPool, FileId) {
+        let dir = tempfile::tempdir().unwrap();
+        let wal = Wal::open(&dir.path().join("wal")).unwrap();
+        let mut pool = BufferPool::new(capacity, wal);
+        let pager = Pager::create(&dir.path().join("foo.tbl")).unwrap();
+        let file_id = pool.add_file("foo.tbl", pager);
+        (dir, pool, file_id)
+    }
+
+    fn write(pool: &mut BufferPool, file_id: FileId, page_no: usize, b: u8) {
+        let frame_id = pool.fetch(file_id, page_no).unwrap();
+        pool.page_mut(frame_id).copy_from_slice(&page_of(b));
+        pool.unpin(frame_id);
+    }
+
+    fn read(pool: &mut BufferPool, file_id: FileId, page_no: usize) -> Vec<u8> {
+        let frame_id = pool.fetch(file_id, page_no).unwrap();
+        let page = pool.page(frame_id).to_vec();
+        pool.unpin(frame_id);
+        page
+    }
+
+    #[test]
+    fn test_eviction_writes_back_dirty_pages() {
+        let (_dir, mut pool, file_id) = pool_with_file(2);
+
+        for page_no in 0..10 {
+            write(&mut pool, file_id, page_no, page_no as u8);
+        }
+
+        assert_eq!(2, pool.frames.len());
+        for page_no in 0..10 {
+            assert_eq!(page_of(page_no as u8), read(&mut pool, file_id, page_no));
+        }
+    }
+
+    #[test]
+    fn test_pinned_pages_are_not_evicted() {
+        let (_dir, mut pool, file_id) = pool_with_file(2);
+
+        let pinned = pool.fetch(file_id, 0).unwrap();
+        pool.page_mut(pinned).copy_from_slice(&page_of(9));
+        for page_no in 1..5 {
+            write(&mut pool, file_id, page_no, 1);
+        }
+        assert_eq!(page_of(9), pool.page(pinned));
+
+        let _also_pinned = pool.fetch(file_id, 1).unwrap();
+        assert!(pool.fetch(file_id, 2).is_err());
+    }
+
+    #[test]
+    fn test_clock_prefers_unreferenced_pages() {
+        let (_dir, mut pool, file_id) = pool_with_file(3);
+
+        for page_no in 0..3 {
+            read(&mut pool, file_id, page_no);
+        }
+        // The first sweep clears every reference bit and evicts page 0, so
+        // touching page 1 again should leave page 2 as the next victim.
+        read(&mut pool, file_id, 3);
+        read(&mut pool, file_id, 1);
+        read(&mut pool, file_id, 4);
+
+        assert!(pool.page_table.contains_key(&(file_id, 1)));
+        assert!(!pool.page_table.contains_key(&(file_id, 2)));
+    }
+
+    #[test]
+    fn test_rollback_restores_evicted_pages() {
+        let (_dir, mut pool, file_id) = pool_with_file(1);
+        write(&mut pool, file_id, 0, 1);
+        write(&mut pool, file_id, 1, 1);
+        pool.flush_file(file_id).unwrap();
+
+        pool.begin().unwrap();
+        write(&mut pool, file_id, 0, 2);
+        write(&mut pool, file_id, 1, 2);
+        write(&mut pool, file_id, 2, 2);
+        pool.rollback().unwrap();
+
+        assert_eq!(page_of(1), read(&mut pool, file_id, 0));
+        assert_eq!(page_of(1), read(&mut pool, file_id, 1));
+        assert_eq!(page_of(0), read(&mut pool, file_id, 2));
+        assert!(pool.wal.records().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rollback_keeps_committed_records() {
+        let (_dir, mut pool, file_id) = pool_with_file(4);
+        pool.begin().unwrap();
+        write(&mut pool, file_id, 0, 1);
+        pool.log_commit().unwrap();
+
+        // The committed page never reached its file, so the log must keep
+        // it.
+        pool.begin().unwrap();
+        write(&mut pool, file_id, 1, 2);
+        pool.rollback().unwrap();
+
+        let records = pool.wal.records().unwrap();
+        assert!(records.contains(&LogRecord::Commit { tx_id: 1 }));
+        assert!(records.iter().all(|r| r.tx_id() == 1));
+    }
+}
@@ -0,0 +1,287 @@
+use std::{io, path::Path};
+
+use crate::{
+    encoding::{Decoder, Encoder},
+    pager::{Pager, PAGE_SIZE},
+    table::{ColumnSpec, ColumnType},
+};
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct CatalogEntry {
+    pub table_name: String,
+    pub file_name: String,
+    pub column_specs: Vec<ColumnSpec>,
+    pub row_count: usize,
+    pub page_count: usize,
+}
+
+/// The catalog lives in its own file. Page 0 is a header holding the length
+/// of the encoded entries, which are written across the pages that follow it.
+pub struct Catalog {
+    pager: Pager,
+    pub entries: Vec<CatalogEntry>,
+}
+
+impl Catalog {
+    const HEADER_MAGIC: &'static [u8; 8] = b"MERLINCT";
+
+    pub fn open(path: &Path) -> io::Result<Catalog> {
+        if !path.exists() {
+            let mut catalog = Catalog {
+                pager: Pager::create(path)?,
+                entries: Vec::new(),
+            };
+            catalog.save()?;
+            return Ok(catalog);
+        }
+
+        let mut pager = Pager::open(path)?;
+        let header = pager.read_page(0)?;
+
+        if &header[0..8] != Catalog::HEADER_MAGIC {
+            return Err(io::Error::other(format!(
+                "{} is not a catalog file",
+                path.display()
+            )));
+        }
+
+        let body_len = u64::from_be_bytes(header[8..16].try_into().unwrap()) as usize;
+        let mut body = Vec::with_capacity(body_len);
+        let mut page_no = 1;
+        while body.len() < body_len {
+            body.extend(pager.read_page(page_no)?);
+            page_no += 1;
+        }
+        body.truncate(body_len);
+
+        let entries = decode_entries(&mut Decoder::new(&body))?;
+        Ok(Catalog { pager, entries })
+    }
+
+    pub fn get(&self, table_name: &str) -> Option<&CatalogEntry> {
+        self.entries.iter().find(|e| e.table_name == table_name)
+    }
+
+    pub fn get_mut(&mut self, table_name: &str) -> Option<&mut CatalogEntry> {
+        self.entries.iter_mut().find(|e| e.table_name == table_name)
+    }
+
+    pub fn save(&mut self) -> io::Result<()> {
+        for (page_no, page) in self.pages().iter().enumerate() {
+            self.pager.write_page(page_no, page)?;
+        }
+        self.pager.sync()
+    }
+
+    /// Encodes the catalog into the header page followed by its body pages.
+    pub fn pages(&self) -> Vec<Vec<u8>> {
+        let mut encoder = Encoder::default();
+        encode_entries(&mut encoder, &self.entries);
+        let body = encoder.bytes;
+
+        let mut header = vec![0; PAGE_SIZE];
+        header[0..8].copy_from_slice(Catalog::HEADER_MAGIC);
+        header[8..16].copy_from_slice(&(body.len() as u64).to_be_bytes());
+
+        let mut pages = vec![header];
+        for chunk in body.chunks(PAGE_SIZE) {
+            let mut page = vec![0; PAGE_SIZE];
+            page[0..chunk.len()].copy_from_slice(chunk);
+            pages.push(page);
+        }
+        pages
+    }
+}
+
+fn encode_column_type(encoder: &mut Encoder, column_type: &ColumnType) {
+    match column_type {
+        ColumnType::Varchar { max_len } => {
+            encoder.u8(0);
+            encoder.u64(*max_len as u64);
+        }
+        ColumnType::Number => encoder.u8(1),
+        ColumnType::Boolean => encoder.u8(2),
+        ColumnType::SmallInt => encoder.u8(3),
+        ColumnType::Int => encoder.u8(4),
+        ColumnType::Double => encoder.u8(5),
+        ColumnType::Decimal { precision, scale } => {
+            encoder.u8(6);
+            encoder.u8(*precision);
+            encoder.u8(*scale);
+        }
+        ColumnType::Date => encoder.u8(7),
+        ColumnType::Time => encoder.u8(8),
+        ColumnType::Timestamp => encoder.u8(9),
+        ColumnType::Interval => encoder.u8(10),
+        ColumnType::Text => encoder.u8(11),
+        ColumnType::Blob => encoder.u8(12),
+    }
+}
+
+fn encode_entries(encoder: &mut Encoder, entries: &[CatalogEntry]) {
+    encoder.u64(entries.len() as u64);
+    for entry in entries {
+        encoder.string(&entry.table_name);
+        encoder.string(&entry.file_name);
+        encoder.u64(entry.row_count as u64);
+        encoder.u64(entry.page_count as u64);
+        encoder.u64(entry.column_specs.len() as u64);
+        for cs in entry.column_specs.iter() {
+            encoder.string(&cs.column_name);
+            encode_column_type(encoder, &cs.column_type);
+            encoder.u8(cs.not_null as u8);
+        }
+    }
+}
+
+fn decode_column_type(decoder: &mut Decoder) -> io::Result<ColumnType> {
+    match decoder.u8()? {
+        0 => Ok(ColumnType::Varchar {
+            max_len: decoder.u64()? as usize,
+        }),
+        1 => Ok(ColumnType::Number),
+        2 => Ok(ColumnType::Boolean),
+        3 => Ok(ColumnType::SmallInt),
+        4 => Ok(ColumnType::Int),
+        5 => Ok(ColumnType::Double),
+        6 => Ok(ColumnType::Decimal {
+            precision: decoder.u8()?,
+            scale: decoder.u8()?,
+        }),
+        7 => Ok(ColumnType::Date),
+        8 => Ok(ColumnType::Time),
+        9 => Ok(ColumnType::Timestamp),
+        10 => Ok(ColumnType::Interval),
+        11 => Ok(ColumnType::Text),
+        12 => Ok(ColumnType::Blob),
+        tag => Err(io::Error::other(format!(
+            "Unknown column type {} in catalog",
+            tag
+        ))),
+    }
+}
+
+fn decode_entries(decoder: &mut Decoder) -> io::Result<Vec<CatalogEntry>> {
+    let count = decoder.u64()?;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let table_name = decoder.string()?;
+        let file_name = decoder.string()?;
+        let row_count = decoder.u64()? as usize;
+        let page_count = decoder.u64()? as usize;
+        let column_count = decoder.u64()?;
+        let mut column_specs = Vec::new();
+        for _ in 0..column_count {
+            column_specs.push(ColumnSpec {
+                column_name: decoder.string()?,
+                column_type: decode_column_type(decoder)?,
+                not_null: decoder.u8()? == 1,
+            });
+        }
+        entries.push(CatalogEntry {
+            table_name,
+            file_name,
+            column_specs,
+            row_count,
+            page_count,
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catalog");
+
+        let entries: Vec<CatalogEntry> = (0..200)
+            .map(|i| CatalogEntry {
+                table_name: format!("table{}", i),
+                file_name: format!("table{}.tbl", i),
+                column_specs: vec![
+                    ColumnSpec {
+                        column_name: "foo".to_string(),
+                        column_type: ColumnType::Varchar { max_len: 12 },
+                        not_null: false,
+                    },
+                    ColumnSpec {
+                        column_name: "bar".to_string(),
+                        column_type: ColumnType::Number,
+                        not_null: false,
+                    },
+                    ColumnSpec {
+                        column_name: "baz".to_string(),
+                        column_type: ColumnType::Boolean,
+                        not_null: false,
+                    },
+                    ColumnSpec {
+                        column_name: "qux".to_string(),
+                        column_type: ColumnType::SmallInt,
+                        not_null: true,
+                    },
+                    ColumnSpec {
+                        column_name: "quux".to_string(),
+                        column_type: ColumnType::Int,
+                        not_null: false,
+                    },
+                    ColumnSpec {
+                        column_name: "corge".to_string(),
+                        column_type: ColumnType::Double,
+                        not_null: false,
+                    },
+                    ColumnSpec {
+                        column_name: "grault".to_string(),
+                        column_type: ColumnType::Decimal { precision: 12, scale: 4 },
+                        not_null: false,
+                    },
+                    ColumnSpec {
+                        column_name: "garply".to_string(),
+                        column_type: ColumnType::Date,
+                        not_null: false,
+                    },
+                    ColumnSpec {
+                        column_name: "waldo".to_string(),
+                        column_type: ColumnType::Time,
+                        not_null: false,
+                    },
+                    ColumnSpec {
+                        column_name: "fred".to_string(),
+                        column_type: ColumnType::Timestamp,
+                        not_null: true,
+                    },
+                    ColumnSpec {
+                        column_name: "plugh".to_string(),
+                        column_type: ColumnType::Interval,
+                        not_null: false,
+                    },
+                    ColumnSpec {
+                        column_name: "xyzzy".to_string(),
+                        column_type: ColumnType::Text,
+                        not_null: true,
+                    },
+                    ColumnSpec {
+                        column_name: "thud".to_string(),
+                        column_type: ColumnType::Blob,
+                        not_null: false,
+                    },
+                ],
+                row_count: i * 10,
+                page_count: i,
+            })
+            .collect();
+
+        let mut catalog = Catalog::open(&path).unwrap();
+        assert!(catalog.entries.is_empty());
+        catalog.entries = entries.clone();
+        catalog.save().unwrap();
+        drop(catalog);
+
+        let catalog = Catalog::open(&path).unwrap();
+        assert_eq!(entries, catalog.entries);
+        assert_eq!(Some(&entries[42]), catalog.get("table42"));
+    }
+}
@@ -1,10 +1,54 @@
 use std::{
     io::{stdin, stdout, Write},
+    path::PathBuf,
 };
 
 use console::Style;
 
-use crate::table::{self, Table};
+use crate::{
+    expression::DataType,
+    mapper::InsertValueMapper,
+    sql_parser::{
+        AggregateFunction, ArithmeticOperator, ComparisonOperator, Expression, InsertValue, PatternKind, SetOperator,
+        WindowFunction,
+    },
+    table::{self, ColumnSpec},
+};
+
+pub struct Args {
+    pub data_dir: PathBuf,
+    pub buffer_pool_pages: usize,
+}
+
+impl Args {
+    const DEFAULT_DATA_DIR: &'static str = "merlin_data";
+    const DEFAULT_BUFFER_POOL_PAGES: usize = 1024;
+
+    /// Parses `[data_dir] [--buffer-pool-pages n]`.
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+        let mut data_dir = None;
+        let mut buffer_pool_pages = Args::DEFAULT_BUFFER_POOL_PAGES;
+
+        while let Some(arg) = args.next() {
+            if arg == "--buffer-pool-pages" {
+                buffer_pool_pages = args
+                    .next()
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .filter(|n| *n > 0)
+                    .ok_or("--buffer-pool-pages expects a number of pages greater than 0")?;
+            } else if data_dir.is_none() {
+                data_dir = Some(PathBuf::from(arg));
+            } else {
+                return Err(format!("Unexpected argument '{}'", arg));
+            }
+        }
+
+        Ok(Args {
+            data_dir: data_dir.unwrap_or(PathBuf::from(Args::DEFAULT_DATA_DIR)),
+            buffer_pool_pages,
+        })
+    }
+}
 
 pub fn print_wizard() {
     println!("               _");
@@ -44,7 +88,7 @@ pub fn read_input() -> String {
 
         if res.ends_with("\\") {
             res.pop();
-            res.push_str("\n");
+            res.push('\n');
             print!("      {} ", arrow.apply_to("🡆"));
             stdout().flush().unwrap();
         } else {
@@ -82,22 +126,44 @@ pub fn print_insert_success(table_name: &String, row_count: usize) {
     println!("{}. Table {} has {} row{}.", success.apply_to("Insert successful"), name_style.apply_to(table_name), row_count, plural);
 }
 
-pub fn print_table(name: &String, table: &Table) {
+pub fn print_delete_success(table_name: &String, deleted: usize, row_count: usize) {
+    let success: Style = Style::new().green().bold();
+    let name_style: Style = Style::new().yellow().bold();
+    let plural = if deleted == 1 { "" } else { "s" };
+    println!("{}. Deleted {} row{}, table {} has {} left.", success.apply_to("Delete successful"), deleted, plural, name_style.apply_to(table_name), row_count);
+}
+
+pub fn print_update_success(table_name: &String, updated: usize) {
+    let success: Style = Style::new().green().bold();
+    let name_style: Style = Style::new().yellow().bold();
+    let plural = if updated == 1 { "" } else { "s" };
+    println!("{}. Updated {} row{} in table {}.", success.apply_to("Update successful"), updated, plural, name_style.apply_to(table_name));
+}
+
+pub fn print_row_count(row_count: usize) {
+    let plural = if row_count == 1 { "" } else { "s" };
+    println!("{} row{}", row_count, plural);
+}
+
+pub fn print_table(name: &String, column_specs: &[ColumnSpec]) {
     let name_style: Style = Style::new().yellow().bold();
     println!("{}", name_style.apply_to(name));
 
     let header = vec![ "Field".to_string(), "Type".to_string() ];
 
-    let rows: Vec<Vec<String>> = table.column_specs.iter().map(|cs| {
+    let rows: Vec<Vec<String>> = column_specs.iter().map(|cs| {
         let field = cs.column_name.clone();
-        let field_type = format!("{}", cs.column_type);
+        let field_type = match cs.not_null {
+            true => format!("{} not null", cs.column_type),
+            false => format!("{}", cs.column_type),
+        };
         vec![ field, field_type ]
     }).collect();
 
     print_string_table(&header, &rows);
 }
 
-pub fn print_string_table(header: &Vec<String>, rows: &Vec<Vec<String>>) {
+pub fn print_string_table(header: &[String], rows: &[Vec<String>]) {
     const PADDING_H: usize = 1;
 
     let column_widths: Vec<usize> = header
@@ -133,7 +199,7 @@ pub fn print_string_table(header: &Vec<String>, rows: &Vec<Vec<String>>) {
         }
         let header_text = header
             .get(i)
-            .map(|k| k.clone())
+            .cloned()
             .unwrap_or_else(|| " ".repeat(*width));
         print!("{}", header_text);
         for _ in 0..PADDING_H + width - header_text.len() {
@@ -156,7 +222,7 @@ pub fn print_string_table(header: &Vec<String>, rows: &Vec<Vec<String>>) {
     }
 
 
-    for (i, row) in rows.iter().enumerate() {
+    for row in rows.iter() {
         println!();
         for (j, width) in column_widths.iter().enumerate() {
             print!("┃");
@@ -165,7 +231,7 @@ pub fn print_string_table(header: &Vec<String>, rows: &Vec<Vec<String>>) {
             }
             let value = row
                 .get(j)
-                .map(|s| s.clone())
+                .cloned()
                 .unwrap_or_else(|| " ".repeat(*width));
 
             print!("{}", value);
@@ -198,7 +264,17 @@ impl std::fmt::Display for table::ColumnType {
         match self {
             table::ColumnType::Varchar { max_len } => write!(f, "Varchar({})", max_len)?,
             table::ColumnType::Number => write!(f, "number")?,
+            table::ColumnType::SmallInt => write!(f, "smallint")?,
+            table::ColumnType::Int => write!(f, "int")?,
+            table::ColumnType::Double => write!(f, "double")?,
+            table::ColumnType::Decimal { precision, scale } => write!(f, "decimal({}, {})", precision, scale)?,
             table::ColumnType::Boolean => write!(f, "boolean")?,
+            table::ColumnType::Date => write!(f, "date")?,
+            table::ColumnType::Time => write!(f, "time")?,
+            table::ColumnType::Timestamp => write!(f, "timestamp")?,
+            table::ColumnType::Interval => write!(f, "interval")?,
+            table::ColumnType::Text => write!(f, "text")?,
+            table::ColumnType::Blob => write!(f, "blob")?,
         }
         Ok(())
     }
@@ -209,8 +285,239 @@ impl std::fmt::Display for table::Value {
         match self {
             table::Value::Varchar { value } => write!(f, "\"{}\"", value)?,
             table::Value::Number { value } => write!(f, "{}", value)?,
+            table::Value::Int { value } => write!(f, "{}", value)?,
+            table::Value::Decimal { value } => write!(f, "{}", value)?,
+            // Debug keeps the point, so a whole double still looks like one.
+            table::Value::Double { value } => write!(f, "{:?}", value)?,
             table::Value::Boolean { value } => write!(f, "{}", value)?,
+            table::Value::Date { value } => write!(f, "{}", value)?,
+            table::Value::Time { value } => write!(f, "{}", value)?,
+            table::Value::Timestamp { value } => write!(f, "{}", value)?,
+            table::Value::Interval { value } => write!(f, "{}", value)?,
+            table::Value::Blob { value } => {
+                write!(f, "x\"")?;
+                for b in value {
+                    write!(f, "{:02x}", b)?;
+                }
+                write!(f, "\"")?
+            }
+            table::Value::Null => write!(f, "null")?,
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+impl std::fmt::Display for ComparisonOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ComparisonOperator::Equal => write!(f, "="),
+            ComparisonOperator::NotEqual => write!(f, "<>"),
+            ComparisonOperator::LessThan => write!(f, "<"),
+            ComparisonOperator::LessThanOrEqual => write!(f, "<="),
+            ComparisonOperator::GreaterThan => write!(f, ">"),
+            ComparisonOperator::GreaterThanOrEqual => write!(f, ">="),
+        }
+    }
+}
+
+impl std::fmt::Display for ArithmeticOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArithmeticOperator::Add => write!(f, "+"),
+            ArithmeticOperator::Subtract => write!(f, "-"),
+            ArithmeticOperator::Multiply => write!(f, "*"),
+            ArithmeticOperator::Divide => write!(f, "/"),
+            ArithmeticOperator::Concat => write!(f, "||"),
+        }
+    }
+}
+
+impl std::fmt::Display for DataType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataType::Text => write!(f, "text"),
+            DataType::Number => write!(f, "number"),
+            DataType::Boolean => write!(f, "boolean"),
+            DataType::Date => write!(f, "date"),
+            DataType::Time => write!(f, "time"),
+            DataType::Timestamp => write!(f, "timestamp"),
+            DataType::Interval => write!(f, "interval"),
+            DataType::Blob => write!(f, "blob"),
+        }
+    }
+}
+
+impl std::fmt::Display for AggregateFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AggregateFunction::Count => write!(f, "count"),
+            AggregateFunction::Sum => write!(f, "sum"),
+            AggregateFunction::Min => write!(f, "min"),
+            AggregateFunction::Max => write!(f, "max"),
+            AggregateFunction::Avg => write!(f, "avg"),
+        }
+    }
+}
+
+impl std::fmt::Display for WindowFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WindowFunction::RowNumber => write!(f, "row_number"),
+            WindowFunction::Rank => write!(f, "rank"),
+            WindowFunction::DenseRank => write!(f, "dense_rank"),
+            WindowFunction::Lag => write!(f, "lag"),
+            WindowFunction::Lead => write!(f, "lead"),
+            WindowFunction::Aggregate(function) => write!(f, "{}", function),
+        }
+    }
+}
+
+impl std::fmt::Display for SetOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SetOperator::Union => write!(f, "union"),
+            SetOperator::Intersect => write!(f, "intersect"),
+            SetOperator::Except => write!(f, "except"),
+        }
+    }
+}
+
+/// Writes an expression back out as SQL, which is also how a computed
+/// column is named. Operands that are operations themselves are
+/// parenthesised.
+impl std::fmt::Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operand = |e: &Expression| match e {
+            Expression::Comparison { .. }
+            | Expression::Arithmetic { .. }
+            | Expression::And { .. }
+            | Expression::Or { .. } => format!("({})", e),
+            _ => format!("{}", e),
+        };
+
+        match self {
+            Expression::Column {
+                table_name: Some(table_name),
+                column_name,
+            } => write!(f, "{}.{}", table_name, column_name),
+            Expression::Column {
+                table_name: None,
+                column_name,
+            } => write!(f, "{}", column_name),
+            Expression::Literal { value } => match InsertValueMapper::sql_parser_to_table(value) {
+                value @ table::Value::Date { .. } => write!(f, "date \"{}\"", value),
+                value @ table::Value::Time { .. } => write!(f, "time \"{}\"", value),
+                value @ table::Value::Timestamp { .. } => write!(f, "timestamp \"{}\"", value),
+                value @ table::Value::Interval { .. } => write!(f, "interval \"{}\"", value),
+                value => write!(f, "{}", value),
+            },
+            Expression::Comparison { left, operator, right } => {
+                write!(f, "{} {} {}", operand(left), operator, operand(right))
+            }
+            Expression::Arithmetic { left, operator, right } => {
+                write!(f, "{} {} {}", operand(left), operator, operand(right))
+            }
+            Expression::And { left, right } => write!(f, "{} and {}", operand(left), operand(right)),
+            Expression::Or { left, right } => write!(f, "{} or {}", operand(left), operand(right)),
+            Expression::Not { expression } => write!(f, "not {}", operand(expression)),
+            Expression::Aggregate { function, argument } => match argument {
+                Some(argument) => write!(f, "{}({})", function, argument),
+                None => write!(f, "{}(*)", function),
+            },
+            Expression::Function { name, arguments } if name == "extract" && arguments.len() == 2 => {
+                match &arguments[0] {
+                    Expression::Literal {
+                        value: InsertValue::Varchar { value },
+                    } => write!(f, "extract({} from {})", value, arguments[1]),
+                    field => write!(f, "extract({}, {})", field, arguments[1]),
+                }
+            }
+            Expression::Function { name, arguments } => {
+                let arguments: Vec<String> = arguments.iter().map(|a| format!("{}", a)).collect();
+                write!(f, "{}({})", name, arguments.join(", "))
+            }
+            Expression::Exists { .. } => write!(f, "exists (...)"),
+            Expression::InSubquery {
+                expression, negated, ..
+            } => write!(f, "{} {}in (...)", operand(expression), if *negated { "not " } else { "" }),
+            Expression::Window {
+                function,
+                arguments,
+                partition_by,
+                order_by,
+            } => {
+                let list = |expressions: Vec<String>| expressions.join(", ");
+                let arguments = match function {
+                    WindowFunction::Aggregate(AggregateFunction::Count) if arguments.is_empty() => "*".to_string(),
+                    _ => list(arguments.iter().map(|a| format!("{}", a)).collect()),
+                };
+                let mut window = Vec::new();
+                if !partition_by.is_empty() {
+                    window.push(format!("partition by {}", list(partition_by.iter().map(|p| format!("{}", p)).collect())));
+                }
+                if !order_by.is_empty() {
+                    let keys = order_by
+                        .iter()
+                        .map(|o| format!("{}{}", o.expression, if o.ascending { "" } else { " desc" }))
+                        .collect();
+                    window.push(format!("order by {}", list(keys)));
+                }
+                write!(f, "{}({}) over ({})", function, arguments, window.join(" "))
+            }
+            Expression::PatternMatch {
+                expression,
+                kind,
+                pattern,
+                negated,
+            } => {
+                let operator = match (kind, negated) {
+                    (PatternKind::Like, false) => "like",
+                    (PatternKind::Like, true) => "not like",
+                    (PatternKind::ILike, false) => "ilike",
+                    (PatternKind::ILike, true) => "not ilike",
+                    (PatternKind::Regex, false) => "~",
+                    (PatternKind::Regex, true) => "!~",
+                };
+                write!(f, "{} {} {}", operand(expression), operator, operand(pattern))
+            }
+            Expression::InList {
+                expression,
+                list,
+                negated,
+            } => {
+                let list: Vec<String> = list.iter().map(|e| format!("{}", e)).collect();
+                let not = if *negated { "not " } else { "" };
+                write!(f, "{} {}in ({})", operand(expression), not, list.join(", "))
+            }
+            Expression::Between {
+                expression,
+                low,
+                high,
+                negated,
+            } => {
+                let not = if *negated { "not " } else { "" };
+                write!(f, "{} {}between {} and {}", operand(expression), not, operand(low), operand(high))
+            }
+            Expression::IsNull { expression, negated } => {
+                write!(f, "{} is {}null", operand(expression), if *negated { "not " } else { "" })
+            }
+            Expression::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                write!(f, "case")?;
+                if let Some(operand) = operand {
+                    write!(f, " {}", operand)?;
+                }
+                for (when, then) in branches {
+                    write!(f, " when {} then {}", when, then)?;
+                }
+                if let Some(otherwise) = otherwise {
+                    write!(f, " else {}", otherwise)?;
+                }
+                write!(f, " end")
+            }
+        }
+    }
+}
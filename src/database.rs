@@ -0,0 +1,612 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    buffer_pool::{BufferPool, SharedBufferPool},
+    catalog::{Catalog, CatalogEntry},
+    datetime::Formats,
+    table::{ColumnSpec, Row, Table, Value},
+    wal::Wal,
+};
+
+/// A data directory holding the catalog, the write-ahead log and one file per
+/// table. Every table listed in the catalog is opened up front, sharing a
+/// buffer pool of `buffer_pool_pages` pages.
+///
+/// Each statement that changes the database runs as a transaction: the pages
+/// it touched and the catalog are logged and committed to the write-ahead log
+/// before being written to their files. If the statement fails, its changes
+/// are rolled back instead.
+pub struct Database {
+    data_dir: PathBuf,
+    pub catalog: Catalog,
+    tables: HashMap<String, Table>,
+    pool: SharedBufferPool,
+}
+
+impl Database {
+    const CATALOG_FILE_NAME: &'static str = "catalog";
+    const WAL_FILE_NAME: &'static str = "wal";
+
+    pub fn open(data_dir: &Path, buffer_pool_pages: usize) -> io::Result<Database> {
+        fs::create_dir_all(data_dir)?;
+
+        let mut wal = Wal::open(&data_dir.join(Database::WAL_FILE_NAME))?;
+        wal.recover(data_dir)?;
+        let pool = BufferPool::new(buffer_pool_pages, wal).shared();
+
+        let catalog = Catalog::open(&data_dir.join(Database::CATALOG_FILE_NAME))?;
+
+        // A table whose creation never committed, say because of a crash,
+        // leaves files the catalog doesn't list, which would keep the name
+        // from being used again.
+        for dir_entry in fs::read_dir(data_dir)? {
+            let path = dir_entry?.path();
+            let is_table_file = matches!(path.extension().and_then(|e| e.to_str()), Some("tbl" | "ovf"));
+            let is_listed = catalog
+                .entries
+                .iter()
+                .any(|entry| Path::new(&entry.file_name).file_stem() == path.file_stem());
+            if is_table_file && !is_listed {
+                fs::remove_file(&path)?;
+            }
+        }
+
+        let mut tables = HashMap::new();
+        for entry in catalog.entries.iter() {
+            let table = Table::open(
+                &pool,
+                &data_dir.join(&entry.file_name),
+                &entry.column_specs,
+                entry.page_count,
+                entry.row_count,
+            )?;
+            tables.insert(entry.table_name.clone(), table);
+        }
+
+        Ok(Database {
+            data_dir: data_dir.to_path_buf(),
+            catalog,
+            tables,
+            pool,
+        })
+    }
+
+    pub fn create_table(&mut self, table_name: &str, column_specs: &[ColumnSpec]) -> io::Result<()> {
+        if self.catalog.get(table_name).is_some() {
+            return Err(io::Error::other(format!(
+                "A table named '{}' already exists.",
+                table_name
+            )));
+        }
+
+        let file_name = format!("{}.tbl", table_name);
+        let table = Table::create(&self.pool, &self.data_dir.join(&file_name), column_specs)?;
+
+        self.catalog.entries.push(CatalogEntry {
+            table_name: table_name.to_string(),
+            file_name: file_name.clone(),
+            column_specs: column_specs.to_vec(),
+            row_count: 0,
+            page_count: 0,
+        });
+        self.tables.insert(table_name.to_string(), table);
+
+        self.pool.borrow_mut().begin()?;
+        if let Err(err) = self.log_commit(table_name) {
+            self.pool.borrow_mut().rollback()?;
+            self.catalog.entries.pop();
+            self.tables.remove(table_name);
+            // Should removing the files fail, opening the database removes them.
+            let path = self.data_dir.join(&file_name);
+            let _ = fs::remove_file(path.with_extension("ovf"));
+            let _ = fs::remove_file(path);
+            return Err(err);
+        }
+        self.write_commit(table_name)
+    }
+
+    pub fn table(&self, table_name: &str) -> Option<&Table> {
+        self.tables.get(table_name)
+    }
+
+    pub fn table_mut(&mut self, table_name: &str) -> Option<&mut Table> {
+        self.tables.get_mut(table_name)
+    }
+
+    fn table_or_err(&mut self, table_name: &str) -> io::Result<&mut Table> {
+        self.tables.get_mut(table_name).ok_or(io::Error::other(format!(
+            "No table named '{}' is defined.",
+            table_name
+        )))
+    }
+
+    /// Logs the pages modified by the open transaction along with the
+    /// catalog, recording the table's new size. Once this succeeds the
+    /// transaction is committed.
+    fn log_commit(&mut self, table_name: &str) -> io::Result<()> {
+        self.update_catalog_entry(table_name);
+
+        let mut pool = self.pool.borrow_mut();
+        for (page_no, page) in self.catalog.pages().into_iter().enumerate() {
+            pool.log_page(Database::CATALOG_FILE_NAME, page_no, page)?;
+        }
+        pool.log_commit()
+    }
+
+    /// Writes a committed transaction's pages and the catalog to their files,
+    /// after which the log is no longer needed.
+    fn write_commit(&mut self, table_name: &str) -> io::Result<()> {
+        self.tables.get_mut(table_name).unwrap().flush()?;
+        self.catalog.save()?;
+        self.pool.borrow_mut().checkpoint()
+    }
+
+    fn rollback(&mut self, table_name: &str) -> io::Result<()> {
+        self.pool.borrow_mut().rollback()?;
+
+        self.tables.get_mut(table_name).unwrap().rollback();
+        self.update_catalog_entry(table_name);
+        Ok(())
+    }
+
+    fn update_catalog_entry(&mut self, table_name: &str) {
+        let table = &self.tables[table_name];
+        let entry = self.catalog.get_mut(table_name).unwrap();
+        entry.row_count = table.row_count;
+        entry.page_count = table.page_count();
+    }
+
+    /// Runs `f` against the table as a single transaction.
+    fn transaction<F>(&mut self, table_name: &str, f: F) -> io::Result<usize>
+    where
+        F: FnOnce(&mut Table) -> io::Result<()>,
+    {
+        self.table_or_err(table_name)?;
+        self.pool.borrow_mut().begin()?;
+
+        let result = f(self.tables.get_mut(table_name).unwrap()).and_then(|_| self.log_commit(table_name));
+        if let Err(err) = result {
+            self.rollback(table_name)?;
+            return Err(err);
+        }
+
+        self.write_commit(table_name)?;
+        Ok(self.tables[table_name].row_count)
+    }
+
+    /// Inserts a row, returning the table's new row count.
+    pub fn insert(&mut self, table_name: &str, row: &Row) -> io::Result<usize> {
+        self.transaction(table_name, |table| table.insert(row).map(|_| ()))
+    }
+
+    /// Replaces every row for which `update` returns a new row, returning the
+    /// number of rows updated.
+    pub fn update<F>(&mut self, table_name: &str, update: F) -> io::Result<usize>
+    where
+        F: Fn(&[Value]) -> Result<Option<Row>, String>,
+    {
+        let mut updated = 0;
+        self.transaction(table_name, |table| {
+            let mut rows = Vec::new();
+            for result in table.scan() {
+                let (row_id, row) = result?;
+                if let Some(row) = update(&row.into_values()).map_err(io::Error::other)? {
+                    rows.push((row_id, row));
+                }
+            }
+
+            for (row_id, row) in rows.iter() {
+                table.update(*row_id, row)?;
+            }
+            updated = rows.len();
+            Ok(())
+        })?;
+        Ok(updated)
+    }
+
+    /// Deletes every row matching `predicate`, returning the number of rows
+    /// deleted and the table's new row count.
+    pub fn delete<F>(&mut self, table_name: &str, predicate: F) -> io::Result<(usize, usize)>
+    where
+        F: Fn(&[Value]) -> Result<bool, String>,
+    {
+        let mut deleted = 0;
+        let row_count = self.transaction(table_name, |table| {
+            let mut row_ids = Vec::new();
+            for result in table.scan() {
+                let (row_id, row) = result?;
+                if predicate(&row.into_values()).map_err(io::Error::other)? {
+                    row_ids.push(row_id);
+                }
+            }
+
+            for row_id in row_ids.iter() {
+                table.delete(*row_id)?;
+            }
+            deleted = row_ids.len();
+            Ok(())
+        })?;
+        Ok((deleted, row_count))
+    }
+
+    /// Imports a CSV file, returning the table's new row count. Either every
+    /// row is imported or none are.
+    pub fn csv_import(
+        &mut self,
+        table_name: &str,
+        csv_path: &String,
+        column_mapping: &HashMap<String, String>,
+        with_truncate: bool,
+        formats: &Formats,
+    ) -> io::Result<usize> {
+        self.transaction(table_name, |table| {
+            table.csv_import(csv_path, column_mapping, with_truncate, formats)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::table::{ColumnType, RowId};
+
+    #[test]
+    fn test_reopen_database() {
+        let column_specs = vec![
+            ColumnSpec {
+                column_name: "name".to_string(),
+                column_type: ColumnType::Varchar { max_len: 10 },
+                not_null: true,
+            },
+            ColumnSpec {
+                column_name: "age".to_string(),
+                column_type: ColumnType::Number,
+                not_null: false,
+            },
+        ];
+        let column_values = HashMap::from([
+            (
+                "name".to_string(),
+                Value::Varchar {
+                    value: "Martin".to_string(),
+                },
+            ),
+            ("age".to_string(), Value::Number { value: 35 }),
+        ]);
+        let row = Row::new(&column_values, &column_specs).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut database = Database::open(dir.path(), 16).unwrap();
+        database.create_table("person", &column_specs).unwrap();
+        assert_eq!(1, database.insert("person", &row).unwrap());
+        assert!(database.create_table("person", &column_specs).is_err());
+        drop(database);
+
+        let database = Database::open(dir.path(), 16).unwrap();
+        let entry = database.catalog.get("person").unwrap();
+        assert_eq!(column_specs, entry.column_specs);
+        assert_eq!(1, entry.row_count);
+        assert_eq!(1, entry.page_count);
+        assert_eq!(Some(row), database.table("person").unwrap().get(RowId { page: 0, slot: 0 }).unwrap());
+    }
+
+    #[test]
+    fn test_failed_csv_import_is_rolled_back() {
+        let column_specs = vec![
+            ColumnSpec {
+                column_name: "name".to_string(),
+                column_type: ColumnType::Varchar { max_len: 10 },
+                not_null: false,
+            },
+            ColumnSpec {
+                column_name: "age".to_string(),
+                column_type: ColumnType::Number,
+                not_null: false,
+            },
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let csv_path = dir.path().join("people.csv");
+        fs::write(&csv_path, "name,age\nMartin,35\nLaura,thirty four\n").unwrap();
+        let column_mapping = HashMap::from([
+            ("name".to_string(), "name".to_string()),
+            ("age".to_string(), "age".to_string()),
+        ]);
+
+        let mut database = Database::open(dir.path(), 16).unwrap();
+        database.create_table("person", &column_specs).unwrap();
+        let result = database.csv_import(
+            "person",
+            &csv_path.to_str().unwrap().to_string(),
+            &column_mapping,
+            false,
+            &Formats::default(),
+        );
+
+        assert!(result.is_err());
+        assert_eq!(0, database.table("person").unwrap().row_count);
+        assert_eq!(0, database.catalog.get("person").unwrap().row_count);
+    }
+
+    #[test]
+    fn test_csv_import_nulls() {
+        let column_specs = vec![
+            ColumnSpec {
+                column_name: "name".to_string(),
+                column_type: ColumnType::Varchar { max_len: 10 },
+                not_null: true,
+            },
+            ColumnSpec {
+                column_name: "age".to_string(),
+                column_type: ColumnType::Number,
+                not_null: false,
+            },
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let csv_path = dir.path().join("people.csv");
+        fs::write(&csv_path, "name,age\nMartin,\n,35\n").unwrap();
+        let column_mapping = HashMap::from([
+            ("name".to_string(), "name".to_string()),
+            ("age".to_string(), "age".to_string()),
+        ]);
+
+        let mut database = Database::open(dir.path(), 16).unwrap();
+        database.create_table("person", &column_specs).unwrap();
+        let path = csv_path.to_str().unwrap().to_string();
+        assert_eq!(2, database.csv_import("person", &path, &column_mapping, false, &Formats::default()).unwrap());
+
+        // Only a nullable column reads an empty field as null.
+        let rows: Vec<Vec<Value>> = database
+            .table("person")
+            .unwrap()
+            .scan()
+            .map(|r| r.unwrap().1.into_values())
+            .collect();
+        let varchar = |value: &str| Value::Varchar {
+            value: value.to_string(),
+        };
+        assert_eq!(
+            vec![
+                vec![varchar("Martin"), Value::Null],
+                vec![varchar(""), Value::Number { value: 35 }]
+            ],
+            rows
+        );
+    }
+
+    #[test]
+    fn test_csv_import_date_formats() {
+        let column_specs = vec![
+            ColumnSpec {
+                column_name: "day".to_string(),
+                column_type: ColumnType::Date,
+                not_null: false,
+            },
+            ColumnSpec {
+                column_name: "at".to_string(),
+                column_type: ColumnType::Timestamp,
+                not_null: false,
+            },
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let csv_path = dir.path().join("charts.csv");
+        fs::write(&csv_path, "day,at
+31/01/2017,2017-01-31 08:15:00
+1/2/2017,2017-02-01T20:00
+").unwrap();
+        let column_mapping = HashMap::from([
+            ("day".to_string(), "day".to_string()),
+            ("at".to_string(), "at".to_string()),
+        ]);
+        let formats = Formats {
+            date: Some("%d/%m/%Y".to_string()),
+            ..Formats::default()
+        };
+
+        let mut database = Database::open(dir.path(), 16).unwrap();
+        database.create_table("chart", &column_specs).unwrap();
+        let path = csv_path.to_str().unwrap().to_string();
+        assert!(database.csv_import("chart", &path, &column_mapping, false, &Formats::default()).is_err());
+        assert_eq!(2, database.csv_import("chart", &path, &column_mapping, false, &formats).unwrap());
+
+        let rows: Vec<String> = database
+            .table("chart")
+            .unwrap()
+            .scan()
+            .map(|r| {
+                let (_, row) = r.unwrap();
+                format!("{} {}", row.values[0].0, row.values[1].0)
+            })
+            .collect();
+        assert_eq!(vec!["2017-01-31 2017-01-31 08:15:00", "2017-02-01 2017-02-01 20:00:00"], rows);
+    }
+
+    #[test]
+    fn test_csv_import_larger_than_buffer_pool() {
+        let column_specs = vec![
+            ColumnSpec {
+                column_name: "name".to_string(),
+                column_type: ColumnType::Varchar { max_len: 100 },
+                not_null: false,
+            },
+            ColumnSpec {
+                column_name: "age".to_string(),
+                column_type: ColumnType::Number,
+                not_null: false,
+            },
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let csv_path = dir.path().join("people.csv");
+        let mut csv = "name,age\n".to_string();
+        for i in 0..1000 {
+            csv.push_str(&format!("person {},{}\n", i, i));
+        }
+        fs::write(&csv_path, &csv).unwrap();
+        let bad_csv_path = dir.path().join("bad_people.csv");
+        fs::write(&bad_csv_path, csv + "someone,unknown\n").unwrap();
+        let column_mapping = HashMap::from([
+            ("name".to_string(), "name".to_string()),
+            ("age".to_string(), "age".to_string()),
+        ]);
+
+        let mut database = Database::open(dir.path(), 2).unwrap();
+        database.create_table("person", &column_specs).unwrap();
+        let path = |p: &Path| p.to_str().unwrap().to_string();
+        assert_eq!(
+            1000,
+            database.csv_import("person", &path(&csv_path), &column_mapping, false, &Formats::default()).unwrap()
+        );
+        assert!(database
+            .csv_import("person", &path(&bad_csv_path), &column_mapping, false, &Formats::default())
+            .is_err());
+        drop(database);
+
+        let database = Database::open(dir.path(), 2).unwrap();
+        let table = database.table("person").unwrap();
+        assert_eq!(1000, table.row_count);
+        let rows: Vec<Row> = table.scan().map(|r| r.unwrap().1).collect();
+        for i in [0, 500, 999] {
+            assert_eq!(Value::Number { value: i as u64 }, rows[i].values[1].0);
+        }
+    }
+
+    #[test]
+    fn test_uncommitted_table_files_are_removed() {
+        let column_specs = vec![ColumnSpec {
+            column_name: "notes".to_string(),
+            column_type: ColumnType::Text,
+            not_null: false,
+        }];
+
+        let dir = tempfile::tempdir().unwrap();
+        let database = Database::open(dir.path(), 16).unwrap();
+
+        // Simulate a crash after the table's files were created, but before
+        // the catalog listing it was committed.
+        Table::create(&database.pool, &dir.path().join("person.tbl"), &column_specs).unwrap();
+        assert!(dir.path().join("person.ovf").exists());
+        drop(database);
+
+        let mut database = Database::open(dir.path(), 16).unwrap();
+        assert!(!dir.path().join("person.tbl").exists());
+        assert!(!dir.path().join("person.ovf").exists());
+        database.create_table("person", &column_specs).unwrap();
+        drop(database);
+
+        // Files of committed tables are kept.
+        let database = Database::open(dir.path(), 16).unwrap();
+        assert!(database.table("person").is_some());
+        assert!(dir.path().join("person.tbl").exists());
+        assert!(dir.path().join("person.ovf").exists());
+    }
+
+    #[test]
+    fn test_committed_work_is_recovered() {
+        let column_specs = vec![ColumnSpec {
+            column_name: "age".to_string(),
+            column_type: ColumnType::Number,
+            not_null: false,
+        }];
+        let column_values = HashMap::from([("age".to_string(), Value::Number { value: 35 })]);
+        let row = Row::new(&column_values, &column_specs).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut database = Database::open(dir.path(), 16).unwrap();
+        database.create_table("person", &column_specs).unwrap();
+
+        // Simulate a crash after the commit record reached the log, but
+        // before any page reached the data files.
+        database.pool.borrow_mut().begin().unwrap();
+        database.table_mut("person").unwrap().insert(&row).unwrap();
+        database.log_commit("person").unwrap();
+        drop(database);
+
+        let database = Database::open(dir.path(), 16).unwrap();
+        assert_eq!(1, database.catalog.get("person").unwrap().row_count);
+        assert_eq!(Some(row), database.table("person").unwrap().get(RowId { page: 0, slot: 0 }).unwrap());
+    }
+
+    #[test]
+    fn test_delete() {
+        let column_specs = vec![ColumnSpec {
+            column_name: "age".to_string(),
+            column_type: ColumnType::Number,
+            not_null: false,
+        }];
+        let row = |i| {
+            let column_values = HashMap::from([("age".to_string(), Value::Number { value: i })]);
+            Row::new(&column_values, &column_specs).unwrap()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut database = Database::open(dir.path(), 16).unwrap();
+        database.create_table("person", &column_specs).unwrap();
+        for i in 0..10 {
+            database.insert("person", &row(i)).unwrap();
+        }
+
+        let is_even = |values: &[Value]| match values[0] {
+            Value::Number { value } => Ok(value % 2 == 0),
+            _ => Err("Not a number".to_string()),
+        };
+        assert_eq!((5, 5), database.delete("person", is_even).unwrap());
+        assert!(database.delete("person", |_| Err("Oops".to_string())).is_err());
+        drop(database);
+
+        let database = Database::open(dir.path(), 16).unwrap();
+        let entry = database.catalog.get("person").unwrap();
+        assert_eq!(1, entry.page_count);
+        assert_eq!(5, entry.row_count);
+        let ages: Vec<Value> = database
+            .table("person")
+            .unwrap()
+            .scan()
+            .map(|r| r.unwrap().1.into_values().remove(0))
+            .collect();
+        assert_eq!((1..10).step_by(2).map(|value| Value::Number { value }).collect::<Vec<Value>>(), ages);
+    }
+
+    #[test]
+    fn test_update() {
+        let column_specs = vec![ColumnSpec {
+            column_name: "age".to_string(),
+            column_type: ColumnType::Number,
+            not_null: false,
+        }];
+        let row = |value| {
+            let column_values = HashMap::from([("age".to_string(), Value::Number { value })]);
+            Row::new(&column_values, &column_specs).unwrap()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut database = Database::open(dir.path(), 16).unwrap();
+        database.create_table("person", &column_specs).unwrap();
+        for i in 0..4 {
+            database.insert("person", &row(i)).unwrap();
+        }
+
+        let double_odd = |values: &[Value]| match values[0] {
+            Value::Number { value } if value % 2 == 1 => Ok(Some(row(value * 2))),
+            _ => Ok(None),
+        };
+        assert_eq!(2, database.update("person", double_odd).unwrap());
+
+        // A failure part way through leaves every row as it was.
+        let fail_on_last = |values: &[Value]| match values[0] {
+            Value::Number { value: 6 } => Err("Oops".to_string()),
+            _ => Ok(Some(row(0))),
+        };
+        assert!(database.update("person", fail_on_last).is_err());
+        drop(database);
+
+        let database = Database::open(dir.path(), 16).unwrap();
+        let ages: Vec<Row> = database.table("person").unwrap().scan().map(|r| r.unwrap().1).collect();
+        assert_eq!(vec![row(0), row(2), row(2), row(6)], ages);
+    }
+}
@@ -0,0 +1,747 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+const MICROS_PER_SECOND: i64 = 1_000_000;
+const MICROS_PER_MINUTE: i64 = 60 * MICROS_PER_SECOND;
+const MICROS_PER_HOUR: i64 = 60 * MICROS_PER_MINUTE;
+const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+/// How many days a month counts as when intervals are compared.
+const DAYS_PER_MONTH: i64 = 30;
+
+/// A calendar date between the years 1 and 9999, held as the number of days
+/// since 1970-01-01.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Date {
+    days: i32,
+}
+
+/// A time of day, held as microseconds since midnight.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Time {
+    micros: i64,
+}
+
+/// A date and time of day, without a time zone, held as microseconds since
+/// 1970-01-01 00:00:00.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Timestamp {
+    micros: i64,
+}
+
+/// A span of time. Months and days are kept apart from the rest because
+/// their length varies, so a month added to January 31st ends on the last
+/// day of February.
+///
+/// Intervals are compared by counting a month as 30 days and a day as 24
+/// hours, so `1 month` equals `30 days`.
+#[derive(Clone, Copy, Debug)]
+pub struct Interval {
+    months: i32,
+    days: i32,
+    micros: i64,
+}
+
+/// The parts of a date or time, as read from text.
+#[derive(Default)]
+struct Fields {
+    year: Option<i64>,
+    month: Option<i64>,
+    day: Option<i64>,
+    hour: i64,
+    minute: i64,
+    second: i64,
+    micros: i64,
+}
+
+impl Date {
+    const MIN_YEAR: i64 = 1;
+    const MAX_YEAR: i64 = 9999;
+
+    pub fn from_ymd(year: i64, month: i64, day: i64) -> Option<Date> {
+        let valid = (Date::MIN_YEAR..=Date::MAX_YEAR).contains(&year)
+            && (1..=12).contains(&month)
+            && (1..=days_in_month(year, month)).contains(&day);
+        valid.then(|| Date {
+            days: days_from_civil(year, month, day) as i32,
+        })
+    }
+
+    pub fn from_days(days: i64) -> Option<Date> {
+        let (year, _, _) = civil_from_days(days);
+        (Date::MIN_YEAR..=Date::MAX_YEAR)
+            .contains(&year)
+            .then_some(Date { days: days as i32 })
+    }
+
+    pub fn days(&self) -> i32 {
+        self.days
+    }
+
+    /// Parses an ISO-8601 date, like `2017-01-31`.
+    pub fn parse(text: &str) -> Option<Date> {
+        Date::parse_with(text, "%Y-%m-%d")
+    }
+
+    /// Parses a date written in `format`. See `Formats` for how formats are
+    /// written.
+    pub fn parse_with(text: &str, format: &str) -> Option<Date> {
+        let fields = parse_fields(text, format)?;
+        Date::from_ymd(fields.year?, fields.month?, fields.day?)
+    }
+
+    /// The year, month and day.
+    pub fn ymd(&self) -> (i64, i64, i64) {
+        civil_from_days(self.days as i64)
+    }
+
+    pub fn add_days(&self, days: i64) -> Option<Date> {
+        Date::from_days((self.days as i64).checked_add(days)?)
+    }
+
+    /// The number of days from `other` to this date.
+    pub fn days_since(&self, other: &Date) -> i64 {
+        self.days as i64 - other.days as i64
+    }
+
+    pub fn at_midnight(&self) -> Timestamp {
+        Timestamp {
+            micros: self.days as i64 * MICROS_PER_DAY,
+        }
+    }
+
+    /// The day of the week, from 1 for Monday to 7 for Sunday.
+    fn iso_weekday(&self) -> i64 {
+        // 1970-01-01 was a Thursday.
+        (self.days as i64 + 3).rem_euclid(7) + 1
+    }
+
+    fn day_of_year(&self) -> i64 {
+        let (year, _, _) = self.ymd();
+        self.days as i64 - days_from_civil(year, 1, 1) + 1
+    }
+
+    /// The ISO-8601 week number. Weeks start on a Monday, and the first
+    /// week of a year is the one with its first Thursday.
+    fn iso_week(&self) -> i64 {
+        let thursday = Date {
+            days: self.days + (4 - self.iso_weekday()) as i32,
+        };
+        (thursday.day_of_year() - 1) / 7 + 1
+    }
+
+    /// Adds whole months, moving the day back to the end of a shorter
+    /// month.
+    fn add_months(&self, months: i64) -> Option<Date> {
+        let (year, month, day) = self.ymd();
+        let month_index = (year * 12 + month - 1).checked_add(months)?;
+        let (year, month) = (month_index.div_euclid(12), month_index.rem_euclid(12) + 1);
+        if !(Date::MIN_YEAR..=Date::MAX_YEAR).contains(&year) {
+            return None;
+        }
+        Date::from_ymd(year, month, day.min(days_in_month(year, month)))
+    }
+
+    /// The first day of the year, quarter, month, week or day this date is
+    /// in.
+    pub fn truncate(&self, field: &str) -> Result<Date, String> {
+        let (year, month, _) = self.ymd();
+        let date = match field {
+            "year" => Date::from_ymd(year, 1, 1),
+            "quarter" => Date::from_ymd(year, month - (month - 1) % 3, 1),
+            "month" => Date::from_ymd(year, month, 1),
+            "week" => self.add_days(1 - self.iso_weekday()),
+            "day" | "hour" | "minute" | "second" => Some(*self),
+            _ => return Err(format!("Unknown date_trunc field '{}'", field)),
+        };
+        date.ok_or_else(|| format!("date_trunc('{}', {}) is out of range", field, self))
+    }
+
+    /// A field of the date, as `extract` gives it.
+    pub fn extract(&self, field: &str) -> Result<i64, String> {
+        let (year, month, day) = self.ymd();
+        Ok(match field {
+            "year" => year,
+            "quarter" => (month - 1) / 3 + 1,
+            "month" => month,
+            "week" => self.iso_week(),
+            "day" => day,
+            // Sunday is 0.
+            "dow" => self.iso_weekday() % 7,
+            "isodow" => self.iso_weekday(),
+            "doy" => self.day_of_year(),
+            "epoch" => self.days as i64 * (MICROS_PER_DAY / MICROS_PER_SECOND),
+            _ => return Err(format!("Unknown date field '{}'", field)),
+        })
+    }
+}
+
+impl Time {
+    pub fn from_micros(micros: i64) -> Option<Time> {
+        (0..MICROS_PER_DAY).contains(&micros).then_some(Time { micros })
+    }
+
+    pub fn micros(&self) -> i64 {
+        self.micros
+    }
+
+    /// Parses an ISO-8601 time, like `12:30`, `12:30:05` or
+    /// `12:30:05.25`.
+    pub fn parse(text: &str) -> Option<Time> {
+        Time::parse_with(text, "%H:%M:%S").or_else(|| Time::parse_with(text, "%H:%M"))
+    }
+
+    pub fn parse_with(text: &str, format: &str) -> Option<Time> {
+        let fields = parse_fields(text, format)?;
+        if fields.year.is_some() || fields.month.is_some() || fields.day.is_some() {
+            return None;
+        }
+        fields.time_of_day().map(|micros| Time { micros })
+    }
+
+    /// Adds the time part of an interval, wrapping around midnight. Months
+    /// and days make no difference to a time of day.
+    pub fn add(&self, interval: &Interval) -> Time {
+        Time {
+            micros: (self.micros + interval.micros % MICROS_PER_DAY).rem_euclid(MICROS_PER_DAY),
+        }
+    }
+
+    pub fn since(&self, other: &Time) -> Interval {
+        Interval::new(0, 0, self.micros - other.micros)
+    }
+
+    pub fn extract(&self, field: &str) -> Result<FieldValue, String> {
+        extract_time_of_day(self.micros, field).ok_or_else(|| format!("Unknown time field '{}'", field))
+    }
+}
+
+impl Timestamp {
+    pub fn from_micros(micros: i64) -> Option<Timestamp> {
+        let days = micros.div_euclid(MICROS_PER_DAY);
+        Date::from_days(days).map(|_| Timestamp { micros })
+    }
+
+    pub fn micros(&self) -> i64 {
+        self.micros
+    }
+
+    pub fn from_date_and_time(date: Date, time: Time) -> Timestamp {
+        Timestamp {
+            micros: date.at_midnight().micros + time.micros,
+        }
+    }
+
+    pub fn date(&self) -> Date {
+        Date {
+            days: self.micros.div_euclid(MICROS_PER_DAY) as i32,
+        }
+    }
+
+    pub fn time(&self) -> Time {
+        Time {
+            micros: self.micros.rem_euclid(MICROS_PER_DAY),
+        }
+    }
+
+    /// Parses an ISO-8601 date and time, separated by a space or a `T`. The
+    /// seconds can be left out, or the whole time to mean midnight.
+    pub fn parse(text: &str) -> Option<Timestamp> {
+        ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d"]
+            .iter()
+            .find_map(|format| Timestamp::parse_with(text, format))
+    }
+
+    pub fn parse_with(text: &str, format: &str) -> Option<Timestamp> {
+        let fields = parse_fields(text, format)?;
+        let date = Date::from_ymd(fields.year?, fields.month?, fields.day?)?;
+        let time = Time::from_micros(fields.time_of_day()?)?;
+        Some(Timestamp::from_date_and_time(date, time))
+    }
+
+    /// Adds the interval's months, then its days and then the rest.
+    pub fn add(&self, interval: &Interval) -> Option<Timestamp> {
+        let date = self.date().add_months(interval.months as i64)?;
+        let micros = Timestamp::from_date_and_time(date, self.time())
+            .micros
+            .checked_add((interval.days as i64).checked_mul(MICROS_PER_DAY)?)?
+            .checked_add(interval.micros)?;
+        Timestamp::from_micros(micros)
+    }
+
+    pub fn subtract(&self, interval: &Interval) -> Option<Timestamp> {
+        self.add(&interval.negate()?)
+    }
+
+    /// The time from `other` to this timestamp, in days and the time left
+    /// over, which has the same sign.
+    pub fn since(&self, other: &Timestamp) -> Interval {
+        let micros = self.micros - other.micros;
+        Interval::new(0, (micros / MICROS_PER_DAY) as i32, micros % MICROS_PER_DAY)
+    }
+
+    pub fn truncate(&self, field: &str) -> Result<Timestamp, String> {
+        let micros = self.time().micros;
+        let time = match field {
+            "hour" => micros - micros % MICROS_PER_HOUR,
+            "minute" => micros - micros % MICROS_PER_MINUTE,
+            "second" => micros - micros % MICROS_PER_SECOND,
+            _ => 0,
+        };
+        let date = self.date().truncate(field)?;
+        Ok(Timestamp::from_date_and_time(date, Time { micros: time }))
+    }
+
+    pub fn extract(&self, field: &str) -> Result<FieldValue, String> {
+        if field == "epoch" {
+            return Ok(FieldValue::Seconds(self.micros as i128));
+        }
+        match extract_time_of_day(self.time().micros, field) {
+            Some(value) => Ok(value),
+            None => self.date().extract(field).map(FieldValue::Whole),
+        }
+    }
+}
+
+/// The value of a field `extract` takes, which is a whole number except
+/// for seconds, which keep their fraction.
+pub enum FieldValue {
+    Whole(i64),
+    /// A number of seconds, in microseconds.
+    Seconds(i128),
+}
+
+fn extract_time_of_day(micros: i64, field: &str) -> Option<FieldValue> {
+    Some(match field {
+        "hour" => FieldValue::Whole(micros / MICROS_PER_HOUR),
+        "minute" => FieldValue::Whole(micros % MICROS_PER_HOUR / MICROS_PER_MINUTE),
+        "second" => FieldValue::Seconds((micros % MICROS_PER_MINUTE) as i128),
+        "epoch" => FieldValue::Seconds(micros as i128),
+        _ => return None,
+    })
+}
+
+impl Interval {
+    pub fn new(months: i32, days: i32, micros: i64) -> Interval {
+        Interval { months, days, micros }
+    }
+
+    pub fn months(&self) -> i32 {
+        self.months
+    }
+
+    pub fn days(&self) -> i32 {
+        self.days
+    }
+
+    pub fn micros(&self) -> i64 {
+        self.micros
+    }
+
+    /// Parses quantities of units, like `1 year 2 months`, `-3 days` or
+    /// `2 hours 30 minutes`, where units can be singular or plural. A time
+    /// like `04:05:06` can be given instead of hours, minutes and seconds.
+    pub fn parse(text: &str) -> Option<Interval> {
+        let mut interval = Interval::new(0, 0, 0);
+        let mut words = text.split_whitespace();
+        while let Some(word) = words.next() {
+            if word.contains(':') {
+                let (negative, time) = match word.strip_prefix('-') {
+                    Some(time) => (true, time),
+                    None => (false, word),
+                };
+                let micros = Time::parse(time)?.micros;
+                interval.micros = interval.micros.checked_add(if negative { -micros } else { micros })?;
+                continue;
+            }
+
+            let quantity: i64 = word.parse().ok()?;
+            let unit = words.next()?.to_lowercase();
+            let unit = unit.strip_suffix('s').unwrap_or(&unit);
+            let (months, days, micros) = match unit {
+                "year" => (12, 0, 0),
+                "month" => (1, 0, 0),
+                "week" => (0, 7, 0),
+                "day" => (0, 1, 0),
+                "hour" => (0, 0, MICROS_PER_HOUR),
+                "minute" => (0, 0, MICROS_PER_MINUTE),
+                "second" => (0, 0, MICROS_PER_SECOND),
+                "millisecond" => (0, 0, 1000),
+                "microsecond" => (0, 0, 1),
+                _ => return None,
+            };
+            interval = interval.add(&Interval {
+                months: i32::try_from(quantity.checked_mul(months)?).ok()?,
+                days: i32::try_from(quantity.checked_mul(days)?).ok()?,
+                micros: quantity.checked_mul(micros)?,
+            })?;
+        }
+        Some(interval)
+    }
+
+    pub fn add(&self, other: &Interval) -> Option<Interval> {
+        Some(Interval {
+            months: self.months.checked_add(other.months)?,
+            days: self.days.checked_add(other.days)?,
+            micros: self.micros.checked_add(other.micros)?,
+        })
+    }
+
+    pub fn subtract(&self, other: &Interval) -> Option<Interval> {
+        self.add(&other.negate()?)
+    }
+
+    pub fn negate(&self) -> Option<Interval> {
+        Some(Interval {
+            months: self.months.checked_neg()?,
+            days: self.days.checked_neg()?,
+            micros: self.micros.checked_neg()?,
+        })
+    }
+
+    /// Multiplies each part, carrying fractions of months into days and
+    /// fractions of days into the time.
+    pub fn multiply(&self, factor: f64) -> Option<Interval> {
+        let months = self.months as f64 * factor;
+        let days = self.days as f64 * factor + months.fract() * DAYS_PER_MONTH as f64;
+        let micros = self.micros as f64 * factor + days.fract() * MICROS_PER_DAY as f64;
+        let fits = |value: f64, max: f64| value.is_finite() && value.abs() <= max;
+        (fits(months, i32::MAX as f64) && fits(days, i32::MAX as f64) && fits(micros, i64::MAX as f64)).then(|| {
+            Interval {
+                months: months.trunc() as i32,
+                days: days.trunc() as i32,
+                micros: micros.round() as i64,
+            }
+        })
+    }
+
+    /// Divides like `multiply`. Dividing by zero gives `None`.
+    pub fn divide(&self, divisor: f64) -> Option<Interval> {
+        if divisor == 0.0 {
+            return None;
+        }
+        self.multiply(1.0 / divisor)
+    }
+
+    /// The interval's length, counting a month as 30 days.
+    fn total_micros(&self) -> i128 {
+        (self.months as i128 * DAYS_PER_MONTH as i128 + self.days as i128) * MICROS_PER_DAY as i128
+            + self.micros as i128
+    }
+
+    pub fn extract(&self, field: &str) -> Result<FieldValue, String> {
+        Ok(match field {
+            "year" => FieldValue::Whole(self.months as i64 / 12),
+            "month" => FieldValue::Whole(self.months as i64 % 12),
+            "day" => FieldValue::Whole(self.days as i64),
+            "hour" => FieldValue::Whole(self.micros / MICROS_PER_HOUR),
+            "minute" => FieldValue::Whole(self.micros % MICROS_PER_HOUR / MICROS_PER_MINUTE),
+            "second" => FieldValue::Seconds((self.micros % MICROS_PER_MINUTE) as i128),
+            // Counting a year as 365.25 days, as months are counted as 30.
+            "epoch" => FieldValue::Seconds(
+                (self.months as i128 / 12) * 36525 * MICROS_PER_DAY as i128 / 100
+                    + (self.months as i128 % 12 * DAYS_PER_MONTH as i128 + self.days as i128) * MICROS_PER_DAY as i128
+                    + self.micros as i128,
+            ),
+            _ => return Err(format!("Unknown interval field '{}'", field)),
+        })
+    }
+}
+
+impl Ord for Interval {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.total_micros().cmp(&other.total_micros())
+    }
+}
+
+impl PartialOrd for Interval {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Interval {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Interval {}
+
+impl Hash for Interval {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.total_micros().hash(state);
+    }
+}
+
+impl Fields {
+    fn time_of_day(&self) -> Option<i64> {
+        let valid = (0..24).contains(&self.hour) && (0..60).contains(&self.minute) && (0..60).contains(&self.second);
+        valid.then(|| self.hour * MICROS_PER_HOUR + self.minute * MICROS_PER_MINUTE + self.second * MICROS_PER_SECOND + self.micros)
+    }
+}
+
+/// Reads the fields of a date or time from `text` as `format` lays them
+/// out. See `Formats` for the directives a format can use.
+fn parse_fields(text: &str, format: &str) -> Option<Fields> {
+    let mut fields = Fields::default();
+    let mut text = text.trim();
+    let mut directives = format.chars();
+    // Takes up to `max` digits, and at least one.
+    let digits = |text: &mut &str, max: usize| -> Option<i64> {
+        let len = text.chars().take(max).take_while(char::is_ascii_digit).count();
+        let (number, rest) = text.split_at(len);
+        *text = rest;
+        number.parse().ok()
+    };
+
+    while let Some(c) = directives.next() {
+        if c != '%' {
+            text = text.strip_prefix(c)?;
+            continue;
+        }
+        match directives.next()? {
+            'Y' => fields.year = Some(digits(&mut text, 4)?),
+            'm' => fields.month = Some(digits(&mut text, 2)?),
+            'd' => fields.day = Some(digits(&mut text, 2)?),
+            'H' => fields.hour = digits(&mut text, 2)?,
+            'M' => fields.minute = digits(&mut text, 2)?,
+            'S' => {
+                fields.second = digits(&mut text, 2)?;
+                // Seconds can have a fraction, down to microseconds.
+                if let Some(rest) = text.strip_prefix('.') {
+                    let len = rest.chars().take_while(char::is_ascii_digit).count();
+                    if len == 0 || len > 6 {
+                        return None;
+                    }
+                    fields.micros = format!("{:0<6}", &rest[..len]).parse().ok()?;
+                    text = &rest[len..];
+                }
+            }
+            '%' => text = text.strip_prefix('%')?,
+            _ => return None,
+        }
+    }
+    text.is_empty().then_some(fields)
+}
+
+/// How `import csv` reads dates and times. A format is written with `%Y`
+/// for the year, `%m` for the month, `%d` for the day, `%H` for the hour,
+/// `%M` for the minute and `%S` for the second, which can have a fraction,
+/// with `%%` standing for `%` and anything else for itself. Without a
+/// format, values are read as ISO-8601.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Formats {
+    pub date: Option<String>,
+    pub time: Option<String>,
+    pub timestamp: Option<String>,
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The days since 1970-01-01 of a date in the proleptic Gregorian calendar,
+/// counted in 400 year eras that start on March 1st.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// The reverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = (shifted_month + 2) % 12 + 1;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Writes a time of day as `hh:mm:ss`, with a fraction of a second if it
+/// has one.
+fn write_time(f: &mut fmt::Formatter<'_>, micros: i64) -> fmt::Result {
+    let seconds = micros / MICROS_PER_SECOND;
+    write!(f, "{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)?;
+    match micros % MICROS_PER_SECOND {
+        0 => Ok(()),
+        fraction => write!(f, ".{}", format!("{:06}", fraction).trim_end_matches('0')),
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.ymd();
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_time(f, self.micros)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.date(), self.time())
+    }
+}
+
+/// Like `1 year 2 months 3 days 04:05:06`, leaving out the parts that are
+/// zero.
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        let mut unit = |quantity: i64, name: &str| {
+            if quantity != 0 {
+                let plural = if quantity.abs() == 1 { "" } else { "s" };
+                parts.push(format!("{} {}{}", quantity, name, plural));
+            }
+        };
+        unit(self.months as i64 / 12, "year");
+        unit(self.months as i64 % 12, "month");
+        unit(self.days as i64, "day");
+        write!(f, "{}", parts.join(" "))?;
+
+        if self.micros != 0 || parts.is_empty() {
+            let space = if parts.is_empty() { "" } else { " " };
+            let sign = if self.micros < 0 { "-" } else { "" };
+            write!(f, "{}{}", space, sign)?;
+            write_time(f, self.micros.abs())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> Date {
+        Date::parse(text).unwrap()
+    }
+
+    fn timestamp(text: &str) -> Timestamp {
+        Timestamp::parse(text).unwrap()
+    }
+
+    fn interval(text: &str) -> Interval {
+        Interval::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!("2017-01-31", date("2017-01-31").to_string());
+        assert_eq!(0, date("1970-01-01").days());
+        assert_eq!(-1, date("1969-12-31").days());
+        assert_eq!("2016-02-29", date("2016-02-29").to_string());
+        assert!(Date::parse("2017-02-29").is_none());
+        assert!(Date::parse("2017-13-01").is_none());
+        assert!(Date::parse("2017-01-01 10:00").is_none());
+        assert_eq!("0001-01-01", date("1-01-01").to_string());
+        assert_eq!("9999-12-31", date("9999-12-31").to_string());
+
+        assert_eq!("12:30:00", Time::parse("12:30").unwrap().to_string());
+        assert_eq!("23:59:59.25", Time::parse("23:59:59.250").unwrap().to_string());
+        assert!(Time::parse("24:00:00").is_none());
+        assert!(Time::parse("12:30:00.1234567").is_none());
+
+        assert_eq!("2017-01-31 00:00:00", timestamp("2017-01-31").to_string());
+        assert_eq!("2017-01-31 08:15:00", timestamp("2017-01-31T08:15").to_string());
+        assert_eq!("2017-01-31 08:15:01.5", timestamp(" 2017-01-31 08:15:01.5 ").to_string());
+
+        assert_eq!(date("2017-01-31"), Date::parse_with("31/1/2017", "%d/%m/%Y").unwrap());
+        assert_eq!(date("2017-01-31"), Date::parse_with("20170131", "%Y%m%d").unwrap());
+        assert_eq!(
+            timestamp("2017-01-31 08:15"),
+            Timestamp::parse_with("01/31/2017 8h15", "%m/%d/%Y %Hh%M").unwrap()
+        );
+        assert!(Date::parse_with("2017-01-31", "%d/%m/%Y").is_none());
+        assert!(Date::parse_with("2017x", "%Y%q").is_none());
+    }
+
+    #[test]
+    fn test_intervals() {
+        assert_eq!("1 year 2 months 3 days 04:05:06", interval("1 year 2 months 3 days 04:05:06").to_string());
+        assert_eq!("2 days 02:30:00", interval("2 days 2 hours 30 minutes").to_string());
+        assert_eq!("-1 month -00:00:01", interval("-1 month -1 second").to_string());
+        assert_eq!("00:00:00", interval("").to_string());
+        assert_eq!("14 days", interval("2 weeks").to_string());
+        assert!(Interval::parse("1 fortnight").is_none());
+        assert!(Interval::parse("one day").is_none());
+
+        assert_eq!(interval("1 month"), interval("30 days"));
+        assert!(interval("1 day") > interval("23 hours"));
+        assert_eq!("1 day 12:00:00", interval("1 day").multiply(1.5).unwrap().to_string());
+        assert_eq!("15 days", interval("1 month").divide(2.0).unwrap().to_string());
+        assert!(interval("1 day").divide(0.0).is_none());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let add = |t: &str, i: &str| timestamp(t).add(&interval(i)).unwrap().to_string();
+        assert_eq!("2017-02-28 00:00:00", add("2017-01-31", "1 month"));
+        assert_eq!("2016-02-29 00:00:00", add("2016-01-31", "1 month"));
+        assert_eq!("2018-01-01 01:00:00", add("2017-12-31 23:30", "1 hour 30 minutes"));
+        assert_eq!("2016-12-31 00:00:00", add("2017-01-01", "-1 day"));
+        assert!(timestamp("9999-12-31").add(&interval("1 day")).is_none());
+
+        let since = timestamp("2017-01-02 01:00").since(&timestamp("2017-01-01"));
+        assert_eq!("1 day 01:00:00", since.to_string());
+        assert_eq!("-1 day -01:00:00", timestamp("2017-01-01").since(&timestamp("2017-01-02 01:00")).to_string());
+        assert_eq!(59, date("2017-03-01").days_since(&date("2017-01-01")));
+
+        let time = Time::parse("23:00").unwrap();
+        assert_eq!("01:00:00", time.add(&interval("1 day 2 hours")).to_string());
+        assert_eq!("-02:00:00", Time::parse("21:00").unwrap().since(&time).to_string());
+    }
+
+    #[test]
+    fn test_fields() {
+        let whole = |value: Result<FieldValue, String>| match value.unwrap() {
+            FieldValue::Whole(value) => value,
+            FieldValue::Seconds(micros) => micros as i64,
+        };
+        // 2017-01-01 was a Sunday, in the last ISO week of 2016.
+        let sunday = date("2017-01-01");
+        assert_eq!(0, sunday.extract("dow").unwrap());
+        assert_eq!(52, sunday.extract("week").unwrap());
+        assert_eq!(1, date("2017-01-02").extract("week").unwrap());
+        assert_eq!(365, date("2017-12-31").extract("doy").unwrap());
+        assert_eq!(4, date("2017-12-31").extract("quarter").unwrap());
+        assert!(sunday.extract("hour").is_err());
+
+        let ts = timestamp("2017-08-16 13:45:30.5");
+        assert_eq!(13, whole(ts.extract("hour")));
+        assert_eq!(30_500_000, whole(ts.extract("second")));
+        assert_eq!(2017, whole(ts.extract("year")));
+        assert_eq!(86_400_000_000, whole(timestamp("1970-01-02").extract("epoch")));
+        assert_eq!(2, whole(interval("1 year 2 months").extract("month")));
+
+        assert_eq!("2017-07-01", date("2017-08-16").truncate("quarter").unwrap().to_string());
+        assert_eq!("2017-08-14", date("2017-08-16").truncate("week").unwrap().to_string());
+        assert_eq!("2017-08-16 13:00:00", ts.truncate("hour").unwrap().to_string());
+        assert_eq!("2017-01-01 00:00:00", ts.truncate("year").unwrap().to_string());
+        assert!(ts.truncate("fortnight").is_err());
+    }
+}
@@ -0,0 +1,305 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+/// An exact number with a fixed number of decimal places. It's held as a
+/// whole number of units, each worth `10^-scale`, so `12.50` is 1250 units
+/// with a scale of 2.
+///
+/// Arithmetic that drops decimal places rounds half away from zero, and
+/// returns `None` when the result doesn't fit.
+#[derive(Clone, Copy, Debug)]
+pub struct Decimal {
+    units: i128,
+    scale: u8,
+}
+
+impl Decimal {
+    /// The most digits a decimal can have, which is as many as an `i128`
+    /// always holds. A decimal can't have more decimal places than that
+    /// either.
+    pub const MAX_PRECISION: u8 = 38;
+    /// Division keeps at least this many decimal places.
+    const DIVISION_SCALE: u8 = 6;
+
+    pub fn new(units: i128, scale: u8) -> Decimal {
+        assert!(scale <= Decimal::MAX_PRECISION, "scale {} is too large", scale);
+        Decimal { units, scale }
+    }
+
+    pub fn from_whole(value: i128) -> Decimal {
+        Decimal { units: value, scale: 0 }
+    }
+
+    pub fn units(&self) -> i128 {
+        self.units
+    }
+
+    pub fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    /// Parses digits with an optional sign and decimal point, like `-12.50`.
+    pub fn parse(text: &str) -> Option<Decimal> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let all_digits = whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit());
+        if !all_digits || (whole.is_empty() && fraction.is_empty()) || fraction.len() > Decimal::MAX_PRECISION as usize {
+            return None;
+        }
+
+        let units: i128 = format!("{}{}", whole, fraction).parse().ok()?;
+        Some(Decimal {
+            units: if negative { -units } else { units },
+            scale: fraction.len() as u8,
+        })
+    }
+
+    fn power(exponent: u8) -> Option<i128> {
+        10i128.checked_pow(exponent as u32)
+    }
+
+    /// The number of digits, not counting leading zeros.
+    pub fn precision(&self) -> u8 {
+        let mut digits = 1;
+        let mut units = self.units.unsigned_abs() / 10;
+        while units > 0 {
+            digits += 1;
+            units /= 10;
+        }
+        digits
+    }
+
+    /// The same number with `scale` decimal places.
+    pub fn rescale(&self, scale: u8) -> Option<Decimal> {
+        let units = if scale >= self.scale {
+            self.units.checked_mul(Decimal::power(scale - self.scale)?)?
+        } else {
+            round_div(self.units, Decimal::power(self.scale - scale)?)
+        };
+        (scale <= Decimal::MAX_PRECISION).then_some(Decimal { units, scale })
+    }
+
+    /// Both numbers with the same number of decimal places.
+    fn align(&self, other: &Decimal) -> Option<(i128, i128, u8)> {
+        let scale = self.scale.max(other.scale);
+        Some((self.rescale(scale)?.units, other.rescale(scale)?.units, scale))
+    }
+
+    pub fn add(&self, other: &Decimal) -> Option<Decimal> {
+        let (l, r, scale) = self.align(other)?;
+        Some(Decimal::new(l.checked_add(r)?, scale))
+    }
+
+    pub fn subtract(&self, other: &Decimal) -> Option<Decimal> {
+        let (l, r, scale) = self.align(other)?;
+        Some(Decimal::new(l.checked_sub(r)?, scale))
+    }
+
+    /// The product has as many decimal places as both numbers together.
+    pub fn multiply(&self, other: &Decimal) -> Option<Decimal> {
+        let product = Decimal {
+            units: self.units.checked_mul(other.units)?,
+            scale: self.scale + other.scale,
+        };
+        product.rescale(product.scale.min(Decimal::MAX_PRECISION))
+    }
+
+    /// The quotient has as many decimal places as either number, and at
+    /// least `DIVISION_SCALE`. Dividing by zero gives `None`.
+    pub fn divide(&self, other: &Decimal) -> Option<Decimal> {
+        if other.units == 0 {
+            return None;
+        }
+        let scale = self.scale.max(other.scale).max(Decimal::DIVISION_SCALE);
+        let dividend = self.units.checked_mul(Decimal::power(scale + other.scale - self.scale)?)?;
+        Some(Decimal::new(round_div(dividend, other.units), scale))
+    }
+
+    /// The remainder has the sign of `self`, as with whole numbers.
+    pub fn remainder(&self, other: &Decimal) -> Option<Decimal> {
+        let (l, r, scale) = self.align(other)?;
+        Some(Decimal::new(l.checked_rem(r)?, scale))
+    }
+
+    pub fn abs(&self) -> Option<Decimal> {
+        Some(Decimal::new(self.units.checked_abs()?, self.scale))
+    }
+
+    /// Rounds to `places` decimal places, or to a multiple of `10^-places`
+    /// when `places` is negative.
+    pub fn round(&self, places: i64) -> Option<Decimal> {
+        if places >= 0 {
+            return self.rescale(places.min(Decimal::MAX_PRECISION as i64) as u8);
+        }
+        let shift = (-places).min(Decimal::MAX_PRECISION as i64) as u8;
+        let units = match Decimal::power(self.scale + shift) {
+            Some(divisor) => round_div(self.units, divisor).checked_mul(Decimal::power(shift)?)?,
+            // More places than any decimal has, so it rounds to zero.
+            None => 0,
+        };
+        Some(Decimal::new(units, 0))
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.units as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    /// Compares with a double, which is after every decimal if it's NaN.
+    /// The whole parts are compared exactly, but the fractions only as
+    /// doubles.
+    pub fn compare_to_double(&self, double: f64) -> Ordering {
+        if double.is_nan() || double >= i128::MAX as f64 {
+            return Ordering::Less;
+        } else if double <= i128::MIN as f64 {
+            return Ordering::Greater;
+        }
+        let floor = double.floor();
+        let (whole, fraction) = self.whole_and_fraction();
+        whole.cmp(&(floor as i128)).then_with(|| {
+            let fraction = fraction as f64 / 10f64.powi(self.scale as i32);
+            fraction.partial_cmp(&(double - floor)).unwrap()
+        })
+    }
+
+    /// The largest whole number not above this one, and the units left
+    /// over.
+    fn whole_and_fraction(&self) -> (i128, i128) {
+        let one = Decimal::power(self.scale).unwrap();
+        (self.units.div_euclid(one), self.units.rem_euclid(one))
+    }
+
+    /// The same number without trailing zeros after the decimal point.
+    fn normalized(&self) -> Decimal {
+        let mut normalized = *self;
+        while normalized.scale > 0 && normalized.units % 10 == 0 {
+            normalized.units /= 10;
+            normalized.scale -= 1;
+        }
+        normalized
+    }
+}
+
+/// Divides, rounding half away from zero.
+fn round_div(dividend: i128, divisor: i128) -> i128 {
+    let quotient = dividend / divisor;
+    let remainder = (dividend % divisor).unsigned_abs();
+    if remainder >= divisor.unsigned_abs() - remainder {
+        quotient + dividend.signum() * divisor.signum()
+    } else {
+        quotient
+    }
+}
+
+/// Decimals are equal if their values are, whatever their scale, so `1.50`
+/// equals `1.5`.
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (l_whole, l_fraction) = self.whole_and_fraction();
+        let (r_whole, r_fraction) = other.whole_and_fraction();
+        // Each fraction is below 10^scale, so neither overflows.
+        let scale = self.scale.max(other.scale);
+        let l_fraction = l_fraction * Decimal::power(scale - self.scale).unwrap();
+        let r_fraction = r_fraction * Decimal::power(scale - other.scale).unwrap();
+        l_whole.cmp(&r_whole).then(l_fraction.cmp(&r_fraction))
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let normalized = self.normalized();
+        normalized.units.hash(state);
+        normalized.scale.hash(state);
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.units.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+        let sign = if self.units < 0 { "-" } else { "" };
+        match fraction.is_empty() {
+            true => write!(f, "{}{}", sign, whole),
+            false => write!(f, "{}{}.{}", sign, whole, fraction),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(text: &str) -> Decimal {
+        Decimal::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!("12.50", decimal("12.50").to_string());
+        assert_eq!("-0.05", decimal("-.05").to_string());
+        assert_eq!("7", decimal("+7").to_string());
+        assert_eq!(2, decimal("12.50").scale());
+        assert_eq!(4, decimal("-12.50").precision());
+        assert!(Decimal::parse("1.2.3").is_none());
+        assert!(Decimal::parse("-").is_none());
+        assert!(Decimal::parse("1e5").is_none());
+        assert!(Decimal::parse(&"9".repeat(40)).is_none());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!("0.30", decimal("0.10").add(&decimal("0.2")).unwrap().to_string());
+        assert_eq!("-1.9", decimal("0.1").subtract(&decimal("2")).unwrap().to_string());
+        assert_eq!("1.2100", decimal("1.10").multiply(&decimal("1.10")).unwrap().to_string());
+        assert_eq!("0.333333", decimal("1").divide(&decimal("3")).unwrap().to_string());
+        assert_eq!("0.666667", decimal("2").divide(&decimal("3")).unwrap().to_string());
+        assert_eq!("-0.66666667", decimal("-2.00000000").divide(&decimal("3")).unwrap().to_string());
+        assert_eq!(None, decimal("1").divide(&decimal("0.00")));
+        assert_eq!("-1.5", decimal("-7.5").remainder(&decimal("2")).unwrap().to_string());
+        assert_eq!(None, Decimal::new(i128::MAX, 0).add(&decimal("1")));
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!("2.35", decimal("2.345").rescale(2).unwrap().to_string());
+        assert_eq!("-2.35", decimal("-2.345").rescale(2).unwrap().to_string());
+        assert_eq!("2.34", decimal("2.3449").rescale(2).unwrap().to_string());
+        assert_eq!("1.500", decimal("1.5").round(3).unwrap().to_string());
+        assert_eq!("100", decimal("149.5").round(-2).unwrap().to_string());
+        assert_eq!("-200", decimal("-150").round(-2).unwrap().to_string());
+        assert_eq!("0", decimal("5").round(-60).unwrap().to_string());
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert_eq!(decimal("1.50"), decimal("1.5"));
+        assert!(decimal("-0.5") < decimal("0.25"));
+        assert!(decimal("-1.25") < decimal("-1.2"));
+        assert!(decimal("10") > decimal("9.999"));
+        assert_eq!(Ordering::Less, decimal("0.1").compare_to_double(0.25));
+        assert_eq!(Ordering::Equal, decimal("-2.5").compare_to_double(-2.5));
+        assert_eq!(Ordering::Greater, decimal("3").compare_to_double(2.999));
+        assert_eq!(Ordering::Less, decimal("3").compare_to_double(f64::NAN));
+    }
+}
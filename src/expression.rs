@@ -0,0 +1,1292 @@
+use std::cmp::Ordering;
+
+use crate::{
+    datetime::{Date, Interval, Time, Timestamp},
+    function::{to_text, ScalarFunction},
+    mapper::InsertValueMapper,
+    pattern::Pattern,
+    sql_parser::{ArithmeticOperator, ComparisonOperator, Expression, PatternKind},
+    subquery::Subquery,
+    table::{ColumnSpec, ColumnType, Value},
+};
+
+/// The type of the values an expression produces, as far as it's known
+/// before the expression is evaluated. Every type of number is a `Number`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DataType {
+    Text,
+    Number,
+    Boolean,
+    Date,
+    Time,
+    Timestamp,
+    Interval,
+    Blob,
+}
+
+impl DataType {
+    /// The type of a value. Null has no type of its own.
+    pub fn of(value: &Value) -> Option<DataType> {
+        match value {
+            Value::Varchar { .. } => Some(DataType::Text),
+            Value::Number { .. } | Value::Int { .. } | Value::Decimal { .. } | Value::Double { .. } => {
+                Some(DataType::Number)
+            }
+            Value::Boolean { .. } => Some(DataType::Boolean),
+            Value::Date { .. } => Some(DataType::Date),
+            Value::Time { .. } => Some(DataType::Time),
+            Value::Timestamp { .. } => Some(DataType::Timestamp),
+            Value::Interval { .. } => Some(DataType::Interval),
+            Value::Blob { .. } => Some(DataType::Blob),
+            Value::Null => None,
+        }
+    }
+}
+
+impl From<&ColumnType> for DataType {
+    fn from(column_type: &ColumnType) -> DataType {
+        match column_type {
+            ColumnType::Varchar { .. } => DataType::Text,
+            ColumnType::Number
+            | ColumnType::SmallInt
+            | ColumnType::Int
+            | ColumnType::Decimal { .. }
+            | ColumnType::Double => DataType::Number,
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::Date => DataType::Date,
+            ColumnType::Time => DataType::Time,
+            ColumnType::Timestamp => DataType::Timestamp,
+            ColumnType::Interval => DataType::Interval,
+            ColumnType::Text => DataType::Text,
+            ColumnType::Blob => DataType::Blob,
+        }
+    }
+}
+
+/// A column in scope. Its type is unknown if it's a column of a query's
+/// result rather than of a table.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ScopeColumn {
+    pub table_name: Option<String>,
+    pub column_name: String,
+    pub data_type: Option<DataType>,
+}
+
+/// The columns an expression can refer to, in the order they appear in the
+/// rows it's evaluated against. A join's scope is its tables' scopes one
+/// after the other.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Scope {
+    pub columns: Vec<ScopeColumn>,
+}
+
+impl Scope {
+    pub fn table(table_name: &str, column_specs: &[ColumnSpec]) -> Scope {
+        Scope {
+            columns: column_specs
+                .iter()
+                .map(|cs| ScopeColumn {
+                    table_name: Some(table_name.to_string()),
+                    column_name: cs.column_name.clone(),
+                    data_type: Some(DataType::from(&cs.column_type)),
+                })
+                .collect(),
+        }
+    }
+
+    /// A scope of the named columns, such as those of a query's result.
+    pub fn named(table_name: Option<&str>, column_names: &[String]) -> Scope {
+        Scope {
+            columns: column_names
+                .iter()
+                .map(|column_name| ScopeColumn {
+                    table_name: table_name.map(str::to_string),
+                    column_name: column_name.clone(),
+                    data_type: None,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn join(&self, other: &Scope) -> Scope {
+        Scope {
+            columns: self.columns.iter().chain(other.columns.iter()).cloned().collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn contains(&self, table_name: &Option<String>, column_name: &str) -> bool {
+        !self.matching(table_name, column_name).is_empty()
+    }
+
+    fn matching(&self, table_name: &Option<String>, column_name: &str) -> Vec<usize> {
+        self.columns
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| {
+                c.column_name == column_name && (table_name.is_none() || c.table_name == *table_name)
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// The position of a column, which must be qualified if more than one
+    /// table has a column of that name.
+    pub fn resolve(&self, table_name: &Option<String>, column_name: &str) -> Result<usize, String> {
+        let matches = self.matching(table_name, column_name);
+
+        let display_name = match table_name {
+            Some(table_name) => format!("{}.{}", table_name, column_name),
+            None => column_name.to_string(),
+        };
+        match matches[..] {
+            [index] => Ok(index),
+            [] => Err(format!("Unknown column '{}'", display_name)),
+            _ => Err(format!("Column '{}' is ambiguous", display_name)),
+        }
+    }
+
+    /// Binds a column reference to its position in the scope.
+    pub fn bind_column<'a>(&self, table_name: &Option<String>, column_name: &str) -> Result<BoundExpression<'a>, String> {
+        let index = self.resolve(table_name, column_name)?;
+        Ok(BoundExpression::Column {
+            index,
+            data_type: self.columns[index].data_type,
+        })
+    }
+}
+
+/// The pattern of a pattern match. One written as a literal is compiled
+/// when it's bound, anything else for every row.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum MatchPattern<'a> {
+    Compiled(Pattern),
+    Dynamic {
+        kind: PatternKind,
+        pattern: Box<BoundExpression<'a>>,
+    },
+}
+
+/// An expression whose column references have been resolved to positions in
+/// a row, ready to be evaluated against every row of a scan.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BoundExpression<'a> {
+    Column {
+        index: usize,
+        data_type: Option<DataType>,
+    },
+    Literal {
+        value: Value,
+    },
+    Comparison {
+        left: Box<BoundExpression<'a>>,
+        operator: ComparisonOperator,
+        right: Box<BoundExpression<'a>>,
+    },
+    Arithmetic {
+        left: Box<BoundExpression<'a>>,
+        operator: ArithmeticOperator,
+        right: Box<BoundExpression<'a>>,
+    },
+    And {
+        left: Box<BoundExpression<'a>>,
+        right: Box<BoundExpression<'a>>,
+    },
+    Or {
+        left: Box<BoundExpression<'a>>,
+        right: Box<BoundExpression<'a>>,
+    },
+    Not {
+        expression: Box<BoundExpression<'a>>,
+    },
+    Function {
+        function: ScalarFunction,
+        arguments: Vec<BoundExpression<'a>>,
+    },
+    Exists {
+        subquery: Subquery<'a>,
+    },
+    InSubquery {
+        expression: Box<BoundExpression<'a>>,
+        subquery: Subquery<'a>,
+        negated: bool,
+    },
+    PatternMatch {
+        expression: Box<BoundExpression<'a>>,
+        pattern: MatchPattern<'a>,
+        negated: bool,
+    },
+    InList {
+        expression: Box<BoundExpression<'a>>,
+        list: Vec<BoundExpression<'a>>,
+        negated: bool,
+    },
+    IsNull {
+        expression: Box<BoundExpression<'a>>,
+        negated: bool,
+    },
+    /// A `case` with an operand compares it with each branch's `when`
+    /// value; one without evaluates each `when` as a condition.
+    Case {
+        operand: Option<Box<BoundExpression<'a>>>,
+        branches: Vec<(BoundExpression<'a>, BoundExpression<'a>)>,
+        otherwise: Option<Box<BoundExpression<'a>>>,
+    },
+}
+
+impl<'a> BoundExpression<'a> {
+    /// Binds column references to their position in `scope`.
+    pub fn bind(expression: &Expression, scope: &Scope) -> Result<BoundExpression<'a>, String> {
+        BoundExpression::bind_with(expression, &|e| match e {
+            Expression::Column {
+                table_name,
+                column_name,
+            } => scope.bind_column(table_name, column_name).map(Some),
+            _ => Ok(None),
+        })
+    }
+
+    /// Binds an expression, giving `resolve` the first go at every
+    /// subexpression. Columns and aggregates that `resolve` leaves alone
+    /// can't be bound. Operators, functions and `case` branches whose types
+    /// are known are type checked.
+    pub fn bind_with<F>(expression: &Expression, resolve: &F) -> Result<BoundExpression<'a>, String>
+    where
+        F: Fn(&Expression) -> Result<Option<BoundExpression<'a>>, String>,
+    {
+        if let Some(bound) = resolve(expression)? {
+            return Ok(bound);
+        }
+
+        let bind = |e: &Expression| BoundExpression::bind_with(e, resolve).map(Box::new);
+
+        match expression {
+            Expression::Column { .. } => Err(format!("Unknown column '{}'", expression)),
+            Expression::Aggregate { .. } => Err(format!("Aggregate {} is not allowed here", expression)),
+            Expression::Window { .. } => Err(format!("Window function {} is not allowed here", expression)),
+            Expression::Exists { .. } | Expression::InSubquery { .. } => {
+                Err(format!("Subquery {} is not allowed here", expression))
+            }
+            Expression::Literal { value } => Ok(BoundExpression::Literal {
+                value: InsertValueMapper::sql_parser_to_table(value),
+            }),
+            Expression::Comparison {
+                left,
+                operator,
+                right,
+            } => {
+                let (left, right) = (bind(left)?, bind(right)?);
+                check_comparable(&left, &right)?;
+                Ok(BoundExpression::Comparison {
+                    left,
+                    operator: *operator,
+                    right,
+                })
+            }
+            Expression::Arithmetic {
+                left,
+                operator,
+                right,
+            } => {
+                let (left, right) = (bind(left)?, bind(right)?);
+                if let (Some(l), Some(r)) = (left.data_type(), right.data_type()) {
+                    if arithmetic_type(Some(l), *operator, Some(r)).is_none() {
+                        return Err(format!("Cannot apply {:?} to {} and {}", operator, l, r));
+                    }
+                }
+                Ok(BoundExpression::Arithmetic {
+                    left,
+                    operator: *operator,
+                    right,
+                })
+            }
+            Expression::And { left, right } => Ok(BoundExpression::And {
+                left: Box::new(bind(left)?.condition()?),
+                right: Box::new(bind(right)?.condition()?),
+            }),
+            Expression::Or { left, right } => Ok(BoundExpression::Or {
+                left: Box::new(bind(left)?.condition()?),
+                right: Box::new(bind(right)?.condition()?),
+            }),
+            Expression::Not { expression } => Ok(BoundExpression::Not {
+                expression: Box::new(bind(expression)?.condition()?),
+            }),
+            Expression::Function { name, arguments } => {
+                let function = ScalarFunction::lookup(name)?;
+                let arguments = arguments
+                    .iter()
+                    .map(|a| BoundExpression::bind_with(a, resolve))
+                    .collect::<Result<Vec<BoundExpression>, String>>()?;
+                function.return_type(&arguments.iter().map(BoundExpression::data_type).collect::<Vec<_>>())?;
+                Ok(BoundExpression::Function { function, arguments })
+            }
+            Expression::PatternMatch {
+                expression,
+                kind,
+                pattern,
+                negated,
+            } => {
+                let expression = bind(expression)?;
+                let pattern = bind(pattern)?;
+                for operand in [&expression, &pattern] {
+                    if let Some(data_type) = operand.data_type().filter(|t| *t != DataType::Text) {
+                        return Err(format!("Only text can be matched with a pattern, not {}", data_type));
+                    }
+                }
+                let pattern = match *pattern {
+                    BoundExpression::Literal {
+                        value: Value::Varchar { value },
+                    } => MatchPattern::Compiled(Pattern::compile(*kind, &value)?),
+                    pattern => MatchPattern::Dynamic {
+                        kind: *kind,
+                        pattern: Box::new(pattern),
+                    },
+                };
+                Ok(BoundExpression::PatternMatch {
+                    expression,
+                    pattern,
+                    negated: *negated,
+                })
+            }
+            Expression::InList {
+                expression,
+                list,
+                negated,
+            } => {
+                let expression = bind(expression)?;
+                let list = list
+                    .iter()
+                    .map(|e| BoundExpression::bind_with(e, resolve))
+                    .collect::<Result<Vec<BoundExpression>, String>>()?;
+                for item in list.iter() {
+                    check_comparable(&expression, item)?;
+                }
+                Ok(BoundExpression::InList {
+                    expression,
+                    list,
+                    negated: *negated,
+                })
+            }
+            // `x between a and b` is `x >= a and x <= b`.
+            Expression::Between {
+                expression,
+                low,
+                high,
+                negated,
+            } => {
+                let (expression, low, high) = (bind(expression)?, bind(low)?, bind(high)?);
+                check_comparable(&expression, &low)?;
+                check_comparable(&expression, &high)?;
+                let between = BoundExpression::And {
+                    left: Box::new(BoundExpression::Comparison {
+                        left: expression.clone(),
+                        operator: ComparisonOperator::GreaterThanOrEqual,
+                        right: low,
+                    }),
+                    right: Box::new(BoundExpression::Comparison {
+                        left: expression,
+                        operator: ComparisonOperator::LessThanOrEqual,
+                        right: high,
+                    }),
+                };
+                Ok(match negated {
+                    true => BoundExpression::Not {
+                        expression: Box::new(between),
+                    },
+                    false => between,
+                })
+            }
+            Expression::IsNull { expression, negated } => Ok(BoundExpression::IsNull {
+                expression: bind(expression)?,
+                negated: *negated,
+            }),
+            Expression::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                let operand = operand.as_ref().map(|o| bind(o)).transpose()?;
+                let mut bound_branches = Vec::new();
+                for (when, then) in branches {
+                    let bound_when = BoundExpression::bind_with(when, resolve)?;
+                    if operand.is_none() && bound_when.data_type().is_some_and(|t| t != DataType::Boolean) {
+                        return Err(format!("Case condition {} is not a boolean", when));
+                    }
+                    bound_branches.push((bound_when, BoundExpression::bind_with(then, resolve)?));
+                }
+                let otherwise = otherwise.as_ref().map(|o| bind(o)).transpose()?;
+
+                if let Some(operand) = &operand {
+                    let compared = bound_branches.iter().map(|(when, _)| when.data_type());
+                    common_type("values compared by case", std::iter::once(operand.data_type()).chain(compared))?;
+                }
+                common_type(
+                    "results of case",
+                    bound_branches
+                        .iter()
+                        .map(|(_, then)| then)
+                        .chain(otherwise.as_deref())
+                        .map(BoundExpression::data_type),
+                )?;
+                Ok(BoundExpression::Case {
+                    operand,
+                    branches: bound_branches,
+                    otherwise,
+                })
+            }
+        }
+    }
+
+    /// The type of the values the expression produces, if it's known.
+    pub fn data_type(&self) -> Option<DataType> {
+        match self {
+            BoundExpression::Column { data_type, .. } => *data_type,
+            BoundExpression::Literal { value } => DataType::of(value),
+            BoundExpression::Comparison { .. }
+            | BoundExpression::And { .. }
+            | BoundExpression::Or { .. }
+            | BoundExpression::Not { .. }
+            | BoundExpression::Exists { .. }
+            | BoundExpression::InSubquery { .. }
+            | BoundExpression::PatternMatch { .. }
+            | BoundExpression::InList { .. }
+            | BoundExpression::IsNull { .. } => Some(DataType::Boolean),
+            BoundExpression::Arithmetic { left, operator, right } => {
+                arithmetic_type(left.data_type(), *operator, right.data_type())
+            }
+            BoundExpression::Function { function, arguments } => function
+                .return_type(&arguments.iter().map(BoundExpression::data_type).collect::<Vec<_>>())
+                .ok()
+                .flatten(),
+            BoundExpression::Case {
+                branches, otherwise, ..
+            } => branches
+                .iter()
+                .map(|(_, then)| then)
+                .chain(otherwise.as_deref())
+                .find_map(BoundExpression::data_type),
+        }
+    }
+
+    pub fn evaluate(&self, row: &[Value]) -> Result<Value, String> {
+        match self {
+            BoundExpression::Column { index, .. } => Ok(row[*index].clone()),
+            BoundExpression::Literal { value } => Ok(value.clone()),
+            BoundExpression::Comparison {
+                left,
+                operator,
+                right,
+            } => {
+                let (left, right) = (left.evaluate(row)?, right.evaluate(row)?);
+                if left == Value::Null || right == Value::Null {
+                    return Ok(Value::Null);
+                }
+                let ordering = compare(&left, &right)?;
+                let value = match operator {
+                    ComparisonOperator::Equal => ordering == Ordering::Equal,
+                    ComparisonOperator::NotEqual => ordering != Ordering::Equal,
+                    ComparisonOperator::LessThan => ordering == Ordering::Less,
+                    ComparisonOperator::LessThanOrEqual => ordering != Ordering::Greater,
+                    ComparisonOperator::GreaterThan => ordering == Ordering::Greater,
+                    ComparisonOperator::GreaterThanOrEqual => ordering != Ordering::Less,
+                };
+                Ok(Value::Boolean { value })
+            }
+            BoundExpression::Arithmetic {
+                left,
+                operator,
+                right,
+            } => arithmetic(&left.evaluate(row)?, *operator, &right.evaluate(row)?),
+            // A null stands for a value that isn't known, so the result is
+            // only null when the unknown value would decide it.
+            BoundExpression::And { left, right } => {
+                let value = match left.truth(row)? {
+                    Some(false) => Some(false),
+                    left => match right.truth(row)? {
+                        Some(false) => Some(false),
+                        right => left.and(right),
+                    },
+                };
+                Ok(value.map_or(Value::Null, |value| Value::Boolean { value }))
+            }
+            BoundExpression::Or { left, right } => {
+                let value = match left.truth(row)? {
+                    Some(true) => Some(true),
+                    left => match right.truth(row)? {
+                        Some(true) => Some(true),
+                        right => left.and(right),
+                    },
+                };
+                Ok(value.map_or(Value::Null, |value| Value::Boolean { value }))
+            }
+            BoundExpression::Not { expression } => {
+                let value = expression.truth(row)?.map(|value| !value);
+                Ok(value.map_or(Value::Null, |value| Value::Boolean { value }))
+            }
+            BoundExpression::Function { function, arguments } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|a| a.evaluate(row))
+                    .collect::<Result<Vec<Value>, String>>()?;
+                function.evaluate(&arguments)
+            }
+            BoundExpression::Exists { subquery } => subquery.any_rows(row),
+            BoundExpression::InSubquery {
+                expression,
+                subquery,
+                negated,
+            } => match subquery.contains(&expression.evaluate(row)?, row)? {
+                Value::Boolean { value } => Ok(Value::Boolean { value: value != *negated }),
+                value => Ok(value),
+            },
+            BoundExpression::PatternMatch {
+                expression,
+                pattern,
+                negated,
+            } => {
+                let value = match expression.evaluate(row)? {
+                    Value::Null => return Ok(Value::Null),
+                    Value::Varchar { value } => value,
+                    other => return Err(format!("Only text can be matched with a pattern, not {}", other)),
+                };
+                let matched = match pattern {
+                    MatchPattern::Compiled(pattern) => pattern.matches(&value),
+                    MatchPattern::Dynamic { kind, pattern } => match pattern.evaluate(row)? {
+                        Value::Null => return Ok(Value::Null),
+                        Value::Varchar { value: pattern } => Pattern::compile(*kind, &pattern)?.matches(&value),
+                        other => return Err(format!("Only text can be a pattern, not {}", other)),
+                    },
+                };
+                Ok(Value::Boolean {
+                    value: matched != *negated,
+                })
+            }
+            // Like a chain of `=` comparisons joined by `or`, the answer is
+            // null rather than false when the value is null or isn't found
+            // but the list has a null.
+            BoundExpression::InList {
+                expression,
+                list,
+                negated,
+            } => {
+                let value = expression.evaluate(row)?;
+                if value == Value::Null {
+                    return Ok(Value::Null);
+                }
+                let mut has_null = false;
+                for item in list {
+                    match item.evaluate(row)? {
+                        Value::Null => has_null = true,
+                        item if compare(&value, &item)? == Ordering::Equal => {
+                            return Ok(Value::Boolean { value: !*negated });
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(match has_null {
+                    true => Value::Null,
+                    false => Value::Boolean { value: *negated },
+                })
+            }
+            BoundExpression::IsNull { expression, negated } => Ok(Value::Boolean {
+                value: (expression.evaluate(row)? == Value::Null) != *negated,
+            }),
+            BoundExpression::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                let operand = operand.as_ref().map(|o| o.evaluate(row)).transpose()?;
+                for (when, then) in branches {
+                    let matched = match &operand {
+                        // A null operand equals nothing.
+                        Some(Value::Null) => false,
+                        Some(operand) => {
+                            let when = when.evaluate(row)?;
+                            when != Value::Null && compare(operand, &when)? == Ordering::Equal
+                        }
+                        None => when.matches(row)?,
+                    };
+                    if matched {
+                        return then.evaluate(row);
+                    }
+                }
+                otherwise.as_ref().map_or(Ok(Value::Null), |o| o.evaluate(row))
+            }
+        }
+    }
+
+    /// Checks that a condition, like a `where` clause, is a boolean if its
+    /// type is known.
+    pub fn condition(self) -> Result<BoundExpression<'a>, String> {
+        match self.data_type() {
+            Some(data_type) if data_type != DataType::Boolean => {
+                Err(format!("Expected a boolean condition, but got {}", data_type))
+            }
+            _ => Ok(self),
+        }
+    }
+
+    /// Evaluates a predicate, which must produce a boolean. A null result
+    /// doesn't match.
+    pub fn matches(&self, row: &[Value]) -> Result<bool, String> {
+        Ok(self.truth(row)? == Some(true))
+    }
+
+    /// Evaluates a predicate to true, false or, when it's null, `None`.
+    fn truth(&self, row: &[Value]) -> Result<Option<bool>, String> {
+        match self.evaluate(row)? {
+            Value::Boolean { value } => Ok(Some(value)),
+            Value::Null => Ok(None),
+            value => Err(format!("Expected a boolean condition, but got {}", value)),
+        }
+    }
+}
+
+/// Compares values of the same type, numbers of any type by their value
+/// and dates with timestamps. Text compared with a date or time is read as
+/// one, so `Date < "2017-06-01"` compares dates.
+pub fn compare(left: &Value, right: &Value) -> Result<Ordering, String> {
+    if let Some(ordering) = left.compare_numbers(right).or_else(|| left.compare_times(right)) {
+        Ok(ordering)
+    } else if std::mem::discriminant(left) == std::mem::discriminant(right) {
+        Ok(left.cmp(right))
+    } else if let (Value::Varchar { value }, time) | (time, Value::Varchar { value }) = (left, right) {
+        let text = read_as(value, time).ok_or_else(|| format!("Cannot compare {} with {}", left, right))?;
+        match left {
+            Value::Varchar { .. } => compare(&text, right),
+            _ => compare(left, &text),
+        }
+    } else {
+        Err(format!("Cannot compare {} with {}", left, right))
+    }
+}
+
+/// Checks that operands whose types are known can be compared, as `compare`
+/// allows.
+fn check_comparable(left: &BoundExpression, right: &BoundExpression) -> Result<(), String> {
+    use DataType::*;
+
+    let comparable = |l: DataType, r: DataType| {
+        l == r
+            || matches!(
+                (l, r),
+                (Date, Timestamp) | (Timestamp, Date) | (Text, Date | Time | Timestamp | Interval)
+            )
+    };
+    match (left.data_type(), right.data_type()) {
+        (Some(l), Some(r)) if !comparable(l, r) && !comparable(r, l) => {
+            Err(format!("Cannot compare {} with {}", l, r))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Reads text as the same type of date or time as `like`.
+fn read_as(text: &str, like: &Value) -> Option<Value> {
+    match like {
+        Value::Date { .. } => Date::parse(text).map(|value| Value::Date { value }),
+        Value::Time { .. } => Time::parse(text).map(|value| Value::Time { value }),
+        Value::Timestamp { .. } => Timestamp::parse(text).map(|value| Value::Timestamp { value }),
+        Value::Interval { .. } => Interval::parse(text).map(|value| Value::Interval { value }),
+        _ => None,
+    }
+}
+
+/// Checks that the known types among `types` are all the same.
+fn common_type<I>(what: &str, types: I) -> Result<(), String>
+where
+    I: Iterator<Item = Option<DataType>>,
+{
+    let mut common = None;
+    for data_type in types.flatten() {
+        match common {
+            Some(common) if common != data_type => {
+                return Err(format!("The {} mix {} and {}", what, common, data_type));
+            }
+            _ => common = Some(data_type),
+        }
+    }
+    Ok(())
+}
+
+/// The type of an arithmetic operation's result, if its operands' types
+/// are known well enough to tell.
+fn arithmetic_type(left: Option<DataType>, operator: ArithmeticOperator, right: Option<DataType>) -> Option<DataType> {
+    use ArithmeticOperator::*;
+    use DataType::*;
+
+    match (left, operator, right) {
+        (_, Concat, _) => Some(Text),
+        (Some(Date), Add | Subtract, Some(Number)) | (Some(Number), Add, Some(Date)) => Some(Date),
+        (Some(Date), Subtract, Some(Date)) => Some(Number),
+        (Some(Date | Timestamp), Add | Subtract, Some(Interval)) | (Some(Interval), Add, Some(Date | Timestamp)) => {
+            Some(Timestamp)
+        }
+        (Some(Date | Timestamp), Subtract, Some(Date | Timestamp)) => Some(Interval),
+        (Some(Time), Add | Subtract, Some(Interval)) | (Some(Interval), Add, Some(Time)) => Some(Time),
+        (Some(Time), Subtract, Some(Time)) => Some(Interval),
+        (Some(Interval), Add | Subtract, Some(Interval))
+        | (Some(Interval), Multiply | Divide, Some(Number))
+        | (Some(Number), Multiply, Some(Interval)) => Some(Interval),
+        (Some(Number), _, Some(Number)) | (Some(Number), _, None) | (None, _, Some(Number)) => Some(Number),
+        _ => None,
+    }
+}
+
+/// Arithmetic on dates and times: moving them by intervals, or dates by a
+/// number of days, and measuring the time between them. Intervals add up
+/// and can be multiplied or divided by a number. `None` means the
+/// operation doesn't apply to the operands.
+fn time_arithmetic(left: &Value, operator: ArithmeticOperator, right: &Value) -> Option<Option<Value>> {
+    use ArithmeticOperator::*;
+
+    let date = |value: Option<Date>| value.map(|value| Value::Date { value });
+    let timestamp = |value: Option<Timestamp>| value.map(|value| Value::Timestamp { value });
+    let interval = |value: Option<Interval>| value.map(|value| Value::Interval { value });
+    let as_timestamp = |value: &Value| match value {
+        Value::Date { value } => Some(value.at_midnight()),
+        Value::Timestamp { value } => Some(*value),
+        _ => None,
+    };
+
+    Some(match (left, operator, right) {
+        (Value::Date { value }, Add | Subtract, days) | (days, Add, Value::Date { value }) if days.whole().is_some() => {
+            let days = days.whole().and_then(|days| i64::try_from(days).ok());
+            let days = if operator == Subtract { days.and_then(i64::checked_neg) } else { days };
+            date(days.and_then(|days| value.add_days(days)))
+        }
+        (Value::Date { value: l }, Subtract, Value::Date { value: r }) => Some(Value::Int {
+            value: l.days_since(r),
+        }),
+        (l, Add, Value::Interval { value }) | (Value::Interval { value }, Add, l) if as_timestamp(l).is_some() => {
+            timestamp(as_timestamp(l).unwrap().add(value))
+        }
+        (l, Subtract, Value::Interval { value }) if as_timestamp(l).is_some() => {
+            timestamp(as_timestamp(l).unwrap().subtract(value))
+        }
+        (l, Subtract, r) if as_timestamp(l).is_some() && as_timestamp(r).is_some() => {
+            Some(Value::Interval {
+                value: as_timestamp(l).unwrap().since(&as_timestamp(r).unwrap()),
+            })
+        }
+        (Value::Time { value: time }, Add | Subtract, Value::Interval { value })
+        | (Value::Interval { value }, Add, Value::Time { value: time }) => {
+            let value = if operator == Subtract { value.negate() } else { Some(*value) };
+            value.map(|value| Value::Time { value: time.add(&value) })
+        }
+        (Value::Time { value: l }, Subtract, Value::Time { value: r }) => Some(Value::Interval { value: l.since(r) }),
+        (Value::Interval { value: l }, Add, Value::Interval { value: r }) => interval(l.add(r)),
+        (Value::Interval { value: l }, Subtract, Value::Interval { value: r }) => interval(l.subtract(r)),
+        (Value::Interval { value }, Multiply, factor) | (factor, Multiply, Value::Interval { value })
+            if factor.is_number() =>
+        {
+            interval(value.multiply(factor.double().unwrap()))
+        }
+        (Value::Interval { value }, Divide, divisor) if divisor.is_number() => {
+            interval(value.divide(divisor.double().unwrap()))
+        }
+        _ => return None,
+    })
+}
+
+pub fn arithmetic(left: &Value, operator: ArithmeticOperator, right: &Value) -> Result<Value, String> {
+    // Anything can be concatenated, as its text.
+    if operator == ArithmeticOperator::Concat {
+        return Ok(match (left, right) {
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            (l, r) => Value::Varchar {
+                value: to_text(l) + &to_text(r),
+            },
+        });
+    }
+
+    if *left == Value::Null || *right == Value::Null {
+        return Ok(Value::Null);
+    }
+    if operator == ArithmeticOperator::Divide && right.double() == Some(0.0) {
+        return Err("Division by zero".to_string());
+    }
+    if let Some(value) = time_arithmetic(left, operator, right) {
+        return value.ok_or(format!("Result of {:?} on {} and {} is out of range", operator, left, right));
+    }
+
+    // Whole numbers stay whole, and stay unsigned unless an int is
+    // involved. A decimal makes the result an exact decimal, and a double
+    // makes it a double.
+    let value = match (left, right) {
+        (Value::Double { .. }, _) | (_, Value::Double { .. }) => match (left.double(), right.double()) {
+            (Some(l), Some(r)) => Some(Value::Double {
+                value: match operator {
+                    ArithmeticOperator::Add => l + r,
+                    ArithmeticOperator::Subtract => l - r,
+                    ArithmeticOperator::Multiply => l * r,
+                    ArithmeticOperator::Divide => l / r,
+                    ArithmeticOperator::Concat => unreachable!("handled above"),
+                },
+            }),
+            _ => return Err(format!("Cannot apply {:?} to {} and {}", operator, left, right)),
+        },
+        (Value::Decimal { .. }, _) | (_, Value::Decimal { .. }) => match (left.decimal(), right.decimal()) {
+            (Some(l), Some(r)) => match operator {
+                ArithmeticOperator::Add => l.add(&r),
+                ArithmeticOperator::Subtract => l.subtract(&r),
+                ArithmeticOperator::Multiply => l.multiply(&r),
+                ArithmeticOperator::Divide => l.divide(&r),
+                ArithmeticOperator::Concat => unreachable!("handled above"),
+            }
+            .map(|value| Value::Decimal { value }),
+            _ => return Err(format!("Cannot apply {:?} to {} and {}", operator, left, right)),
+        },
+        _ => match (left.whole(), right.whole()) {
+            (Some(l), Some(r)) => match operator {
+                ArithmeticOperator::Add => l.checked_add(r),
+                ArithmeticOperator::Subtract => l.checked_sub(r),
+                ArithmeticOperator::Multiply => l.checked_mul(r),
+                ArithmeticOperator::Divide => l.checked_div(r),
+                ArithmeticOperator::Concat => unreachable!("handled above"),
+            }
+            .and_then(|value| {
+                let unsigned = matches!((left, right), (Value::Number { .. }, Value::Number { .. }));
+                Value::from_whole(value, unsigned)
+            }),
+            _ => return Err(format!("Cannot apply {:?} to {} and {}", operator, left, right)),
+        },
+    };
+    value.ok_or(format!("Result of {:?} on {} and {} is out of range", operator, left, right))
+}
+
+pub fn bind_where_clause<'a>(
+    where_clause: &Option<Expression>,
+    scope: &Scope,
+) -> Result<Option<BoundExpression<'a>>, String> {
+    where_clause
+        .as_ref()
+        .map(|w| BoundExpression::bind(w, scope).and_then(BoundExpression::condition))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decimal::Decimal, sql_parser::InsertValue, table::ColumnType};
+
+    fn column_specs() -> Vec<ColumnSpec> {
+        vec![
+            ColumnSpec {
+                column_name: "name".to_string(),
+                column_type: ColumnType::Varchar { max_len: 10 },
+                not_null: false,
+            },
+            ColumnSpec {
+                column_name: "age".to_string(),
+                column_type: ColumnType::Number,
+                not_null: false,
+            },
+        ]
+    }
+
+    fn row() -> Vec<Value> {
+        vec![
+            Value::Varchar {
+                value: "Martin".to_string(),
+            },
+            Value::Number { value: 35 },
+        ]
+    }
+
+    fn bind(input: &str) -> Result<BoundExpression<'static>, String> {
+        let (remaining, expression) = Expression::parse(input).unwrap();
+        assert_eq!("", remaining);
+        BoundExpression::bind(&expression, &Scope::table("person", &column_specs()))
+    }
+
+    fn condition(input: &str) -> BoundExpression<'static> {
+        bind(input).unwrap()
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert!(condition("name = \"Martin\"").matches(&row()).unwrap());
+        assert!(!condition("name <> \"Martin\"").matches(&row()).unwrap());
+        assert!(condition("age > 34").matches(&row()).unwrap());
+        assert!(condition("age >= 35").matches(&row()).unwrap());
+        assert!(!condition("age < 35").matches(&row()).unwrap());
+        assert!(condition("36 > age").matches(&row()).unwrap());
+        assert!(condition("age = 35 and name = \"Martin\"").matches(&row()).unwrap());
+        assert!(!condition("age = 35 and name = \"Laura\"").matches(&row()).unwrap());
+    }
+
+    #[test]
+    fn test_logic() {
+        assert!(condition("age = 1 or name = \"Martin\"").matches(&row()).unwrap());
+        assert!(!condition("not age = 35").matches(&row()).unwrap());
+        assert!(condition("not not age = 35").matches(&row()).unwrap());
+        assert!(condition("age = 1 and age = 2 or age = 35").matches(&row()).unwrap());
+        assert!(!condition("age = 1 and (age = 2 or age = 35)").matches(&row()).unwrap());
+        assert!(condition("(age + 1) * 2 = 72").matches(&row()).unwrap());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let value = |input| condition(input).evaluate(&row()).unwrap();
+        assert_eq!(Value::Number { value: 41 }, value("age + 2 * 3"));
+        assert_eq!(Value::Number { value: 17 }, value("age / 2"));
+        assert_eq!(Value::Number { value: 0 }, value("age - 35"));
+        assert!(condition("age * 2 = 70").matches(&row()).unwrap());
+
+        assert!(condition("age - 36").evaluate(&row()).is_err());
+        assert!(condition("age / 0").evaluate(&row()).is_err());
+
+        // An int makes the result an int, a decimal a decimal and a double a
+        // double.
+        let decimal = |text| Value::Decimal {
+            value: Decimal::parse(text).unwrap(),
+        };
+        assert_eq!(Value::Int { value: -1 }, value("age + -36"));
+        assert_eq!(Value::Int { value: -3 }, value("-7 / 2"));
+        assert_eq!(decimal("17.5"), value("age / 2.0"));
+        assert_eq!(decimal("34.75"), value("age - 0.25"));
+        assert_eq!(decimal("0.3"), value("0.1 + 0.2"));
+        assert_eq!(decimal("0.333333"), value("1.0 / 3"));
+        assert_eq!(decimal("-0.0625"), value("0.25 * -0.25"));
+        assert_eq!(Value::Double { value: 17.5 }, value("age / 2e0"));
+        assert_eq!(Value::Double { value: 0.5 }, value("0.25 + 25e-2"));
+        assert!(condition("age / 0.0").evaluate(&row()).is_err());
+        assert!(condition("9999999999999999999999999999999999999.9 * 10.00").evaluate(&row()).is_err());
+        assert!(condition("-9223372036854775807 - 2").evaluate(&row()).is_err());
+
+        assert!(condition("age = 35.0").matches(&row()).unwrap());
+        assert!(condition("age > -1").matches(&row()).unwrap());
+        assert!(condition("age < 35.5").matches(&row()).unwrap());
+        assert!(condition("age between -5 and 35.0").matches(&row()).unwrap());
+        assert!(condition("age in (1.5, 35.0)").matches(&row()).unwrap());
+        assert!(condition("0.1 + 0.2 = 0.3").matches(&row()).unwrap());
+        assert!(condition("age = 3.5e1").matches(&row()).unwrap());
+    }
+
+    #[test]
+    fn test_unknown_column() {
+        let expression = Expression::Comparison {
+            left: Box::new(Expression::Column {
+                table_name: None,
+                column_name: "height".to_string(),
+            }),
+            operator: ComparisonOperator::Equal,
+            right: Box::new(Expression::Literal {
+                value: InsertValue::Number { value: 1 },
+            }),
+        };
+
+        assert_eq!(
+            Err("Unknown column 'height'".to_string()),
+            BoundExpression::bind(&expression, &Scope::table("person", &column_specs()))
+        );
+    }
+
+    #[test]
+    fn test_scope() {
+        let pet = Scope::table(
+            "pet",
+            &[ColumnSpec {
+                column_name: "name".to_string(),
+                column_type: ColumnType::Varchar { max_len: 10 },
+                not_null: false,
+            }],
+        );
+        let scope = Scope::table("person", &column_specs()).join(&pet);
+
+        assert_eq!(Ok(1), scope.resolve(&None, "age"));
+        assert_eq!(Ok(0), scope.resolve(&Some("person".to_string()), "name"));
+        assert_eq!(Ok(2), scope.resolve(&Some("pet".to_string()), "name"));
+        assert_eq!(
+            Err("Column 'name' is ambiguous".to_string()),
+            scope.resolve(&None, "name")
+        );
+        assert_eq!(
+            Err("Unknown column 'pet.age'".to_string()),
+            scope.resolve(&Some("pet".to_string()), "age")
+        );
+    }
+
+    #[test]
+    fn test_type_mismatch() {
+        // Operands whose types are known are checked when binding.
+        assert!(bind("name = 35").is_err());
+        assert!(bind("name + 1").is_err());
+        assert!(bind("age in (1, \"35\")").is_err());
+        assert!(bind("age between 1 and \"40\"").is_err());
+        assert!(bind("date \"2017-01-01\" = 1").is_err());
+        assert!(bind("date \"2017-01-01\" * 2").is_err());
+        assert!(bind("not age").is_err());
+        assert!(bind("name and age > 1").is_err());
+        assert!(bind("age > 1 or age").is_err());
+
+        assert!(bind("name || 1 = \"Martin1\"").is_ok());
+        assert!(bind("date \"2017-01-01\" = \"2017-01-01\"").is_ok());
+        assert!(bind("\"2017-01-01\" < timestamp \"2017-01-01 00:00\"").is_ok());
+        assert!(bind("not nullif(age > 1, true)").is_ok());
+        assert!(condition("age").condition().is_err());
+        assert!(condition("age > 1").condition().is_ok());
+    }
+
+    #[test]
+    fn test_functions() {
+        let value = |input| condition(input).evaluate(&row()).unwrap();
+        let varchar = |value: &str| Value::Varchar {
+            value: value.to_string(),
+        };
+        assert_eq!(varchar("MARTIN"), value("upper(name)"));
+        assert_eq!(varchar("martin"), value("LOWER(name)"));
+        assert!(condition("upper(name) = \"MARTIN\"").matches(&row()).unwrap());
+
+        let scope = Scope::table("person", &column_specs());
+        let bind = |input| BoundExpression::bind(&Expression::parse(input).unwrap().1, &scope);
+        assert_eq!(Err("Unknown function 'shout'".to_string()), bind("shout(name)"));
+        assert!(bind("upper(name, age)").is_err());
+        assert!(bind("upper()").is_err());
+        assert_eq!(
+            Err("upper expects text for argument 1 but was given number".to_string()),
+            bind("upper(age)")
+        );
+    }
+
+    #[test]
+    fn test_function_library() {
+        let value = |input| condition(input).evaluate(&row()).unwrap();
+        let varchar = |value: &str| Value::Varchar {
+            value: value.to_string(),
+        };
+        let number = |value| Value::Number { value };
+
+        assert_eq!(number(6), value("length(name)"));
+        assert_eq!(varchar("art"), value("substr(name, 2, 3)"));
+        assert_eq!(varchar("rtin"), value("substr(name, 3)"));
+        assert_eq!(varchar("M"), value("substr(name, 0, 2)"));
+        assert_eq!(varchar(""), value("substr(name, 10)"));
+        assert_eq!(varchar("Martin"), value("trim(\"  Martin \")"));
+        assert_eq!(varchar("Mirtin"), value("replace(name, \"a\", \"i\")"));
+        assert_eq!(varchar("Martin"), value("replace(name, \"\", \"i\")"));
+        assert_eq!(varchar("Martin is 35"), value("concat(name, \" is \", age)"));
+        assert_eq!(varchar("Martin35"), value("name || age"));
+        assert_eq!(number(35), value("abs(age)"));
+        assert_eq!(number(35), value("round(age, 1)"));
+        assert_eq!(number(5), value("mod(age, 10)"));
+        assert!(condition("mod(age, 0)").evaluate(&row()).is_err());
+        let int = |value| Value::Int { value };
+        let decimal = |text| Value::Decimal {
+            value: Decimal::parse(text).unwrap(),
+        };
+        let double = |value| Value::Double { value };
+        assert_eq!(int(7), value("abs(-7)"));
+        assert_eq!(decimal("2.5"), value("abs(-2.5)"));
+        assert_eq!(double(2.5), value("abs(-25e-1)"));
+        assert_eq!(decimal("3"), value("round(2.5)"));
+        assert_eq!(decimal("-1.24"), value("round(-1.2351, 2)"));
+        assert_eq!(decimal("1200"), value("round(1249.99, -2)"));
+        assert_eq!(double(-1.24), value("round(-1.2351e0, 2)"));
+        assert_eq!(double(2.5), value("round(25e-1, 400)"));
+        assert_eq!(double(1e300), value("round(1e300, 20)"));
+        assert_eq!(number(12300), value("round(12345, -2)"));
+        assert_eq!(int(-12350), value("round(-12345, -1)"));
+        assert_eq!(number(0), value("round(age, -2)"));
+        assert_eq!(number(0), value("round(age, -400)"));
+        assert!(condition("round(18446744073709551615, -1)").evaluate(&row()).is_err());
+        assert_eq!(int(-1), value("mod(-7, 3)"));
+        assert_eq!(number(1), value("mod(age, -2)"));
+        assert_eq!(decimal("3.5"), value("mod(age, 4.5)"));
+        assert_eq!(double(3.5), value("mod(age, 45e-1)"));
+        assert_eq!(varchar("Ma"), value("substr(name, -1, 4)"));
+        assert!(condition("substr(name, 1.5)").evaluate(&row()).is_err());
+
+        assert_eq!(Value::Null, value("nullif(age, 35)"));
+        assert_eq!(number(35), value("nullif(age, 1)"));
+        assert_eq!(Value::Null, value("nullif(age, 35.0)"));
+        assert_eq!(Value::Null, value("nullif(1, -1 * -1)"));
+        assert_eq!(Value::Null, value("nullif(null, 1)"));
+        assert_eq!(number(1), value("nullif(1, null)"));
+        assert_eq!(number(1), value("coalesce(nullif(age, 35), 1)"));
+        assert_eq!(Value::Null, value("coalesce(nullif(age, 35), nullif(age, 35))"));
+        assert_eq!(varchar("Martin"), value("concat(nullif(age, 35), name)"));
+        assert_eq!(Value::Null, value("nullif(name, name) || name"));
+        assert_eq!(Value::Null, value("length(nullif(name, name))"));
+
+        let scope = Scope::table("person", &column_specs());
+        let bind = |input| BoundExpression::bind(&Expression::parse(input).unwrap().1, &scope);
+        assert_eq!(
+            Err("substr expects number for argument 2 but was given text".to_string()),
+            bind("substr(name, name)")
+        );
+        assert!(bind("length(age > 1)").is_err());
+        assert!(bind("abs(upper(name))").is_err());
+        assert!(bind("coalesce(name, age)").is_err());
+        assert!(bind("nullif(name)").is_err());
+        assert!(bind("concat()").is_err());
+        assert!(bind("substr(name)").is_err());
+        assert!(bind("substr(name, 1, 2, 3)").is_err());
+        assert_eq!(Some(DataType::Number), bind("length(name) + 1").unwrap().data_type());
+        assert_eq!(Some(DataType::Text), bind("coalesce(name, \"x\")").unwrap().data_type());
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        let value = |input| condition(input).evaluate(&row()).unwrap();
+        let boolean = |value| Value::Boolean { value };
+
+        assert_eq!(boolean(true), value("name like \"Mar%\""));
+        assert_eq!(boolean(false), value("name like \"mar%\""));
+        assert_eq!(boolean(true), value("name ilike \"mar%\""));
+        assert_eq!(boolean(true), value("name not like \"M_rtin_\""));
+        assert_eq!(boolean(true), value("name ~ \"rt\""));
+        assert_eq!(boolean(false), value("name !~ \"^M.*n$\""));
+        assert_eq!(boolean(true), value("name like substr(name, 1, 2) || \"%\""));
+        assert_eq!(Value::Null, value("nullif(name, name) like \"%\""));
+        assert_eq!(Value::Null, value("name like nullif(name, name)"));
+
+        let scope = Scope::table("person", &column_specs());
+        let bind = |input| BoundExpression::bind(&Expression::parse(input).unwrap().1, &scope);
+        match bind("name like \"M%\"").unwrap() {
+            BoundExpression::PatternMatch {
+                pattern: MatchPattern::Compiled(_),
+                ..
+            } => {}
+            other => panic!("Pattern not compiled: {:?}", other),
+        }
+        assert_eq!(
+            Err("Only text can be matched with a pattern, not number".to_string()),
+            bind("age like \"3%\"")
+        );
+        assert!(bind("name ~ \"(\"").is_err());
+        assert!(condition("name ~ upper(\"(\")").evaluate(&row()).is_err());
+    }
+
+    #[test]
+    fn test_predicates() {
+        let value = |input| condition(input).evaluate(&row()).unwrap();
+        let boolean = |value| Value::Boolean { value };
+
+        assert_eq!(boolean(true), value("name in (\"Laura\", \"Martin\")"));
+        assert_eq!(boolean(false), value("name not in (\"Laura\", \"Martin\")"));
+        assert_eq!(boolean(true), value("age in (1, 30 + 5)"));
+        assert_eq!(boolean(true), value("(age > 1) in (false, true)"));
+        assert_eq!(Value::Null, value("age in (1, nullif(age, 35))"));
+        assert_eq!(boolean(true), value("age in (35, nullif(age, 35))"));
+        assert_eq!(Value::Null, value("nullif(age, 35) in (1)"));
+
+        assert_eq!(boolean(true), value("age between 35 and 40"));
+        assert_eq!(boolean(false), value("age between 36 and 40"));
+        assert_eq!(boolean(true), value("age not between 36 and 40"));
+        assert_eq!(boolean(true), value("name between \"M\" and \"N\""));
+        assert_eq!(boolean(false), value("age between 40 and 30"));
+
+        assert_eq!(boolean(false), value("name is null"));
+        assert_eq!(boolean(true), value("name is not null"));
+        assert_eq!(boolean(true), value("nullif(age, 35) is null"));
+        assert_eq!(boolean(false), value("(nullif(age, 35) > 1) is not null"));
+    }
+
+    #[test]
+    fn test_three_valued_logic() {
+        let value = |input| condition(input).evaluate(&row()).unwrap();
+        let boolean = |value| Value::Boolean { value };
+
+        assert_eq!(Value::Null, value("null and true"));
+        assert_eq!(boolean(false), value("null and false"));
+        assert_eq!(boolean(false), value("age < 1 and nullif(age, 35) > 1"));
+        assert_eq!(boolean(true), value("null or true"));
+        assert_eq!(Value::Null, value("null or false"));
+        assert_eq!(Value::Null, value("not null"));
+        assert_eq!(Value::Null, value("not (nullif(age, 35) > 1 or age < 1)"));
+        assert_eq!(boolean(true), value("null is null"));
+        assert_eq!(Value::Null, value("age between 1 and null"));
+        assert_eq!(boolean(false), value("age between 40 and null"));
+        assert!(!condition("not (age > 1 and null)").matches(&row()).unwrap());
+    }
+
+    #[test]
+    fn test_case() {
+        let value = |input| condition(input).evaluate(&row()).unwrap();
+        let varchar = |value: &str| Value::Varchar {
+            value: value.to_string(),
+        };
+
+        assert_eq!(
+            varchar("adult"),
+            value("case when age < 18 then \"child\" when age < 65 then \"adult\" else \"senior\" end")
+        );
+        assert_eq!(varchar("M"), value("case substr(name, 1, 1) when \"L\" then \"L\" when \"M\" then \"M\" end"));
+        assert_eq!(Value::Null, value("case when age > 100 then \"old\" end"));
+        assert_eq!(Value::Null, value("case nullif(age, 35) when nullif(age, 35) then 1 end"));
+        assert!(condition("case when age = 35 then true else false end").matches(&row()).unwrap());
+
+        let scope = Scope::table("person", &column_specs());
+        let bind = |input| BoundExpression::bind(&Expression::parse(input).unwrap().1, &scope);
+        assert_eq!(
+            Err("The results of case mix text and number".to_string()),
+            bind("case when age > 1 then name else 1 end")
+        );
+        assert!(bind("case age when \"x\" then 1 end").is_err());
+        assert_eq!(Err("Case condition age is not a boolean".to_string()), bind("case when age then 1 end"));
+        assert!(bind("case when age > 1 then height end").is_err());
+    }
+
+    #[test]
+    fn test_dates_and_times() {
+        let value = |input| condition(input).evaluate(&row()).unwrap();
+        let date = |text| Value::Date {
+            value: Date::parse(text).unwrap(),
+        };
+        let timestamp = |text| Value::Timestamp {
+            value: Timestamp::parse(text).unwrap(),
+        };
+        let interval = |text| Value::Interval {
+            value: Interval::parse(text).unwrap(),
+        };
+
+        assert!(condition("date \"2017-01-01\" < date \"2017-01-02\"").matches(&row()).unwrap());
+        assert!(condition("date \"2017-01-01\" = \"2017-01-01\"").matches(&row()).unwrap());
+        assert!(condition("date \"2017-01-01\" = timestamp \"2017-01-01 00:00\"").matches(&row()).unwrap());
+        assert!(condition("time \"09:30\" between \"09:00\" and \"10:00\"").matches(&row()).unwrap());
+        assert!(condition("date \"2017-01-01\" = \"January\"").matches(&row()).is_err());
+
+        assert_eq!(date("2017-03-01"), value("date \"2017-02-27\" + 2"));
+        assert_eq!(date("2016-12-31"), value("date \"2017-01-01\" - 1"));
+        assert_eq!(Value::Int { value: 59 }, value("date \"2017-03-01\" - date \"2017-01-01\""));
+        assert_eq!(timestamp("2017-02-28 00:00"), value("date \"2017-01-31\" + interval \"1 month\""));
+        assert_eq!(timestamp("2016-12-31 23:00"), value("date \"2017-01-01\" - interval \"1 hour\""));
+        assert_eq!(interval("1 day 01:30"), value("timestamp \"2017-01-02 01:30\" - date \"2017-01-01\""));
+        assert_eq!(
+            Value::Time {
+                value: Time::parse("00:30").unwrap()
+            },
+            value("time \"23:00\" + interval \"90 minutes\"")
+        );
+        assert_eq!(interval("3 days 01:00"), value("interval \"1 day 20 minutes\" * 3"));
+        assert_eq!(interval("12:00"), value("interval \"1 day\" / 2"));
+        assert!(condition("date \"9999-12-31\" + 1").evaluate(&row()).is_err());
+
+        assert_eq!(Value::Int { value: 2017 }, value("extract(year from date \"2017-06-15\")"));
+        assert_eq!(Value::Int { value: 2 }, value("extract(quarter from timestamp \"2017-06-15 10:00\")"));
+        assert_eq!(
+            Value::Decimal {
+                value: Decimal::parse("5.250000").unwrap()
+            },
+            value("extract(second from time \"10:00:05.25\")")
+        );
+        assert_eq!(date("2017-06-01"), value("date_trunc(\"month\", date \"2017-06-15\")"));
+        assert_eq!(timestamp("2017-06-15 10:00"), value("date_trunc(\"hour\", timestamp \"2017-06-15 10:42\")"));
+        assert!(condition("extract(hour from date \"2017-06-15\")").evaluate(&row()).is_err());
+
+        let scope = Scope::table("person", &column_specs());
+        let bind = |input| BoundExpression::bind(&Expression::parse(input).unwrap().1, &scope);
+        assert_eq!(Some(DataType::Timestamp), bind("date \"2017-01-01\" + interval \"1 day\"").unwrap().data_type());
+        assert_eq!(Some(DataType::Date), bind("date_trunc(\"year\", date \"2017-01-01\")").unwrap().data_type());
+        assert_eq!(
+            Err("extract can't be applied to text".to_string()),
+            bind("extract(year from name)")
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use std::cmp::Ordering;
+
+use crate::{
+    datetime::FieldValue,
+    decimal::Decimal,
+    expression::{self, DataType},
+    table::Value,
+};
+
+/// A function computing one value from the values of its arguments within a
+/// row, as opposed to an aggregate, which computes one over many rows.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ScalarFunction {
+    Upper,
+    Lower,
+    Length,
+    Substr,
+    Trim,
+    Replace,
+    Concat,
+    Coalesce,
+    NullIf,
+    Abs,
+    Round,
+    Mod,
+    Extract,
+    DateTrunc,
+}
+
+/// The arguments a function takes.
+struct Signature {
+    /// The type of each parameter, or `None` for one taking any type.
+    parameters: &'static [Option<DataType>],
+    /// How many arguments must be given. Parameters after those are
+    /// optional.
+    required: usize,
+    /// Whether the last parameter can be repeated.
+    variadic: bool,
+}
+
+impl Signature {
+    /// The type the argument at `index` must have, if it matters.
+    fn parameter(&self, index: usize) -> Option<DataType> {
+        self.parameters[index.min(self.parameters.len() - 1)]
+    }
+}
+
+const TEXT: Option<DataType> = Some(DataType::Text);
+const NUMBER: Option<DataType> = Some(DataType::Number);
+
+impl ScalarFunction {
+    const ALL: [ScalarFunction; 14] = [
+        ScalarFunction::Upper,
+        ScalarFunction::Lower,
+        ScalarFunction::Length,
+        ScalarFunction::Substr,
+        ScalarFunction::Trim,
+        ScalarFunction::Replace,
+        ScalarFunction::Concat,
+        ScalarFunction::Coalesce,
+        ScalarFunction::NullIf,
+        ScalarFunction::Abs,
+        ScalarFunction::Round,
+        ScalarFunction::Mod,
+        ScalarFunction::Extract,
+        ScalarFunction::DateTrunc,
+    ];
+
+    pub fn lookup(name: &str) -> Result<ScalarFunction, String> {
+        ScalarFunction::ALL
+            .into_iter()
+            .find(|f| f.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Unknown function '{}'", name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScalarFunction::Upper => "upper",
+            ScalarFunction::Lower => "lower",
+            ScalarFunction::Length => "length",
+            ScalarFunction::Substr => "substr",
+            ScalarFunction::Trim => "trim",
+            ScalarFunction::Replace => "replace",
+            ScalarFunction::Concat => "concat",
+            ScalarFunction::Coalesce => "coalesce",
+            ScalarFunction::NullIf => "nullif",
+            ScalarFunction::Abs => "abs",
+            ScalarFunction::Round => "round",
+            ScalarFunction::Mod => "mod",
+            ScalarFunction::Extract => "extract",
+            ScalarFunction::DateTrunc => "date_trunc",
+        }
+    }
+
+    fn signature(&self) -> Signature {
+        let fixed = |parameters, required| Signature {
+            parameters,
+            required,
+            variadic: false,
+        };
+        match self {
+            ScalarFunction::Upper | ScalarFunction::Lower | ScalarFunction::Length | ScalarFunction::Trim => {
+                fixed(&[TEXT], 1)
+            }
+            ScalarFunction::Substr => fixed(&[TEXT, NUMBER, NUMBER], 2),
+            ScalarFunction::Replace => fixed(&[TEXT, TEXT, TEXT], 3),
+            ScalarFunction::Concat | ScalarFunction::Coalesce => Signature {
+                parameters: &[None],
+                required: 1,
+                variadic: true,
+            },
+            ScalarFunction::NullIf => fixed(&[None, None], 2),
+            ScalarFunction::Abs => fixed(&[NUMBER], 1),
+            ScalarFunction::Round => fixed(&[NUMBER, NUMBER], 1),
+            ScalarFunction::Mod => fixed(&[NUMBER, NUMBER], 2),
+            // The first argument names a field, like `year`.
+            ScalarFunction::Extract | ScalarFunction::DateTrunc => fixed(&[TEXT, None], 2),
+        }
+    }
+
+    /// Checks the number and the known types of the arguments a call is
+    /// given, and works out the type of its result.
+    pub fn return_type(&self, argument_types: &[Option<DataType>]) -> Result<Option<DataType>, String> {
+        let signature = self.signature();
+        let count = argument_types.len();
+        let maximum = signature.parameters.len();
+        if signature.variadic && count < signature.required {
+            return Err(format!(
+                "{} takes at least {} argument(s) but was given {}",
+                self.name(),
+                signature.required,
+                count
+            ));
+        } else if !signature.variadic && signature.required == maximum && count != maximum {
+            return Err(format!("{} takes {} argument(s) but was given {}", self.name(), maximum, count));
+        } else if !signature.variadic && !(signature.required..=maximum).contains(&count) {
+            return Err(format!(
+                "{} takes {} to {} arguments but was given {}",
+                self.name(),
+                signature.required,
+                maximum,
+                count
+            ));
+        }
+
+        for (index, argument_type) in argument_types.iter().enumerate() {
+            if let (Some(expected), Some(argument_type)) = (signature.parameter(index), argument_type) {
+                if expected != *argument_type {
+                    return Err(format!(
+                        "{} expects {} for argument {} but was given {}",
+                        self.name(),
+                        expected,
+                        index + 1,
+                        argument_type
+                    ));
+                }
+            }
+        }
+
+        match self {
+            ScalarFunction::Length | ScalarFunction::Abs | ScalarFunction::Round | ScalarFunction::Mod => {
+                Ok(Some(DataType::Number))
+            }
+            ScalarFunction::Extract => match argument_types[1] {
+                Some(DataType::Date | DataType::Time | DataType::Timestamp | DataType::Interval) | None => {
+                    Ok(Some(DataType::Number))
+                }
+                Some(other) => Err(format!("extract can't be applied to {}", other)),
+            },
+            // The result is truncated, but of the same type.
+            ScalarFunction::DateTrunc => match argument_types[1] {
+                Some(DataType::Date | DataType::Timestamp) | None => Ok(argument_types[1]),
+                Some(other) => Err(format!("date_trunc can't be applied to {}", other)),
+            },
+            // The result is one of the arguments, so they must agree.
+            ScalarFunction::Coalesce | ScalarFunction::NullIf => {
+                let mut known = argument_types.iter().flatten();
+                let first = known.next().copied();
+                match known.find(|t| Some(**t) != first) {
+                    Some(other) => Err(format!(
+                        "{} arguments must have the same type, but mix {} and {}",
+                        self.name(),
+                        first.unwrap(),
+                        other
+                    )),
+                    None => Ok(first),
+                }
+            }
+            _ => Ok(Some(DataType::Text)),
+        }
+    }
+
+    /// Applies the function. A null argument makes the result null, except
+    /// for `concat`, which leaves nulls out, and `coalesce` and `nullif`,
+    /// which are about nulls.
+    pub fn evaluate(&self, arguments: &[Value]) -> Result<Value, String> {
+        let signature = self.signature();
+        // Arguments whose type wasn't known when the call was bound.
+        for (index, argument) in arguments.iter().enumerate() {
+            if let (Some(expected), Some(actual)) = (signature.parameter(index), DataType::of(argument)) {
+                if expected != actual {
+                    return Err(format!(
+                        "{} expects {} for argument {} but was given {}",
+                        self.name(),
+                        expected,
+                        index + 1,
+                        argument
+                    ));
+                }
+            }
+        }
+
+        match self {
+            ScalarFunction::Concat => {
+                return Ok(Value::Varchar {
+                    value: arguments.iter().filter(|a| **a != Value::Null).map(to_text).collect(),
+                })
+            }
+            ScalarFunction::Coalesce => {
+                return Ok(arguments.iter().find(|a| **a != Value::Null).cloned().unwrap_or(Value::Null));
+            }
+            ScalarFunction::NullIf => {
+                // Equal as `=` has it, so numbers of different types may be.
+                let equal = !arguments.contains(&Value::Null)
+                    && expression::compare(&arguments[0], &arguments[1])? == Ordering::Equal;
+                return Ok(if equal { Value::Null } else { arguments[0].clone() });
+            }
+            _ if arguments.contains(&Value::Null) => return Ok(Value::Null),
+            _ => {}
+        }
+
+        let text = |index: usize| match &arguments[index] {
+            Value::Varchar { value } => value.as_str(),
+            _ => unreachable!("checked above"),
+        };
+        let whole = |index: usize| {
+            arguments[index].whole().ok_or_else(|| {
+                format!(
+                    "{} expects a whole number for argument {} but was given {}",
+                    self.name(),
+                    index + 1,
+                    arguments[index]
+                )
+            })
+        };
+        let varchar = |value: String| Value::Varchar { value };
+
+        Ok(match self {
+            ScalarFunction::Upper => varchar(text(0).to_uppercase()),
+            ScalarFunction::Lower => varchar(text(0).to_lowercase()),
+            ScalarFunction::Length => Value::Number {
+                value: text(0).chars().count() as u64,
+            },
+            // Characters are counted from 1. Counting from before that
+            // still counts towards the length.
+            ScalarFunction::Substr => {
+                let start = whole(1)?;
+                let end = arguments.get(2).map(|_| whole(2)).transpose()?.map(|length| start + length);
+                let value = (1..)
+                    .zip(text(0).chars())
+                    .filter(|(position, _)| *position >= start && end.is_none_or(|end| *position < end))
+                    .map(|(_, c)| c)
+                    .collect();
+                varchar(value)
+            }
+            ScalarFunction::Trim => varchar(text(0).trim().to_string()),
+            ScalarFunction::Replace if text(1).is_empty() => varchar(text(0).to_string()),
+            ScalarFunction::Replace => varchar(text(0).replace(text(1), text(2))),
+            ScalarFunction::Abs => match &arguments[0] {
+                Value::Int { value } => Value::Int {
+                    value: value.checked_abs().ok_or_else(|| format!("abs({}) is out of range", value))?,
+                },
+                Value::Decimal { value } => Value::Decimal {
+                    value: value.abs().ok_or_else(|| format!("abs({}) is out of range", value))?,
+                },
+                Value::Double { value } => Value::Double { value: value.abs() },
+                number => number.clone(),
+            },
+            // Rounds to a number of decimal places, or to tens, hundreds and
+            // so on when it's negative. A decimal keeps exactly that many
+            // places.
+            ScalarFunction::Round => match &arguments[0] {
+                Value::Decimal { value } => {
+                    let places = arguments.get(1).map(|_| whole(1)).transpose()?.unwrap_or(0);
+                    Value::Decimal {
+                        value: value
+                            .round(places.clamp(-100, 100) as i64)
+                            .ok_or_else(|| format!("round({}) is out of range", value))?,
+                    }
+                }
+                // A double has no more than 17 significant digits, so rounding
+                // to more places than that, or scaling it out of range, leaves
+                // it as it is.
+                Value::Double { value } => {
+                    let places = arguments.get(1).map(|_| whole(1)).transpose()?.unwrap_or(0);
+                    let scale = 10f64.powi(places.clamp(-308, 308) as i32);
+                    let scaled = value * scale;
+                    Value::Double {
+                        value: match places < 17 && scaled.is_finite() {
+                            true => scaled.round() / scale,
+                            false => *value,
+                        },
+                    }
+                }
+                number => {
+                    let places = arguments.get(1).map(|_| whole(1)).transpose()?.unwrap_or(0);
+                    let value = number.whole().unwrap();
+                    // Rounds half away from zero. A power of ten beyond any
+                    // whole number rounds it to zero.
+                    let rounded = match u32::try_from(-places).ok().and_then(|shift| 10i128.checked_pow(shift)) {
+                        _ if places >= 0 => value,
+                        Some(divisor) => (value.abs() + divisor / 2) / divisor * divisor * value.signum(),
+                        None => 0,
+                    };
+                    Value::from_whole(rounded, matches!(number, Value::Number { .. }))
+                        .ok_or_else(|| format!("round({}, {}) is out of range", number, places))?
+                }
+            },
+            ScalarFunction::Mod if arguments[1].double() == Some(0.0) => return Err("Division by zero".to_string()),
+            ScalarFunction::Mod => match (&arguments[0], &arguments[1]) {
+                (Value::Double { .. }, _) | (_, Value::Double { .. }) => Value::Double {
+                    value: arguments[0].double().unwrap() % arguments[1].double().unwrap(),
+                },
+                (Value::Decimal { .. }, _) | (_, Value::Decimal { .. }) => {
+                    let (l, r) = (arguments[0].decimal().unwrap(), arguments[1].decimal().unwrap());
+                    Value::Decimal {
+                        value: l.remainder(&r).ok_or_else(|| format!("mod({}, {}) is out of range", l, r))?,
+                    }
+                }
+                // The result has the sign of the dividend, which fits its type.
+                (l, r) => Value::from_whole(
+                    l.whole().unwrap() % r.whole().unwrap(),
+                    matches!(l, Value::Number { .. }),
+                )
+                .unwrap(),
+            },
+            // Fields with seconds keep their fraction.
+            ScalarFunction::Extract => {
+                let field = match &arguments[1] {
+                    Value::Date { value } => value.extract(&text(0).to_lowercase()).map(FieldValue::Whole),
+                    Value::Time { value } => value.extract(&text(0).to_lowercase()),
+                    Value::Timestamp { value } => value.extract(&text(0).to_lowercase()),
+                    Value::Interval { value } => value.extract(&text(0).to_lowercase()),
+                    other => Err(format!("extract can't be applied to {}", other)),
+                }?;
+                match field {
+                    FieldValue::Whole(value) => Value::Int { value },
+                    FieldValue::Seconds(micros) => Value::Decimal {
+                        value: Decimal::new(micros, 6),
+                    },
+                }
+            }
+            ScalarFunction::DateTrunc => match &arguments[1] {
+                Value::Date { value } => Value::Date {
+                    value: value.truncate(&text(0).to_lowercase())?,
+                },
+                Value::Timestamp { value } => Value::Timestamp {
+                    value: value.truncate(&text(0).to_lowercase())?,
+                },
+                other => return Err(format!("date_trunc can't be applied to {}", other)),
+            },
+            ScalarFunction::Concat | ScalarFunction::Coalesce | ScalarFunction::NullIf => {
+                unreachable!("evaluated above")
+            }
+        })
+    }
+}
+
+/// A value as text, as `concat` and `||` join it.
+pub fn to_text(value: &Value) -> String {
+    match value {
+        Value::Varchar { value } => value.clone(),
+        other => format!("{}", other),
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use crate::{
+    expression::{BoundExpression, DataType, MatchPattern, Scope},
+    query::Rows,
+    sql_parser::{ComparisonOperator, Expression, JoinKind},
+    table::Value,
+};
+
+enum Strategy {
+    /// Compares every left row with every right row.
+    NestedLoop { condition: BoundExpression<'static> },
+    /// Looks up the right rows whose keys equal the left row's, then checks
+    /// whatever else the `on` condition asks for.
+    Hash {
+        left_keys: Vec<BoundExpression<'static>>,
+        right_keys: Vec<BoundExpression<'static>>,
+        residual: Option<BoundExpression<'static>>,
+    },
+}
+
+/// Joins a stream of left rows with the materialised rows of a table. Each
+/// output row is the left row followed by the right row, and a left join
+/// pads left rows that matched nothing with nulls.
+///
+/// When the `on` condition requires some expression over the left columns to
+/// equal one over the right columns, the right rows are put in a hash table
+/// on those expressions instead of being compared with every left row.
+pub struct JoinPlan {
+    kind: JoinKind,
+    strategy: Strategy,
+    right_width: usize,
+}
+
+impl JoinPlan {
+    pub fn new(kind: JoinKind, on: &Expression, left: &Scope, right: &Scope) -> Result<JoinPlan, String> {
+        // Binding the whole condition first reports unknown and ambiguous
+        // columns, which binding its parts against one side could miss.
+        let scope = left.join(right);
+        let condition = BoundExpression::bind(on, &scope)?.condition()?;
+
+        let mut left_keys = Vec::new();
+        let mut right_keys = Vec::new();
+        let mut residual = Vec::new();
+        for conjunct in conjuncts(on) {
+            match equi_join_keys(conjunct, left, right) {
+                Some((left_key, right_key)) => {
+                    left_keys.push(left_key);
+                    right_keys.push(right_key);
+                }
+                None => residual.push(BoundExpression::bind(conjunct, &scope)?),
+            }
+        }
+
+        let strategy = if left_keys.is_empty() {
+            Strategy::NestedLoop { condition }
+        } else {
+            Strategy::Hash {
+                left_keys,
+                right_keys,
+                residual: residual.into_iter().reduce(|left, right| BoundExpression::And {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                }),
+            }
+        };
+
+        Ok(JoinPlan {
+            kind,
+            strategy,
+            right_width: right.len(),
+        })
+    }
+
+    pub fn is_hash_join(&self) -> bool {
+        matches!(self.strategy, Strategy::Hash { .. })
+    }
+
+    pub fn run<'a>(self, left_rows: Rows<'a>, right_rows: Vec<Vec<Value>>) -> Result<Rows<'a>, String> {
+        let buckets = match &self.strategy {
+            Strategy::NestedLoop { .. } => HashMap::new(),
+            Strategy::Hash { right_keys, .. } => {
+                let mut buckets: HashMap<Vec<Value>, Vec<usize>> = HashMap::new();
+                for (index, row) in right_rows.iter().enumerate() {
+                    if let Some(key) = evaluate_key(right_keys, row)? {
+                        buckets.entry(key).or_default().push(index);
+                    }
+                }
+                buckets
+            }
+        };
+
+        Ok(Box::new(left_rows.flat_map(move |left_row| {
+            match left_row.and_then(|left_row| self.join_row(left_row, &right_rows, &buckets)) {
+                Ok(rows) => rows.into_iter().map(Ok).collect(),
+                Err(err) => vec![Err(err)],
+            }
+        })))
+    }
+
+    /// The output rows for one left row.
+    fn join_row(
+        &self,
+        left_row: Vec<Value>,
+        right_rows: &[Vec<Value>],
+        buckets: &HashMap<Vec<Value>, Vec<usize>>,
+    ) -> Result<Vec<Vec<Value>>, String> {
+        let combine = |right_row: &[Value]| {
+            let mut row = left_row.clone();
+            row.extend_from_slice(right_row);
+            row
+        };
+
+        let mut joined = Vec::new();
+        match &self.strategy {
+            Strategy::NestedLoop { condition } => {
+                for right_row in right_rows {
+                    let row = combine(right_row);
+                    if condition.matches(&row)? {
+                        joined.push(row);
+                    }
+                }
+            }
+            Strategy::Hash {
+                left_keys, residual, ..
+            } => {
+                let candidates = match evaluate_key(left_keys, &left_row)? {
+                    Some(key) => buckets.get(&key).map(Vec::as_slice).unwrap_or_default(),
+                    None => &[],
+                };
+                for index in candidates {
+                    let row = combine(&right_rows[*index]);
+                    if residual.as_ref().map_or(Ok(true), |r| r.matches(&row))? {
+                        joined.push(row);
+                    }
+                }
+            }
+        }
+
+        if joined.is_empty() && self.kind == JoinKind::Left {
+            joined.push(combine(&vec![Value::Null; self.right_width]));
+        }
+        Ok(joined)
+    }
+}
+
+/// The values of the join keys for a row, or `None` if any is null, since
+/// null equals nothing.
+fn evaluate_key(keys: &[BoundExpression], row: &[Value]) -> Result<Option<Vec<Value>>, String> {
+    let key = keys
+        .iter()
+        .map(|k| k.evaluate(row).map(Value::key))
+        .collect::<Result<Vec<Value>, String>>()?;
+    Ok((!key.contains(&Value::Null)).then_some(key))
+}
+
+fn conjuncts(expression: &Expression) -> Vec<&Expression> {
+    match expression {
+        Expression::And { left, right } => {
+            let mut all = conjuncts(left);
+            all.extend(conjuncts(right));
+            all
+        }
+        _ => vec![expression],
+    }
+}
+
+/// Splits `l = r` into a key over the left columns and one over the right
+/// columns, whichever way round it's written.
+fn equi_join_keys(
+    expression: &Expression,
+    left: &Scope,
+    right: &Scope,
+) -> Option<(BoundExpression<'static>, BoundExpression<'static>)> {
+    let (l, r) = match expression {
+        Expression::Comparison {
+            left: l,
+            operator: ComparisonOperator::Equal,
+            right: r,
+        } => (l, r),
+        _ => return None,
+    };
+
+    let keys = |a: &Expression, b: &Expression| {
+        let left_key = BoundExpression::bind(a, left).ok()?;
+        let right_key = BoundExpression::bind(b, right).ok()?;
+        // A key without columns, like `1 = 1`, is better left to the residual.
+        // So are keys where text may meet a date or time: `=` reads the text as
+        // one, but their keys don't.
+        (references_columns(&left_key)
+            && references_columns(&right_key)
+            && !may_read_as_time(left_key.data_type(), right_key.data_type()))
+        .then_some((left_key, right_key))
+    };
+    keys(l, r).or_else(|| keys(r, l))
+}
+
+/// Whether one side may be text and the other a date or time, where an
+/// unknown type may be either.
+fn may_read_as_time(left: Option<DataType>, right: Option<DataType>) -> bool {
+    let text = |data_type| matches!(data_type, None | Some(DataType::Text));
+    let time = |data_type| {
+        matches!(
+            data_type,
+            None | Some(DataType::Date | DataType::Time | DataType::Timestamp | DataType::Interval)
+        )
+    };
+    (text(left) && time(right)) || (time(left) && text(right))
+}
+
+fn references_columns(expression: &BoundExpression) -> bool {
+    match expression {
+        // A subquery may depend on the row, so it counts as a column.
+        BoundExpression::Column { .. } | BoundExpression::Exists { .. } | BoundExpression::InSubquery { .. } => true,
+        BoundExpression::Literal { .. } => false,
+        BoundExpression::Comparison { left, right, .. }
+        | BoundExpression::Arithmetic { left, right, .. }
+        | BoundExpression::And { left, right }
+        | BoundExpression::Or { left, right } => references_columns(left) || references_columns(right),
+        BoundExpression::Not { expression } | BoundExpression::IsNull { expression, .. } => {
+            references_columns(expression)
+        }
+        BoundExpression::InList { expression, list, .. } => {
+            references_columns(expression) || list.iter().any(references_columns)
+        }
+        BoundExpression::PatternMatch { expression, pattern, .. } => {
+            references_columns(expression)
+                || matches!(pattern, MatchPattern::Dynamic { pattern, .. } if references_columns(pattern))
+        }
+        BoundExpression::Function { arguments, .. } => arguments.iter().any(references_columns),
+        BoundExpression::Case {
+            operand,
+            branches,
+            otherwise,
+        } => {
+            operand.iter().chain(otherwise.iter()).any(|e| references_columns(e))
+                || branches.iter().any(|(when, then)| references_columns(when) || references_columns(then))
+        }
+    }
+}
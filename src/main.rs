@@ -1,59 +1,84 @@
 #![allow(dead_code)]
 
+mod aggregate;
+mod buffer_pool;
+mod catalog;
 mod cli;
+mod database;
+mod datetime;
+mod decimal;
+mod encoding;
+mod expression;
+mod function;
+mod join;
 mod mapper;
+mod overflow;
+mod pager;
+mod pattern;
+mod query;
+mod slotted_page;
 mod sql_parser;
+mod subquery;
 mod table;
+mod wal;
+mod window;
 
-use std::{collections::{HashMap, HashSet}, iter, sync::Mutex};
+use std::collections::{HashMap, HashSet};
 
 use cli::*;
-use lazy_static::lazy_static;
+use database::Database;
+use expression::{bind_where_clause, BoundExpression, Scope};
 use mapper::ColumnSpecMapper;
-use sql_parser::{CreateTable, CsvImport, Insert, Select};
-use table::{ColumnSpec, Table};
+use sql_parser::{CreateTable, CsvImport, Delete, Insert, Query, Update};
+use table::ColumnSpec;
 
 use crate::{mapper::InsertValueMapper, sql_parser::Statement, table::Row};
 
-lazy_static! {
-    static ref TABLES: Mutex<HashMap<String, Table>> = Mutex::new(HashMap::new());
-}
 
-fn exec_create_table(fields: &CreateTable) {
+fn exec_create_table(database: &mut Database, fields: &CreateTable) {
     let column_specs: Vec<ColumnSpec> = fields
         .column_specs
         .iter()
         .map(ColumnSpecMapper::sql_parser_to_table)
         .collect();
-    let table = Table::new(&column_specs);
-    let mut map = TABLES.lock().unwrap();
-    print_table(&fields.table_name, &table);
-    map.insert(fields.table_name.clone(), table);
+
+    match database.create_table(&fields.table_name, &column_specs) {
+        Ok(_) => print_table(&fields.table_name, &column_specs),
+        Err(err) => print_error(format!("Create table failed. {:?}", err).as_str()),
+    }
 }
 
-fn exec_show_tables() {
-    let map = TABLES.lock().unwrap();
+fn exec_show_tables(database: &Database) {
     println!();
-    for (name, table) in map.iter() {
-        print_table(name, table);
+    for entry in database.catalog.entries.iter() {
+        print_table(&entry.table_name, &entry.column_specs);
+        print_row_count(entry.row_count);
     }
     println!();
 }
 
-fn exec_insert(insert: &Insert) {
-    let mut map = TABLES.lock().unwrap();
-    let table = map.get_mut(&insert.table_name);
+fn exec_insert(database: &mut Database, insert: &Insert) {
+    let table = database.table(&insert.table_name);
 
     match table {
+        Some(_) if insert.column_refs.len() != insert.column_values.len() => {
+            print_error(format!(
+                "Insert failed. {} columns are named but {} values are given.",
+                insert.column_refs.len(),
+                insert.column_values.len()
+            ).as_str());
+        },
         Some(table) => {
-            let values: Vec<table::Value> = insert.column_values.iter().map(|v| InsertValueMapper::sql_parser_to_table(&v)).collect();
-            let name_values = insert.column_refs.iter().chain(iter::repeat(&String::new())).zip(values).map(|(k, v)| (k.clone(), v)).collect();
+            let values: Vec<table::Value> = insert.column_values.iter().map(InsertValueMapper::sql_parser_to_table).collect();
+            let name_values = insert.column_refs.iter().zip(values).map(|(k, v)| (k.clone(), v)).collect();
             let row_build = Row::new(&name_values, &table.column_specs);
 
             match row_build {
                 Ok(row) => {
-                    table.insert(&row);
-                    print_insert_success(&insert.table_name, table.row_count);
+                    match database.insert(&insert.table_name, &row) {
+                        Ok(row_count) => print_insert_success(&insert.table_name, row_count),
+                        Err(err) => print_error(format!("Insert failed. {:?}", err).as_str()),
+                    }
                 },
                 Err(err) => print_error(format!("Insert failed. {:?}", err).as_str())
             }
@@ -64,87 +89,149 @@ fn exec_insert(insert: &Insert) {
     }
 }
 
-fn exec_select(select: &Select) {
-    let mut map = TABLES.lock().unwrap();
-    let table = map.get_mut(&select.table_name);
-
-    match table {
-        Some(table) => {
-            let named_columns: HashSet<String> = select.column_refs.iter().map(|c| match c {
-                sql_parser::SelectColumnReference::Named { column_name } => Some(column_name.clone()),
-                sql_parser::SelectColumnReference::Wildcard => None,
-            }).flatten().collect();
-            let unknown_columns: Vec<&String> = named_columns.iter().filter(|c1| {
-                table.column_specs.iter().filter(|c2| c2.column_name == **c1).count() == 0
-            }).collect();
-
-            if !unknown_columns.is_empty() {
-                print_error(format!("Unknown columns {:?} in select query", unknown_columns).as_str());
-            }
-            else {
-                let has_wildcard = select.column_refs.iter().find(|c| match c {
-                    sql_parser::SelectColumnReference::Named { column_name: _ } => false,
-                    sql_parser::SelectColumnReference::Wildcard => true,
-                }).is_some();
-
-                let mut results = Vec::new();
-
-                let shown_indicies: Vec<usize> = table.column_specs.iter().enumerate().filter(|(_, cs)| has_wildcard || named_columns.contains(&cs.column_name)).map(|(i, _)| i).collect();
-
-                for i in 0..table.row_count {
-                    let row = table.get(i);
-                    match row {
-                        Ok(row) => {
-                            let string_row: Vec<String> = shown_indicies.iter().flat_map(|i| row.values.get(*i)).map(|(v,_)| format!("{}", v)).collect();
-                            results.push(string_row);
-                        },
-                        Err(err) => print_error(format!("Unable to read row {}: {:?}", i, err).as_str()),
-                    }
-                }
-
-                println!("{:?}", results);
-
-                let header = table.column_specs.iter().filter(|cs| has_wildcard || named_columns.contains(&cs.column_name)).map(|cs| cs.column_name.clone()).collect();
-                print_string_table(&header, &results);
-            }
+fn exec_select(database: &Database, query: &Query) {
+    match query::execute(database, query) {
+        Ok(result) => {
+            let rows: Vec<Vec<String>> = result.rows.iter().map(|row| row.iter().map(|v| format!("{}", v)).collect()).collect();
+            print_string_table(&result.column_names, &rows);
         },
+        Err(err) => print_error(format!("Select failed. {}", err).as_str()),
+    }
+}
+
+fn exec_delete(database: &mut Database, delete: &Delete) {
+    let table = match database.table(&delete.table_name) {
+        Some(table) => table,
         None => {
-            print_error(format!("Insert failed. No table named '{}' is defined.", select.table_name).as_str());
+            print_error(format!("Delete failed. No table named '{}' is defined.", delete.table_name).as_str());
+            return;
         }
+    };
+
+    let scope = Scope::table(&delete.table_name, &table.column_specs);
+    let condition = match bind_where_clause(&delete.where_clause, &scope) {
+        Ok(condition) => condition,
+        Err(err) => {
+            print_error(format!("Delete failed. {}", err).as_str());
+            return;
+        }
+    };
+
+    let result = database.delete(&delete.table_name, |values| match &condition {
+        Some(condition) => condition.matches(values),
+        None => Ok(true),
+    });
+
+    match result {
+        Ok((deleted, row_count)) => print_delete_success(&delete.table_name, deleted, row_count),
+        Err(err) => print_error(format!("Delete failed. {:?}", err).as_str()),
     }
 }
 
-fn exec_csv_import(import: &CsvImport) {
-    let mut map = TABLES.lock().unwrap();
-    let table = map.get_mut(&import.table_name);
+fn exec_update(database: &mut Database, update: &Update) {
+    let table = match database.table(&update.table_name) {
+        Some(table) => table,
+        None => {
+            print_error(format!("Update failed. No table named '{}' is defined.", update.table_name).as_str());
+            return;
+        }
+    };
+    let column_specs = table.column_specs.clone();
+
+    let scope = Scope::table(&update.table_name, &column_specs);
+    let bound = bind_where_clause(&update.where_clause, &scope).and_then(|condition| {
+        let mut assigned = HashSet::new();
+        let mut assignments = Vec::new();
+        for (column_name, expression) in update.assignments.iter() {
+            if !column_specs.iter().any(|cs| cs.column_name == *column_name) {
+                return Err(format!("Unknown column '{}'", column_name));
+            }
+            if !assigned.insert(column_name) {
+                return Err(format!("Column '{}' is assigned more than once", column_name));
+            }
+            assignments.push((column_name.clone(), BoundExpression::bind(expression, &scope)?));
+        }
+        Ok((condition, assignments))
+    });
+
+    let (condition, assignments) = match bound {
+        Ok(bound) => bound,
+        Err(err) => {
+            print_error(format!("Update failed. {}", err).as_str());
+            return;
+        }
+    };
 
-    match table {
-        Some(table) => {
-            match table.csv_import(&import.file_path, &import.column_mapping, import.with_truncate) {
-                Ok(_) => print_success(format!("Woohoo! Table has {} rows.", table.row_count).as_str()),
-                Err(err) => print_error(format!("CSV import failed. {:?}", err).as_str()),
+    let result = database.update(&update.table_name, |values| {
+        if let Some(condition) = &condition {
+            if !condition.matches(values)? {
+                return Ok(None);
             }
-        },
-        None => {
-            print_error(format!("Insert failed. No table named '{}' is defined.", import.table_name).as_str());
         }
+
+        let mut column_values: HashMap<String, table::Value> = column_specs.iter().map(|cs| cs.column_name.clone()).zip(values.iter().cloned()).collect();
+        for (column_name, expression) in assignments.iter() {
+            column_values.insert(column_name.clone(), expression.evaluate(values)?);
+        }
+        Row::new(&column_values, &column_specs).map(Some).map_err(|err| format!("{:?}", err))
+    });
+
+    match result {
+        Ok(updated) => print_update_success(&update.table_name, updated),
+        Err(err) => print_error(format!("Update failed. {:?}", err).as_str()),
+    }
+}
+
+fn exec_csv_import(database: &mut Database, import: &CsvImport) {
+    let result = database.csv_import(
+        &import.table_name,
+        &import.file_path,
+        &import.column_mapping,
+        import.with_truncate,
+        &import.formats,
+    );
+
+    match result {
+        Ok(row_count) => print_success(format!("Woohoo! Table has {} rows.", row_count).as_str()),
+        Err(err) => print_error(format!("CSV import failed. {:?}", err).as_str()),
     }
 }
 
 fn main() {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            print_error(err.as_str());
+            return;
+        }
+    };
+
+    let mut database = match Database::open(&args.data_dir, args.buffer_pool_pages) {
+        Ok(database) => database,
+        Err(err) => {
+            print_error(format!("Unable to open data directory {}. {:?}", args.data_dir.display(), err).as_str());
+            return;
+        }
+    };
+
     print_wizard();
-    println!("");
+    println!();
 
     loop {
         let input = read_input();
         let statement = sql_parser::Statement::parse(input.as_str());
 
         match statement {
-            Ok((_, Statement::CreateTable(fields))) => exec_create_table(&fields),
-            Ok((_, Statement::Select(fields))) => exec_select(&fields),
-            Ok((_, Statement::ShowTables)) => exec_show_tables(),
-            Ok((_, Statement::Insert(insert))) => exec_insert(&insert),
-            Ok((_, Statement::CsvImport(fields))) => exec_csv_import(&fields),
+            Ok((remaining, _)) if !remaining.trim().is_empty() => {
+                print_invalid_statement_syntax(format!("Unexpected input '{}'", remaining.trim()).as_str())
+            }
+            Ok((_, Statement::CreateTable(fields))) => exec_create_table(&mut database, &fields),
+            Ok((_, Statement::Select(fields))) => exec_select(&database, &fields),
+            Ok((_, Statement::ShowTables)) => exec_show_tables(&database),
+            Ok((_, Statement::Insert(insert))) => exec_insert(&mut database, &insert),
+            Ok((_, Statement::CsvImport(fields))) => exec_csv_import(&mut database, &fields),
+            Ok((_, Statement::Delete(delete))) => exec_delete(&mut database, &delete),
+            Ok((_, Statement::Update(update))) => exec_update(&mut database, &update),
             Err(error_message) => {
                 print_invalid_statement_syntax(format!("{}", error_message).as_str())
             }
@@ -7,6 +7,7 @@ impl ColumnSpecMapper {
     table::ColumnSpec {
         column_name: column_spec.name.clone(),
         column_type: ColumnTypeMapper::sql_parser_to_table(&column_spec.column_type),
+        not_null: column_spec.not_null,
     }
   }
 }
@@ -18,7 +19,20 @@ impl ColumnTypeMapper {
     match column_type {
         sql_parser::ColumnType::Varchar { max_length } => table::ColumnType::Varchar { max_len: *max_length as usize },
         sql_parser::ColumnType::Number => table::ColumnType::Number,
+        sql_parser::ColumnType::SmallInt => table::ColumnType::SmallInt,
+        sql_parser::ColumnType::Int => table::ColumnType::Int,
+        sql_parser::ColumnType::Decimal { precision, scale } => table::ColumnType::Decimal {
+            precision: *precision,
+            scale: *scale,
+        },
+        sql_parser::ColumnType::Double => table::ColumnType::Double,
         sql_parser::ColumnType::Boolean => table::ColumnType::Boolean,
+        sql_parser::ColumnType::Date => table::ColumnType::Date,
+        sql_parser::ColumnType::Time => table::ColumnType::Time,
+        sql_parser::ColumnType::Timestamp => table::ColumnType::Timestamp,
+        sql_parser::ColumnType::Interval => table::ColumnType::Interval,
+        sql_parser::ColumnType::Text => table::ColumnType::Text,
+        sql_parser::ColumnType::Blob => table::ColumnType::Blob,
     }
   }
 }
@@ -30,7 +44,16 @@ impl InsertValueMapper {
     match insert_value {
         sql_parser::InsertValue::Varchar { value } => table::Value::Varchar { value: value.clone() },
         sql_parser::InsertValue::Number { value } => table::Value::Number { value: *value },
+        sql_parser::InsertValue::Int { value } => table::Value::Int { value: *value },
+        sql_parser::InsertValue::Decimal { value } => table::Value::Decimal { value: *value },
+        sql_parser::InsertValue::Double { value } => table::Value::Double { value: *value },
         sql_parser::InsertValue::Boolean { value } => table::Value::Boolean { value: *value },
+        sql_parser::InsertValue::Date { value } => table::Value::Date { value: *value },
+        sql_parser::InsertValue::Time { value } => table::Value::Time { value: *value },
+        sql_parser::InsertValue::Timestamp { value } => table::Value::Timestamp { value: *value },
+        sql_parser::InsertValue::Interval { value } => table::Value::Interval { value: *value },
+        sql_parser::InsertValue::Blob { value } => table::Value::Blob { value: value.clone() },
+        sql_parser::InsertValue::Null => table::Value::Null,
     }
   }
 }
\ No newline at end of file
@@ -0,0 +1,186 @@
+use std::{io, path::Path};
+
+use crate::{
+    buffer_pool::{FileId, SharedBufferPool},
+    pager::{Pager, PAGE_SIZE},
+};
+
+/// Values too long to be kept in a table's rows, such as text and blobs,
+/// are stored in a file of overflow pages next to the table's. Each value
+/// is a chain of pages, each page linking to the next one.
+///
+/// Page 0 is a header holding the number of pages the file has, including
+/// the header, and the first page of a list of freed pages, which are
+/// reused before the file grows. A data page starts with the number of the
+/// next page in its chain, or 0 for the last page, followed by the value's
+/// bytes.
+///
+/// Pages are accessed through the shared buffer pool, so the header and the
+/// chains change along with the table's rows in its transactions.
+pub struct Overflow {
+    pool: SharedBufferPool,
+    file_id: FileId,
+}
+
+impl Overflow {
+    const HEADER_PAGE: usize = 0;
+    const NO_PAGE: usize = 0;
+    const DATA_LEN: usize = PAGE_SIZE - 8;
+
+    /// Opens the overflow file at `path`, creating it if it doesn't exist.
+    pub fn open(pool: &SharedBufferPool, path: &Path) -> io::Result<Overflow> {
+        let pager = if path.exists() { Pager::open(path)? } else { Pager::create(path)? };
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        let file_id = pool.borrow_mut().add_file(&file_name, pager);
+        Ok(Overflow {
+            pool: pool.clone(),
+            file_id,
+        })
+    }
+
+    /// Writes the file's modified pages back to it.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.pool.borrow_mut().flush_file(self.file_id)
+    }
+
+    /// Stores a value, returning the first page of its chain.
+    pub fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        let chunks: Vec<&[u8]> = bytes.chunks(Overflow::DATA_LEN).collect();
+        let pages = (0..chunks.len().max(1))
+            .map(|_| self.allocate())
+            .collect::<io::Result<Vec<usize>>>()?;
+
+        let mut pool = self.pool.borrow_mut();
+        for (index, page_no) in pages.iter().enumerate() {
+            let next = pages.get(index + 1).copied().unwrap_or(Overflow::NO_PAGE);
+            let chunk = chunks.get(index).copied().unwrap_or_default();
+
+            let frame_id = pool.fetch(self.file_id, *page_no)?;
+            let page = pool.page_mut(frame_id);
+            page[0..8].copy_from_slice(&(next as u64).to_be_bytes());
+            page[8..8 + chunk.len()].copy_from_slice(chunk);
+            page[8 + chunk.len()..].fill(0);
+            pool.unpin(frame_id);
+        }
+        Ok(pages[0])
+    }
+
+    /// Reads the `len` bytes of the value stored from `first_page`.
+    pub fn read(&self, first_page: usize, len: usize) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(len);
+        let mut pool = self.pool.borrow_mut();
+        let mut page_no = first_page;
+        while bytes.len() < len {
+            if page_no == Overflow::NO_PAGE {
+                return Err(io::Error::other(format!(
+                    "Overflow chain from page {} ends after {} of {} bytes",
+                    first_page,
+                    bytes.len(),
+                    len
+                )));
+            }
+            let frame_id = pool.fetch(self.file_id, page_no)?;
+            let page = pool.page(frame_id);
+            let chunk_len = (len - bytes.len()).min(Overflow::DATA_LEN);
+            bytes.extend_from_slice(&page[8..8 + chunk_len]);
+            page_no = next_page(page);
+            pool.unpin(frame_id);
+        }
+        Ok(bytes)
+    }
+
+    /// Returns the chain starting at `first_page` to the free list.
+    pub fn free(&mut self, first_page: usize) -> io::Result<()> {
+        let mut pool = self.pool.borrow_mut();
+        let mut last_page = first_page;
+        loop {
+            let frame_id = pool.fetch(self.file_id, last_page)?;
+            let next = next_page(pool.page(frame_id));
+            pool.unpin(frame_id);
+            if next == Overflow::NO_PAGE {
+                break;
+            }
+            last_page = next;
+        }
+
+        let header_id = pool.fetch(self.file_id, Overflow::HEADER_PAGE)?;
+        let (_, free_head) = read_header(pool.page(header_id));
+        let frame_id = pool.fetch(self.file_id, last_page)?;
+        pool.page_mut(frame_id)[0..8].copy_from_slice(&(free_head as u64).to_be_bytes());
+        pool.unpin(frame_id);
+        pool.page_mut(header_id)[8..16].copy_from_slice(&(first_page as u64).to_be_bytes());
+        pool.unpin(header_id);
+        Ok(())
+    }
+
+    /// A page to write to, taken from the free list or else added to the
+    /// end of the file.
+    fn allocate(&mut self) -> io::Result<usize> {
+        let mut pool = self.pool.borrow_mut();
+        let header_id = pool.fetch(self.file_id, Overflow::HEADER_PAGE)?;
+        let (page_count, free_head) = read_header(pool.page(header_id));
+
+        let page_no = if free_head != Overflow::NO_PAGE {
+            let frame_id = pool.fetch(self.file_id, free_head)?;
+            let next_free = next_page(pool.page(frame_id));
+            pool.unpin(frame_id);
+            pool.page_mut(header_id)[8..16].copy_from_slice(&(next_free as u64).to_be_bytes());
+            free_head
+        } else {
+            pool.page_mut(header_id)[0..8].copy_from_slice(&(page_count as u64 + 1).to_be_bytes());
+            page_count
+        };
+        pool.unpin(header_id);
+        Ok(page_no)
+    }
+}
+
+/// The page count and the first free page. A new file's header reads as
+/// zeros, but the header itself is always there.
+fn read_header(page: &[u8]) -> (usize, usize) {
+    let page_count = u64::from_be_bytes(page[0..8].try_into().unwrap()) as usize;
+    (page_count.max(1), next_page(&page[8..]))
+}
+
+fn next_page(page: &[u8]) -> usize {
+    u64::from_be_bytes(page[0..8].try_into().unwrap()) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{buffer_pool::BufferPool, wal::Wal};
+
+    #[test]
+    fn test_write_read_and_reuse() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal = Wal::open(&dir.path().join("wal")).unwrap();
+        let pool = BufferPool::new(4, wal).shared();
+        let path = dir.path().join("foo.ovf");
+        let mut overflow = Overflow::open(&pool, &path).unwrap();
+
+        let long: Vec<u8> = (0..PAGE_SIZE * 3).map(|i| (i % 251) as u8).collect();
+        let short = b"hello".to_vec();
+        let long_page = overflow.write(&long).unwrap();
+        let short_page = overflow.write(&short).unwrap();
+        assert_eq!(1, long_page);
+        assert_eq!(5, short_page);
+        assert_eq!(long, overflow.read(long_page, long.len()).unwrap());
+        assert_eq!(short, overflow.read(short_page, short.len()).unwrap());
+        assert_eq!(Vec::<u8>::new(), overflow.read(short_page, 0).unwrap());
+
+        // The freed chain is reused before the file grows.
+        overflow.free(long_page).unwrap();
+        let reused = overflow.write(&long[..PAGE_SIZE]).unwrap();
+        assert_eq!(long_page, reused);
+        assert_eq!(&long[..PAGE_SIZE], &overflow.read(reused, PAGE_SIZE).unwrap()[..]);
+        assert_eq!(3, overflow.write(b"again").unwrap());
+        assert_eq!(4, overflow.write(&long[..PAGE_SIZE * 2]).unwrap());
+        overflow.flush().unwrap();
+        drop(overflow);
+
+        let overflow = Overflow::open(&pool, &path).unwrap();
+        assert_eq!(short, overflow.read(short_page, short.len()).unwrap());
+        assert!(overflow.read(short_page, PAGE_SIZE).is_err());
+    }
+}
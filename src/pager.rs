@@ -0,0 +1,124 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+pub const PAGE_SIZE: usize = 4096;
+
+pub struct Pager {
+    file: File,
+    page_count: usize,
+}
+
+impl Pager {
+    /// Creates a new, empty file. An existing file is never overwritten,
+    /// so its pages can't be lost by creating it again.
+    pub fn create(path: &Path) -> io::Result<Pager> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::AlreadyExists => {
+                    io::Error::other(format!("File {} already exists", path.display()))
+                }
+                _ => err,
+            })?;
+        Ok(Pager {
+            file,
+            page_count: 0,
+        })
+    }
+
+    pub fn open(path: &Path) -> io::Result<Pager> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let len = file.metadata()?.len() as usize;
+
+        if !len.is_multiple_of(PAGE_SIZE) {
+            return Err(io::Error::other(format!(
+                "File {} is {} bytes long, which is not a whole number of {} byte pages",
+                path.display(),
+                len,
+                PAGE_SIZE
+            )));
+        }
+
+        Ok(Pager {
+            file,
+            page_count: len / PAGE_SIZE,
+        })
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.page_count
+    }
+
+    /// Pages past the end of the file read as zeroes, so callers can treat a
+    /// freshly allocated page the same as one already on disk.
+    pub fn read_page(&mut self, page_no: usize) -> io::Result<Vec<u8>> {
+        let mut page = vec![0; PAGE_SIZE];
+
+        if page_no < self.page_count {
+            self.file
+                .seek(SeekFrom::Start((page_no * PAGE_SIZE) as u64))?;
+            self.file.read_exact(&mut page)?;
+        }
+
+        Ok(page)
+    }
+
+    pub fn write_page(&mut self, page_no: usize, page: &[u8]) -> io::Result<()> {
+        assert_eq!(page.len(), PAGE_SIZE);
+
+        if page_no > self.page_count {
+            self.file.set_len((page_no * PAGE_SIZE) as u64)?;
+        }
+
+        self.file
+            .seek(SeekFrom::Start((page_no * PAGE_SIZE) as u64))?;
+        self.file.write_all(page)?;
+        self.page_count = self.page_count.max(page_no + 1);
+        Ok(())
+    }
+
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_unwritten_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut pager = Pager::create(&dir.path().join("data")).unwrap();
+
+        assert_eq!(0, pager.page_count());
+        assert_eq!(vec![0; PAGE_SIZE], pager.read_page(3).unwrap());
+    }
+
+    #[test]
+    fn test_page_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data");
+
+        let mut page = vec![0; PAGE_SIZE];
+        page[0] = 42;
+        page[PAGE_SIZE - 1] = 7;
+
+        let mut pager = Pager::create(&path).unwrap();
+        pager.write_page(2, &page).unwrap();
+        pager.sync().unwrap();
+        drop(pager);
+
+        assert!(Pager::create(&path).is_err());
+        let mut pager = Pager::open(&path).unwrap();
+        assert_eq!(3, pager.page_count());
+        assert_eq!(vec![0; PAGE_SIZE], pager.read_page(1).unwrap());
+        assert_eq!(page, pager.read_page(2).unwrap());
+    }
+}
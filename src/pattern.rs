@@ -0,0 +1,104 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::sql_parser::PatternKind;
+
+/// A compiled `like`, `ilike` or regular expression pattern. Patterns
+/// written as literals are compiled once, when the query is planned, and
+/// reused for every row.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    kind: PatternKind,
+    text: String,
+    regex: Regex,
+}
+
+impl Pattern {
+    pub fn compile(kind: PatternKind, text: &str) -> Result<Pattern, String> {
+        let regex = match kind {
+            PatternKind::Like | PatternKind::ILike => RegexBuilder::new(&like_to_regex(text))
+                .case_insensitive(kind == PatternKind::ILike)
+                .dot_matches_new_line(true)
+                .build(),
+            PatternKind::Regex => Regex::new(text),
+        };
+        Ok(Pattern {
+            kind,
+            text: text.to_string(),
+            regex: regex.map_err(|err| format!("Invalid pattern '{}'. {}", text, err))?,
+        })
+    }
+
+    /// Whether `value` matches. A `like` pattern must match all of it, while
+    /// a regular expression can match any part unless it's anchored.
+    pub fn matches(&self, value: &str) -> bool {
+        self.regex.is_match(value)
+    }
+}
+
+/// Two patterns are equal if they're written the same.
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.text == other.text
+    }
+}
+
+impl Eq for Pattern {}
+
+/// Translates a `like` pattern, in which `%` stands for any run of
+/// characters, `_` for any one character and `\` makes the character after
+/// it stand for itself.
+fn like_to_regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => regex.push_str(".*"),
+            '_' => regex.push('.'),
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    regex.push_str(&regex::escape(&escaped.to_string()));
+                }
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_like() {
+        let like = |pattern: &str, value: &str| Pattern::compile(PatternKind::Like, pattern).unwrap().matches(value);
+
+        assert!(like("abc", "abc"));
+        assert!(!like("abc", "abcd"));
+        assert!(!like("abc", "ABC"));
+        assert!(like("a%", "a"));
+        assert!(like("a%", "abc"));
+        assert!(like("%b%", "abc"));
+        assert!(!like("%b%", "ac"));
+        assert!(like("a_c", "abc"));
+        assert!(!like("a_c", "ac"));
+        assert!(like("a.c*", "a.c*"));
+        assert!(!like("a.c", "abc"));
+        assert!(like("100\\%", "100%"));
+        assert!(!like("100\\%", "1000"));
+        assert!(like("%", "line\nbreak"));
+
+        let ilike = Pattern::compile(PatternKind::ILike, "the %").unwrap();
+        assert!(ilike.matches("The Beatles"));
+    }
+
+    #[test]
+    fn test_regex() {
+        let regex = Pattern::compile(PatternKind::Regex, "^[A-Z][a-z]+ [0-9]+$").unwrap();
+        assert!(regex.matches("Track 12"));
+        assert!(!regex.matches("track 12"));
+        assert!(Pattern::compile(PatternKind::Regex, "love").unwrap().matches("Endless love song"));
+        assert!(Pattern::compile(PatternKind::Regex, "(unclosed").is_err());
+    }
+}
@@ -0,0 +1,1354 @@
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use crate::{
+    aggregate::{find_aggregates, Aggregation},
+    database::Database,
+    expression::{BoundExpression, Scope},
+    join::JoinPlan,
+    sql_parser::{
+        CommonTableExpression, Expression, Query, Select, SelectColumnReference, SetOperator, TableReference,
+    },
+    subquery::{Frame, Subquery},
+    table::{Table, Value},
+    window::Windows,
+};
+
+pub struct QueryResult {
+    pub column_names: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+struct SortKey<'a> {
+    expression: BoundExpression<'a>,
+    ascending: bool,
+}
+
+pub type Rows<'a> = Box<dyn Iterator<Item = Result<Vec<Value>, String>> + 'a>;
+
+/// The result of a common table expression, which queries can select from
+/// by name.
+#[derive(Clone)]
+struct NamedResult {
+    name: String,
+    result: Rc<QueryResult>,
+    referenced: Rc<Cell<bool>>,
+}
+
+/// What a query can refer to besides the database's tables: the current
+/// rows of the queries it's a subquery of, innermost first, and the results
+/// of the common table expressions in scope, later ones hiding earlier ones
+/// of the same name.
+#[derive(Clone, Default)]
+pub struct Context {
+    outer: Vec<Frame>,
+    tables: Vec<NamedResult>,
+}
+
+impl Context {
+    /// The context of a subquery run for `frame`'s row.
+    pub fn with_frame(&self, frame: Frame) -> Context {
+        let mut outer = vec![frame];
+        outer.extend(self.outer.iter().cloned());
+        Context {
+            outer,
+            tables: self.tables.clone(),
+        }
+    }
+
+    fn with_table(&self, name: &str, result: QueryResult) -> Context {
+        let mut context = self.clone();
+        context.tables.push(NamedResult {
+            name: name.to_string(),
+            result: Rc::new(result),
+            referenced: Rc::new(Cell::new(false)),
+        });
+        context
+    }
+
+    fn table(&self, name: &str) -> Option<&NamedResult> {
+        self.tables.iter().rev().find(|t| t.name == name)
+    }
+}
+
+/// How many times the recursive part of a common table expression can run
+/// before the query is taken to never finish.
+const MAX_RECURSION: usize = 100_000;
+
+/// Runs a query. The sides of a set operation are run in full before being
+/// combined, sorted and limited.
+pub fn execute(database: &Database, query: &Query) -> Result<QueryResult, String> {
+    execute_in(database, query, &Context::default())
+}
+
+/// Runs a query within `context`, as a subquery of the queries whose rows
+/// it holds and with its common table expressions to select from.
+pub fn execute_in(database: &Database, query: &Query, context: &Context) -> Result<QueryResult, String> {
+    match query {
+        Query::With {
+            recursive,
+            tables,
+            query,
+        } => {
+            let mut context = context.clone();
+            for table in tables {
+                let result = if *recursive {
+                    execute_recursive(database, table, &context)?
+                } else {
+                    rename_columns(table, execute_in(database, &table.query, &context)?)?
+                };
+                context = context.with_table(&table.name, result);
+            }
+            execute_in(database, query, &context)
+        }
+        Query::Select(s) => select(database, s, context),
+        Query::SetOperation {
+            operator,
+            all,
+            left,
+            right,
+            order_by,
+            limit,
+            offset,
+        } => {
+            let left = execute_in(database, left, context)?;
+            let right = execute_in(database, right, context)?;
+            if left.column_names.len() != right.column_names.len() {
+                return Err(format!(
+                    "Both sides of {} must select the same number of columns, but they select {} and {}",
+                    operator,
+                    left.column_names.len(),
+                    right.column_names.len()
+                ));
+            }
+
+            // The combined rows are named after the left side's columns.
+            let scope = Scope::named(None, &left.column_names);
+            let sort_keys = order_by
+                .iter()
+                .map(|o| {
+                    Ok(SortKey {
+                        expression: BoundExpression::bind(&o.expression, &scope)?,
+                        ascending: o.ascending,
+                    })
+                })
+                .collect::<Result<Vec<SortKey>, String>>()?;
+
+            let rows = sort(combine(*operator, *all, left.rows, right.rows), &sort_keys)?;
+            Ok(QueryResult {
+                column_names: left.column_names,
+                rows: offset_and_limit(Box::new(rows.into_iter().map(Ok)), *offset, *limit)?,
+            })
+        }
+    }
+}
+
+/// Runs a common table expression of the form `anchor union [all] step`
+/// whose step selects from the table itself. The anchor's rows start the
+/// table off, then the step is run over the rows added last until it adds
+/// none. Without `all`, rows already in the table aren't added again.
+fn execute_recursive(database: &Database, table: &CommonTableExpression, context: &Context) -> Result<QueryResult, String> {
+    let (all, anchor, step) = match &table.query {
+        Query::SetOperation {
+            operator: SetOperator::Union,
+            all,
+            left,
+            right,
+            order_by,
+            limit: None,
+            offset: 0,
+        } if order_by.is_empty() => (*all, left, right),
+        query => return rename_columns(table, execute_in(database, query, context)?),
+    };
+
+    let anchor = rename_columns(table, execute_in(database, anchor, context)?)?;
+    let column_names = anchor.column_names;
+    let mut seen = HashSet::new();
+    let mut added: Vec<Vec<Value>> = anchor.rows.into_iter().filter(|row| all || seen.insert(row_key(row))).collect();
+    let mut rows = added.clone();
+
+    for _ in 0..MAX_RECURSION {
+        if added.is_empty() {
+            return Ok(QueryResult { column_names, rows });
+        }
+
+        let context = context.with_table(
+            &table.name,
+            QueryResult {
+                column_names: column_names.clone(),
+                rows: added,
+            },
+        );
+        let result = execute_in(database, step, &context)?;
+        if result.column_names.len() != column_names.len() {
+            return Err(format!(
+                "Both sides of union must select the same number of columns, but they select {} and {}",
+                column_names.len(),
+                result.column_names.len()
+            ));
+        }
+
+        added = result.rows.into_iter().filter(|row| all || seen.insert(row_key(row))).collect();
+        rows.extend(added.iter().cloned());
+
+        // A step that doesn't select from the table adds the same rows
+        // every time it runs.
+        if !context.table(&table.name).is_some_and(|t| t.referenced.get()) {
+            return Ok(QueryResult { column_names, rows });
+        }
+    }
+    Err(format!("Recursive query '{}' didn't finish after {} steps", table.name, MAX_RECURSION))
+}
+
+/// Names a common table expression's columns as its column list says.
+fn rename_columns(table: &CommonTableExpression, mut result: QueryResult) -> Result<QueryResult, String> {
+    if !table.column_names.is_empty() {
+        if table.column_names.len() != result.column_names.len() {
+            return Err(format!(
+                "Table '{}' has {} column(s) but {} are named",
+                table.name,
+                result.column_names.len(),
+                table.column_names.len()
+            ));
+        }
+        result.column_names = table.column_names.clone();
+    }
+    Ok(result)
+}
+
+/// Runs a select: the rows of the first table are scanned and joined with
+/// each of the joined tables in turn, filtered by the `where` clause,
+/// aggregated if the query groups or uses aggregates, given the values of
+/// any window functions, sorted by the `order by` keys, projected onto the
+/// selected columns, rid of duplicates if the select is `distinct` and
+/// finally cut down to the `limit`.
+///
+/// Rows are pulled through the scan one at a time, so without an `order by`,
+/// aggregation or window function the scan stops reading pages as soon as
+/// the limit is reached. Joined tables and subqueries in `from` are read in
+/// full before the first row comes out.
+fn select(database: &Database, select: &Select, context: &Context) -> Result<QueryResult, String> {
+    let (mut scope, mut joined) = match &select.from {
+        Some(from) => source(database, from, context)?,
+        // Without a from, the select is evaluated once.
+        None => (Scope::default(), Box::new(std::iter::once(Ok(Vec::new()))) as Rows),
+    };
+
+    for join in select.joins.iter() {
+        if scope.columns.iter().any(|c| c.table_name.as_ref() == Some(join.table.name())) {
+            return Err(format!("Table name '{}' is used more than once", join.table.name()));
+        }
+        let (right_scope, right_rows) = source(database, &join.table, context)?;
+        let plan = JoinPlan::new(join.kind, &join.on, &scope, &right_scope)?;
+        let right_rows = right_rows.collect::<Result<Vec<Vec<Value>>, String>>()?;
+        joined = plan.run(joined, right_rows)?;
+        scope = scope.join(&right_scope);
+    }
+
+    let binder = Binder {
+        database,
+        scope: &scope,
+        context,
+    };
+    let condition = select.where_clause.as_ref().map(|w| binder.bind(w)?.condition()).transpose()?;
+    let filtered = joined.filter_map(|result| {
+        result
+            .and_then(|values| match &condition {
+                Some(condition) => condition.matches(&values).map(|m| m.then_some(values)),
+                None => Ok(Some(values)),
+            })
+            .transpose()
+    });
+
+    let columns = output_columns(&select.column_refs, &scope);
+    let sort_expressions: Vec<&Expression> = select
+        .order_by
+        .iter()
+        .map(|o| resolve_alias(&select.column_refs, &o.expression))
+        .collect();
+    let grouped_expressions: Vec<&Expression> = columns
+        .iter()
+        .map(|(_, e)| e)
+        .chain(select.having.iter())
+        .chain(sort_expressions.iter().copied())
+        .collect();
+    let is_aggregate = !select.group_by.is_empty()
+        || select.having.is_some()
+        || grouped_expressions.iter().any(|e| !find_aggregates(e).is_empty());
+    let windowed_expressions: Vec<&Expression> = columns
+        .iter()
+        .map(|(_, e)| e)
+        .chain(sort_expressions.iter().copied())
+        .collect();
+
+    let (rows, projection, sort_keys): (Rows, Vec<BoundExpression>, Vec<BoundExpression>) = if is_aggregate {
+        let aggregation = Aggregation::new(&select.group_by, &grouped_expressions, &scope)?;
+        let windows = Windows::new(&windowed_expressions, aggregation.width(), |e| aggregation.bind(e))?;
+        let bind = |e: &Expression| windows.bind(e, &|e| aggregation.resolve(e));
+        let projection = bind_all(columns.iter().map(|(_, e)| e), bind)?;
+        let sort_keys = bind_all(sort_expressions.iter().copied(), bind)?;
+        let having = select.having.as_ref().map(|h| aggregation.bind(h)?.condition()).transpose()?;
+
+        let mut groups = aggregation.run(filtered)?;
+        if let Some(having) = having {
+            let mut kept = Vec::new();
+            for group in groups {
+                if having.matches(&group)? {
+                    kept.push(group);
+                }
+            }
+            groups = kept;
+        }
+        (windows.run(Box::new(groups.into_iter().map(Ok)))?, projection, sort_keys)
+    } else {
+        let windows = Windows::new(&windowed_expressions, scope.len(), |e| binder.bind(e))?;
+        let bind = |e: &Expression| windows.bind(e, &|e| binder.resolve(e));
+        let projection = bind_all(columns.iter().map(|(_, e)| e), bind)?;
+        let sort_keys = bind_all(sort_expressions.iter().copied(), bind)?;
+        (windows.run(Box::new(filtered))?, projection, sort_keys)
+    };
+
+    let sorted: Rows = if sort_keys.is_empty() {
+        rows
+    } else {
+        let sort_keys: Vec<SortKey> = sort_keys
+            .into_iter()
+            .zip(select.order_by.iter())
+            .map(|(expression, o)| SortKey {
+                expression,
+                ascending: o.ascending,
+            })
+            .collect();
+        let rows = rows.collect::<Result<Vec<Vec<Value>>, String>>()?;
+        Box::new(sort(rows, &sort_keys)?.into_iter().map(Ok))
+    };
+    let projected: Rows = Box::new(sorted.map(move |row| {
+        row.and_then(|values| projection.iter().map(|p| p.evaluate(&values)).collect())
+    }));
+    let rows = if select.distinct { distinct(projected) } else { projected };
+
+    Ok(QueryResult {
+        column_names: columns.into_iter().map(|(name, _)| name).collect(),
+        rows: offset_and_limit(rows, select.offset, select.limit)?,
+    })
+}
+
+/// Drops rows equal to an earlier one. Nulls count as equal here.
+fn distinct(rows: Rows) -> Rows {
+    let mut seen = HashSet::new();
+    Box::new(rows.filter(move |row| match row {
+        Ok(values) => seen.insert(row_key(values)),
+        Err(_) => true,
+    }))
+}
+
+/// Combines the rows of both sides of a set operation. Without `all` the
+/// result has no duplicates. With it, `intersect` keeps a row as many times
+/// as it's on both sides, and `except` removes one of a row's occurrences on
+/// the left for every one on the right.
+fn combine(operator: SetOperator, all: bool, left: Vec<Vec<Value>>, right: Vec<Vec<Value>>) -> Vec<Vec<Value>> {
+    let mut right_counts: HashMap<Vec<Value>, usize> = HashMap::new();
+    if operator != SetOperator::Union {
+        for row in right.iter() {
+            *right_counts.entry(row_key(row)).or_default() += 1;
+        }
+    }
+
+    let mut rows: Vec<Vec<Value>> = match operator {
+        SetOperator::Union => left.into_iter().chain(right).collect(),
+        SetOperator::Intersect => left
+            .into_iter()
+            .filter(|row| match right_counts.get_mut(&row_key(row)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    true
+                }
+                _ => false,
+            })
+            .collect(),
+        SetOperator::Except => left
+            .into_iter()
+            .filter(|row| match right_counts.get_mut(&row_key(row)) {
+                Some(count) if *count > 0 => {
+                    *count -= all as usize;
+                    false
+                }
+                _ => true,
+            })
+            .collect(),
+    };
+
+    if !all {
+        let mut seen = HashSet::new();
+        rows.retain(|row| seen.insert(row_key(row)));
+    }
+    rows
+}
+
+/// The row's values as hash keys, so rows that are equal under `=` have the
+/// same key.
+pub fn row_key(row: &[Value]) -> Vec<Value> {
+    row.iter().cloned().map(Value::key).collect()
+}
+
+/// The rows of a table or subquery in `from`, and the scope they make up.
+/// A common table expression hides a table of the same name.
+fn source<'a>(database: &'a Database, reference: &TableReference, context: &Context) -> Result<(Scope, Rows<'a>), String> {
+    match reference {
+        TableReference::Table { table_name, .. } if context.table(table_name).is_some() => {
+            let named = context.table(table_name).unwrap();
+            named.referenced.set(true);
+            let result = named.result.clone();
+            Ok((
+                Scope::named(Some(reference.name()), &result.column_names),
+                Box::new((0..result.rows.len()).map(move |i| Ok(result.rows[i].clone()))),
+            ))
+        }
+        TableReference::Table { table_name, .. } => {
+            let table = database
+                .table(table_name)
+                .ok_or(format!("No table named '{}' is defined.", table_name))?;
+            Ok((Scope::table(reference.name(), &table.column_specs), Box::new(scan(table))))
+        }
+        TableReference::Subquery { query, alias } => {
+            let result = execute_in(database, query, context)?;
+            Ok((
+                Scope::named(Some(alias), &result.column_names),
+                Box::new(result.rows.into_iter().map(Ok)),
+            ))
+        }
+    }
+}
+
+fn scan(table: &Table) -> impl Iterator<Item = Result<Vec<Value>, String>> + '_ {
+    table.scan().map(|result| {
+        result
+            .map(|(_, row)| row.into_values())
+            .map_err(|err| format!("Unable to read row: {:?}", err))
+    })
+}
+
+/// The selected expression an `order by` key names by its alias, or the key
+/// itself if it isn't an alias.
+fn resolve_alias<'a>(column_refs: &'a [SelectColumnReference], expression: &'a Expression) -> &'a Expression {
+    let name = match expression {
+        Expression::Column {
+            table_name: None,
+            column_name,
+        } => column_name,
+        _ => return expression,
+    };
+
+    column_refs
+        .iter()
+        .find_map(|column_ref| match column_ref {
+            SelectColumnReference::Expression {
+                expression,
+                alias: Some(alias),
+            } if alias == name => Some(expression),
+            _ => None,
+        })
+        .unwrap_or(expression)
+}
+
+/// The name and expression of every selected column, with `*` expanded to
+/// the columns of every table in scope. A column is named by its alias, or
+/// else by how its expression is written.
+fn output_columns(column_refs: &[SelectColumnReference], scope: &Scope) -> Vec<(String, Expression)> {
+    let mut columns = Vec::new();
+    for column_ref in column_refs {
+        match column_ref {
+            SelectColumnReference::Expression { expression, alias } => {
+                let name = alias.clone().unwrap_or_else(|| format!("{}", expression));
+                columns.push((name, expression.clone()));
+            }
+            SelectColumnReference::Wildcard => {
+                columns.extend(scope.columns.iter().map(|c| {
+                    (
+                        c.column_name.clone(),
+                        Expression::Column {
+                            table_name: c.table_name.clone(),
+                            column_name: c.column_name.clone(),
+                        },
+                    )
+                }));
+            }
+        }
+    }
+    columns
+}
+
+/// Binds the expressions of a select. Unlike those of other statements,
+/// they can contain subqueries and refer to the columns of enclosing
+/// queries, which are bound to the values in their current rows.
+struct Binder<'a, 's> {
+    database: &'a Database,
+    scope: &'s Scope,
+    context: &'s Context,
+}
+
+impl<'a> Binder<'a, '_> {
+    fn bind(&self, expression: &Expression) -> Result<BoundExpression<'a>, String> {
+        BoundExpression::bind_with(expression, &|e| self.resolve(e))
+    }
+
+    fn resolve(&self, expression: &Expression) -> Result<Option<BoundExpression<'a>>, String> {
+        match expression {
+            Expression::Column {
+                table_name,
+                column_name,
+            } => {
+                if !self.scope.contains(table_name, column_name) {
+                    if let Some(frame) = self.context.outer.iter().find(|f| f.scope.contains(table_name, column_name)) {
+                        let index = frame.scope.resolve(table_name, column_name)?;
+                        return Ok(Some(BoundExpression::Literal {
+                            value: frame.value(index),
+                        }));
+                    }
+                }
+                self.scope.bind_column(table_name, column_name).map(Some)
+            }
+            Expression::Exists { query } => Ok(Some(BoundExpression::Exists {
+                subquery: Subquery::exists(self.database, query, self.scope, self.context),
+            })),
+            Expression::InSubquery {
+                expression,
+                query,
+                negated,
+            } => Ok(Some(BoundExpression::InSubquery {
+                expression: Box::new(self.bind(expression)?),
+                subquery: Subquery::in_values(self.database, query, self.scope, self.context),
+                negated: *negated,
+            })),
+            _ => Ok(None),
+        }
+    }
+}
+
+fn bind_all<'a, 'e, I, F>(expressions: I, bind: F) -> Result<Vec<BoundExpression<'a>>, String>
+where
+    I: Iterator<Item = &'e Expression>,
+    F: Fn(&Expression) -> Result<BoundExpression<'a>, String>,
+{
+    expressions.map(bind).collect()
+}
+
+fn offset_and_limit(mut rows: Rows, offset: usize, limit: Option<usize>) -> Result<Vec<Vec<Value>>, String> {
+    for _ in 0..offset {
+        if rows.next().transpose()?.is_none() {
+            return Ok(Vec::new());
+        }
+    }
+
+    let mut limited = Vec::new();
+    while limit.is_none_or(|limit| limited.len() < limit) {
+        match rows.next().transpose()? {
+            Some(values) => limited.push(values),
+            None => break,
+        }
+    }
+    Ok(limited)
+}
+
+/// A stable sort, so rows with equal keys keep their scan order.
+fn sort(rows: Vec<Vec<Value>>, sort_keys: &[SortKey]) -> Result<Vec<Vec<Value>>, String> {
+    let mut keyed_rows = rows
+        .into_iter()
+        .map(|values| {
+            let keys = sort_keys
+                .iter()
+                .map(|k| k.expression.evaluate(&values))
+                .collect::<Result<Vec<Value>, String>>()?;
+            Ok((keys, values))
+        })
+        .collect::<Result<Vec<(Vec<Value>, Vec<Value>)>, String>>()?;
+
+    keyed_rows.sort_by(|(left, _), (right, _)| {
+        sort_keys
+            .iter()
+            .zip(left.iter().zip(right.iter()))
+            .map(|(key, (l, r))| if key.ascending { l.cmp(r) } else { r.cmp(l) })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    Ok(keyed_rows.into_iter().map(|(_, values)| values).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        datetime::Date,
+        decimal::Decimal,
+        sql_parser::Statement,
+        table::{ColumnSpec, ColumnType, Row},
+    };
+
+    fn person_database(dir: &tempfile::TempDir) -> Database {
+        let column_specs = vec![
+            ColumnSpec {
+                column_name: "name".to_string(),
+                column_type: ColumnType::Varchar { max_len: 10 },
+                not_null: false,
+            },
+            ColumnSpec {
+                column_name: "age".to_string(),
+                column_type: ColumnType::Number,
+                not_null: false,
+            },
+        ];
+
+        let mut database = Database::open(dir.path(), 16).unwrap();
+        database.create_table("person", &column_specs).unwrap();
+        for (name, age) in [("Martin", 35), ("Laura", 28), ("Anna", 35), ("Tom", 7)] {
+            let column_values = HashMap::from([
+                (
+                    "name".to_string(),
+                    Value::Varchar {
+                        value: name.to_string(),
+                    },
+                ),
+                ("age".to_string(), Value::Number { value: age }),
+            ]);
+            let row = Row::new(&column_values, &column_specs).unwrap();
+            database.insert("person", &row).unwrap();
+        }
+        database
+    }
+
+    /// Adds pets, one of which has an owner who isn't a person.
+    fn pet_database(dir: &tempfile::TempDir) -> Database {
+        let column_specs = vec![
+            ColumnSpec {
+                column_name: "name".to_string(),
+                column_type: ColumnType::Varchar { max_len: 10 },
+                not_null: false,
+            },
+            ColumnSpec {
+                column_name: "owner".to_string(),
+                column_type: ColumnType::Varchar { max_len: 10 },
+                not_null: false,
+            },
+        ];
+
+        let mut database = person_database(dir);
+        database.create_table("pet", &column_specs).unwrap();
+        for (name, owner) in [("Rex", "Martin"), ("Tiddles", "Anna"), ("Polly", "Anna"), ("Nemo", "Nobody")] {
+            let column_values = HashMap::from([
+                ("name".to_string(), varchar(name)),
+                ("owner".to_string(), varchar(owner)),
+            ]);
+            let row = Row::new(&column_values, &column_specs).unwrap();
+            database.insert("pet", &row).unwrap();
+        }
+        database
+    }
+
+    /// A table with the same numbers held as an int, a number and a
+    /// decimal.
+    fn number_database(dir: &tempfile::TempDir) -> Database {
+        let column_specs: Vec<ColumnSpec> = [
+            ("n", ColumnType::Int),
+            ("m", ColumnType::Number),
+            ("x", ColumnType::Decimal { precision: 4, scale: 2 }),
+        ]
+        .into_iter()
+        .map(|(column_name, column_type)| ColumnSpec {
+            column_name: column_name.to_string(),
+            column_type,
+            not_null: false,
+        })
+        .collect();
+
+        let mut database = Database::open(dir.path(), 16).unwrap();
+        database.create_table("num", &column_specs).unwrap();
+        for value in [2, 10] {
+            let column_values = HashMap::from([
+                ("n".to_string(), Value::Int { value }),
+                ("m".to_string(), Value::Number { value: value as u64 }),
+                ("x".to_string(), Value::Decimal { value: Decimal::from_whole(value as i128) }),
+            ]);
+            let row = Row::new(&column_values, &column_specs).unwrap();
+            database.insert("num", &row).unwrap();
+        }
+        database
+    }
+
+    /// A table `p` with a date column `d` and a table `q` with a varchar
+    /// column `s`, each holding 2021-01-31.
+    fn date_database(dir: &tempfile::TempDir) -> Database {
+        let mut database = Database::open(dir.path(), 16).unwrap();
+        for (table_name, column_name, column_type, value) in [
+            ("p", "d", ColumnType::Date, Value::Date { value: Date::parse("2021-01-31").unwrap() }),
+            ("q", "s", ColumnType::Varchar { max_len: 10 }, varchar("2021-01-31")),
+        ] {
+            let column_specs = vec![ColumnSpec {
+                column_name: column_name.to_string(),
+                column_type,
+                not_null: false,
+            }];
+            database.create_table(table_name, &column_specs).unwrap();
+            let row = Row::new(&HashMap::from([(column_name.to_string(), value)]), &column_specs).unwrap();
+            database.insert(table_name, &row).unwrap();
+        }
+        database
+    }
+
+    fn varchar(value: &str) -> Value {
+        Value::Varchar {
+            value: value.to_string(),
+        }
+    }
+
+    fn run(database: &Database, query: &str) -> Result<QueryResult, String> {
+        match Statement::parse(query) {
+            Ok(("", Statement::Select(query))) => execute(database, &query),
+            other => panic!("Not a select: {:?}", other),
+        }
+    }
+
+    fn names(result: QueryResult) -> Vec<String> {
+        result
+            .rows
+            .into_iter()
+            .map(|row| match &row[0] {
+                Value::Varchar { value } => value.clone(),
+                other => panic!("Not a name: {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_select_where() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = person_database(&dir);
+
+        let result = run(&database, "select name from person where age > 10 and not name = \"Anna\"").unwrap();
+        assert_eq!(vec!["name".to_string()], result.column_names);
+        assert_eq!(vec!["Martin", "Laura"], names(result));
+
+        assert!(run(&database, "select height from person").is_err());
+        assert!(run(&database, "select * from person where height = 1").is_err());
+        assert!(run(&database, "select * from nobody").is_err());
+
+        // Types are checked before any row is read, so even no rows fail.
+        assert!(run(&database, "select * from person where name").is_err());
+        assert!(run(&database, "select * from person where age > 100 and name = 1").is_err());
+        assert!(run(&database, "select age from person group by age having age").is_err());
+        assert!(run(&database, "select * from person p join person q on p.name").is_err());
+    }
+
+    #[test]
+    fn test_order_by() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = person_database(&dir);
+
+        let result = run(&database, "select * from person order by age desc, name").unwrap();
+        assert_eq!(vec!["Anna", "Martin", "Laura", "Tom"], names(result));
+
+        let result = run(&database, "select name from person order by age").unwrap();
+        assert_eq!(vec!["Tom", "Laura", "Martin", "Anna"], names(result));
+
+        let result = run(&database, "select name from person where age < 30 order by name asc").unwrap();
+        assert_eq!(vec!["Laura", "Tom"], names(result));
+    }
+
+    #[test]
+    fn test_limit_and_offset() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = person_database(&dir);
+
+        let result = run(&database, "select name from person limit 2").unwrap();
+        assert_eq!(vec!["Martin", "Laura"], names(result));
+
+        let result = run(&database, "select name from person limit 2 offset 1").unwrap();
+        assert_eq!(vec!["Laura", "Anna"], names(result));
+
+        let result = run(&database, "select name from person order by name limit 3 offset 2").unwrap();
+        assert_eq!(vec!["Martin", "Tom"], names(result));
+
+        let result = run(&database, "select name from person limit 0").unwrap();
+        assert!(result.rows.is_empty());
+        let result = run(&database, "select name from person limit 5 offset 10").unwrap();
+        assert!(result.rows.is_empty());
+    }
+
+    #[test]
+    fn test_limit_stops_the_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = person_database(&dir);
+
+        // Tom's age makes the condition fail, so the query only succeeds if
+        // his row is never read.
+        assert!(run(&database, "select name from person where age - 8 > 0").is_err());
+        let result = run(&database, "select name from person where age - 8 > 0 limit 3").unwrap();
+        assert_eq!(vec!["Martin", "Laura", "Anna"], names(result));
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = person_database(&dir);
+        let number = |value| Value::Number { value };
+
+        let result = run(&database, "select count(*), sum(age), min(name), max(age), avg(age) from person").unwrap();
+        assert_eq!(vec!["count(*)", "sum(age)", "min(name)", "max(age)", "avg(age)"], result.column_names);
+        assert_eq!(
+            vec![vec![
+                number(4),
+                number(105),
+                Value::Varchar {
+                    value: "Anna".to_string()
+                },
+                number(35),
+                Value::Decimal {
+                    value: Decimal::parse("26.25").unwrap()
+                }
+            ]],
+            result.rows
+        );
+
+        let result = run(&database, "select count(name), sum(age) from person where age > 100").unwrap();
+        assert_eq!(vec![vec![number(0), Value::Null]], result.rows);
+
+        assert!(run(&database, "select name, count(*) from person").is_err());
+        assert!(run(&database, "select * from person where count(*) > 1").is_err());
+        assert!(run(&database, "select sum(name) from person").is_err());
+    }
+
+    #[test]
+    fn test_group_by() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = person_database(&dir);
+        let number = |value| Value::Number { value };
+
+        let result = run(&database, "select age, count(*) from person group by age order by age").unwrap();
+        assert_eq!(vec!["age", "count(*)"], result.column_names);
+        assert_eq!(
+            vec![
+                vec![number(7), number(1)],
+                vec![number(28), number(1)],
+                vec![number(35), number(2)]
+            ],
+            result.rows
+        );
+
+        let result = run(&database, "select max(name) from person group by age having count(*) > 1").unwrap();
+        assert_eq!(vec!["Martin"], names(result));
+
+        let result = run(&database, "select min(name) from person group by age order by count(*) desc, max(age) limit 2").unwrap();
+        assert_eq!(vec!["Anna", "Tom"], names(result));
+
+        let result = run(&database, "select count(*) from person group by age > 18 order by count(*)").unwrap();
+        assert_eq!(vec![vec![number(1)], vec![number(3)]], result.rows);
+
+        assert!(run(&database, "select name from person group by age").is_err());
+        assert!(run(&database, "select * from person group by age").is_err());
+        assert!(run(&database, "select age from person group by age having name = \"Tom\"").is_err());
+    }
+
+    #[test]
+    fn test_computed_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = person_database(&dir);
+        let number = |value| Value::Number { value };
+
+        let result = run(&database, "select upper(name) as shout, age * 2 double_age, age + 1 from person where age > 30").unwrap();
+        assert_eq!(vec!["shout", "double_age", "age + 1"], result.column_names);
+        assert_eq!(
+            vec![
+                vec![varchar("MARTIN"), number(70), number(36)],
+                vec![varchar("ANNA"), number(70), number(36)]
+            ],
+            result.rows
+        );
+
+        let result = run(&database, "select lower(name) as n from person order by n limit 2").unwrap();
+        assert_eq!(vec!["anna", "laura"], names(result));
+
+        let result = run(&database, "select age, count(*) * 10 as weight from person group by age order by weight desc, age").unwrap();
+        assert_eq!(vec!["age", "weight"], result.column_names);
+        assert_eq!(
+            vec![
+                vec![number(35), number(20)],
+                vec![number(7), number(10)],
+                vec![number(28), number(10)]
+            ],
+            result.rows
+        );
+
+        assert!(run(&database, "select upper(age) from person").is_err());
+        assert!(run(&database, "select age as a from person where a > 1").is_err());
+        assert!(run(&database, "select age + name from person where age > 100").is_err());
+    }
+
+    #[test]
+    fn test_case_and_functions() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = pet_database(&dir);
+
+        let result = run(&database, "select name, case when age >= 18 then \"adult\" else \"child\" end as grown from person where age < 30").unwrap();
+        assert_eq!(
+            vec![
+                vec![varchar("Laura"), varchar("adult")],
+                vec![varchar("Tom"), varchar("child")]
+            ],
+            result.rows
+        );
+
+        let result = run(&database, "select person.name || \": \" || coalesce(pet.name, \"no pet\") from person left join pet on person.name = pet.owner where age < 30").unwrap();
+        assert_eq!(vec!["Laura: no pet", "Tom: no pet"], names(result));
+
+        assert!(run(&database, "select upper(age) from person").is_err());
+        // Columns of a subquery have no known type, so this is only caught
+        // when it runs.
+        assert!(run(&database, "select upper(n) from (select age as n from person) s").is_err());
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = pet_database(&dir);
+
+        let result = run(&database, "select name from pet where name like \"%e%\" and name not ilike \"r%\"").unwrap();
+        assert_eq!(vec!["Tiddles", "Nemo"], names(result));
+
+        let result = run(&database, "select name from pet where name ~ \"^[PT]\" order by name").unwrap();
+        assert_eq!(vec!["Polly", "Tiddles"], names(result));
+
+        // The pattern can come from the row being matched.
+        let result = run(&database, "select name from pet where name like substr(owner, 1, 1) || \"%\"").unwrap();
+        assert_eq!(vec!["Nemo"], names(result));
+
+        assert!(run(&database, "select name from person where age like \"3%\"").is_err());
+    }
+
+    #[test]
+    fn test_predicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = pet_database(&dir);
+
+        let result = run(&database, "select name from person where age between 10 and 30 or name in (\"Tom\", \"Nobody\")").unwrap();
+        assert_eq!(vec!["Laura", "Tom"], names(result));
+
+        let result = run(&database, "select person.name from person left join pet on person.name = owner where pet.name is null").unwrap();
+        assert_eq!(vec!["Laura", "Tom"], names(result));
+
+        let result = run(&database, "select distinct owner from pet where owner not in (\"Nobody\") and name is not null").unwrap();
+        assert_eq!(vec!["Martin", "Anna"], names(result));
+    }
+
+    #[test]
+    fn test_nulls() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut database = person_database(&dir);
+        let column_specs = database.table("person").unwrap().column_specs.clone();
+        let row = Row::new(&HashMap::from([("name".to_string(), varchar("Nobody"))]), &column_specs).unwrap();
+        database.insert("person", &row).unwrap();
+
+        let result = run(&database, "select name from person where not age > 30").unwrap();
+        assert_eq!(vec!["Laura", "Tom"], names(result));
+
+        let result = run(&database, "select name from person where age > 30 or age is null").unwrap();
+        assert_eq!(vec!["Martin", "Anna", "Nobody"], names(result));
+
+        let result = run(&database, "select count(age), count(*) from person").unwrap();
+        assert_eq!(vec![vec![Value::Number { value: 4 }, Value::Number { value: 5 }]], result.rows);
+    }
+
+    #[test]
+    fn test_distinct() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = pet_database(&dir);
+        let number = |value| Value::Number { value };
+
+        let result = run(&database, "select distinct age from person").unwrap();
+        assert_eq!(vec![vec![number(35)], vec![number(28)], vec![number(7)]], result.rows);
+
+        let result = run(&database, "select distinct age > 18 from person order by age limit 2").unwrap();
+        assert_eq!(
+            vec![vec![Value::Boolean { value: false }], vec![Value::Boolean { value: true }]],
+            result.rows
+        );
+
+        let result = run(&database, "select distinct owner from pet order by owner desc limit 2 offset 1").unwrap();
+        assert_eq!(vec!["Martin", "Anna"], names(result));
+
+        let result = run(&database, "select distinct pet.name from person left join pet on person.name = owner where age > 10 and pet.name <> \"Rex\" or age < 30").unwrap();
+        assert_eq!(vec![vec![Value::Null], vec![varchar("Tiddles")], vec![varchar("Polly")]], result.rows);
+
+        let dir = tempfile::tempdir().unwrap();
+        let database = number_database(&dir);
+        let result = run(&database, "select distinct v from (select n as v from num union all select x as v from num) as t").unwrap();
+        assert_eq!(vec![vec![Value::Int { value: 2 }], vec![Value::Int { value: 10 }]], result.rows);
+
+        // Groups and partitions are the same way.
+        let both = "(select n as v from num union all select m as v from num) as t";
+        let result = run(&database, &format!("select v, count(*) from {} group by v", both)).unwrap();
+        assert_eq!(vec![vec![Value::Int { value: 2 }, number(2)], vec![Value::Int { value: 10 }, number(2)]], result.rows);
+        let result = run(&database, &format!("select count(*) over (partition by v) from {}", both)).unwrap();
+        assert_eq!(vec![vec![number(2)]; 4], result.rows);
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = pet_database(&dir);
+
+        let result = run(&database, "select name from person union select owner from pet order by name").unwrap();
+        assert_eq!(vec!["name".to_string()], result.column_names);
+        assert_eq!(vec!["Anna", "Laura", "Martin", "Nobody", "Tom"], names(result));
+
+        let result = run(&database, "select name from person where age > 30 union all select owner from pet").unwrap();
+        assert_eq!(vec!["Martin", "Anna", "Martin", "Anna", "Anna", "Nobody"], names(result));
+
+        let result = run(&database, "select owner from pet intersect select name from person").unwrap();
+        assert_eq!(vec!["Martin", "Anna"], names(result));
+
+        let result = run(&database, "select owner from pet intersect all select name from person union all select name from person where age = 35").unwrap();
+        assert_eq!(vec!["Martin", "Anna", "Martin", "Anna"], names(result));
+
+        let result = run(&database, "select name from person except select owner from pet").unwrap();
+        assert_eq!(vec!["Laura", "Tom"], names(result));
+
+        let result = run(&database, "select owner from pet except all select name from person where age < 30 or name = \"Anna\" order by owner desc limit 2").unwrap();
+        assert_eq!(vec!["Nobody", "Martin"], names(result));
+
+        // Rows are the same if their values are equal under `=`, whatever
+        // their types.
+        let dir = tempfile::tempdir().unwrap();
+        let database = number_database(&dir);
+        let result = run(&database, "select n from num union select m from num").unwrap();
+        assert_eq!(vec![vec![Value::Int { value: 2 }], vec![Value::Int { value: 10 }]], result.rows);
+        let result = run(&database, "select x from num where n > 5 union select 10 from num").unwrap();
+        assert_eq!(vec!["10.00"], result.rows.iter().map(|row| row[0].to_string()).collect::<Vec<String>>());
+        let result = run(&database, "select m from num intersect select x from num").unwrap();
+        assert_eq!(2, result.rows.len());
+        let result = run(&database, "select n from num except select x from num where m = 2").unwrap();
+        assert_eq!(vec![vec![Value::Int { value: 10 }]], result.rows);
+
+        let dir = tempfile::tempdir().unwrap();
+        let database = pet_database(&dir);
+        assert!(run(&database, "select name, age from person union select owner from pet").is_err());
+        assert!(run(&database, "select name from person union select owner from pet order by owner").is_err());
+    }
+
+    #[test]
+    fn test_subquery_in_from() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = pet_database(&dir);
+        let number = |value| Value::Number { value };
+
+        let result = run(&database, "select s.n from (select upper(name) as n, age from person where age > 10) s where age < 30").unwrap();
+        assert_eq!(vec!["s.n"], result.column_names);
+        assert_eq!(vec!["LAURA"], names(result));
+
+        let result = run(&database, "select p.name, c.pets from person p join (select owner, count(*) as pets from pet group by owner) as c on p.name = c.owner").unwrap();
+        assert_eq!(
+            vec![vec![varchar("Martin"), number(1)], vec![varchar("Anna"), number(2)]],
+            result.rows
+        );
+
+        let result = run(&database, "select * from (select name from person union select owner from pet) as everyone order by name desc limit 2").unwrap();
+        assert_eq!(vec!["name"], result.column_names);
+        assert_eq!(vec!["Tom", "Nobody"], names(result));
+
+        assert!(run(&database, "select * from (select name from nobody) as s").is_err());
+        assert!(run(&database, "select age from (select name from person) as s").is_err());
+    }
+
+    #[test]
+    fn test_in_and_exists() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = pet_database(&dir);
+
+        let result = run(&database, "select name from person where name in (select owner from pet)").unwrap();
+        assert_eq!(vec!["Martin", "Anna"], names(result));
+
+        let result = run(&database, "select name from person where name not in (select owner from pet where name <> \"Rex\")").unwrap();
+        assert_eq!(vec!["Martin", "Laura", "Tom"], names(result));
+
+        // The subquery returns nulls, so nothing is surely not in it.
+        let result = run(&database, "select name from person where name not in (select owner from person left join pet on person.name = owner)").unwrap();
+        assert!(result.rows.is_empty());
+
+        let result = run(&database, "select name from person p where exists (select * from pet where owner = p.name)").unwrap();
+        assert_eq!(vec!["Martin", "Anna"], names(result));
+
+        let result = run(&database, "select name from person where not exists (select * from pet where owner = person.name) order by name").unwrap();
+        assert_eq!(vec!["Laura", "Tom"], names(result));
+
+        let result = run(&database, "select name, exists (select * from pet where owner = person.name and pet.name <> \"Rex\") as cat from person where age > 30").unwrap();
+        assert_eq!(
+            vec![
+                vec![varchar("Martin"), Value::Boolean { value: false }],
+                vec![varchar("Anna"), Value::Boolean { value: true }]
+            ],
+            result.rows
+        );
+
+        // The innermost subquery refers to both queries enclosing it.
+        let result = run(&database, "select name from person p where exists (select * from pet where exists (select * from person q where q.name = pet.owner and q.age = p.age))").unwrap();
+        assert_eq!(vec!["Martin", "Anna"], names(result));
+
+        let result = run(&database, "select owner, count(*) from pet where owner in (select name from person where age > 30) group by owner").unwrap();
+        assert_eq!(
+            vec![
+                vec![varchar("Martin"), Value::Number { value: 1 }],
+                vec![varchar("Anna"), Value::Number { value: 2 }]
+            ],
+            result.rows
+        );
+
+        assert!(run(&database, "select name from person where name in (select name, owner from pet)").is_err());
+
+        // Numbers of different types are in the subquery if their values are.
+        let dir = tempfile::tempdir().unwrap();
+        let database = number_database(&dir);
+        let result = run(&database, "select n from num where 2 in (select n from num)").unwrap();
+        assert_eq!(2, result.rows.len());
+        let result = run(&database, "select n from num where m in (select x from num where n > 5)").unwrap();
+        assert_eq!(vec![vec![Value::Int { value: 10 }]], result.rows);
+        assert!(run(&database, "select name from person where exists (select * from pet where height = 1)").is_err());
+        assert!(run(&database, "select * from person join pet on exists (select * from pet)").is_err());
+    }
+
+    #[test]
+    fn test_with() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = pet_database(&dir);
+        let numbers = |result: QueryResult| -> Vec<u64> {
+            result
+                .rows
+                .into_iter()
+                .map(|row| match row[0] {
+                    Value::Number { value } => value,
+                    ref other => panic!("Not a number: {}", other),
+                })
+                .collect()
+        };
+
+        let result = run(&database, "with adults as (select name, age from person where age > 18), older as (select name from adults where age > 30) select name from older order by name").unwrap();
+        assert_eq!(vec!["Anna", "Martin"], names(result));
+
+        // A common table expression hides the table of the same name.
+        let result = run(&database, "with pet(name, owner) as (select name, name from person where age < 10) select p.name from pet p").unwrap();
+        assert_eq!(vec!["Tom"], names(result));
+
+        let result = run(&database, "with recursive t(n) as (select 1 union all select n + 1 from t where n < 5) select n from t").unwrap();
+        assert_eq!(vec!["n"], result.column_names);
+        assert_eq!(vec![1, 2, 3, 4, 5], numbers(result));
+
+        // Without all, rows already found end the recursion.
+        let result = run(&database, "with recursive t(n) as (select 0 union select 1 - n from t) select n from t").unwrap();
+        assert_eq!(vec![0, 1], numbers(result));
+
+        // The anchor's 1 is a Number and the recursive one an Int, but they're the same row.
+        let result = run(&database, "with recursive t(n) as (select 1 union select n * -1 * -1 from t) select n from t").unwrap();
+        assert_eq!(vec![1], numbers(result));
+
+        let result = run(&database, "with recursive t(n) as (select 1 union all select age from person where age < 10) select n from t").unwrap();
+        assert_eq!(vec![1, 7], numbers(result));
+
+        let result = run(&database, "select 1 + 2 as three").unwrap();
+        assert_eq!(vec!["three"], result.column_names);
+        assert_eq!(vec![3], numbers(result));
+
+        assert!(run(&database, "with t(a, b) as (select name from person) select * from t").is_err());
+        assert!(run(&database, "with t as (select * from t) select * from t").is_err());
+        assert!(run(&database, "with recursive t(n) as (select 1 union all select n, n from t) select * from t").is_err());
+        assert!(run(&database, "with recursive t(n) as (select 1 union all select n + 1 from t) select * from t").is_err());
+    }
+
+    #[test]
+    fn test_window_functions() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = pet_database(&dir);
+        let number = |value| Value::Number { value };
+
+        let result = run(&database, "select name, row_number() over (order by age desc) as n, rank() over (order by age desc), dense_rank() over (order by age desc) from person order by n").unwrap();
+        assert_eq!(
+            vec!["name", "n", "rank() over (order by age desc)", "dense_rank() over (order by age desc)"],
+            result.column_names
+        );
+        assert_eq!(
+            vec![
+                vec![varchar("Martin"), number(1), number(1), number(1)],
+                vec![varchar("Anna"), number(2), number(1), number(1)],
+                vec![varchar("Laura"), number(3), number(3), number(2)],
+                vec![varchar("Tom"), number(4), number(4), number(3)],
+            ],
+            result.rows
+        );
+
+        // Rows that sort equal are added to a running sum together.
+        let result = run(&database, "select name, sum(age) over (order by name), sum(age) over (order by age), sum(age) over () from person order by name").unwrap();
+        assert_eq!(
+            vec![
+                vec![varchar("Anna"), number(35), number(105), number(105)],
+                vec![varchar("Laura"), number(63), number(35), number(105)],
+                vec![varchar("Martin"), number(98), number(105), number(105)],
+                vec![varchar("Tom"), number(105), number(7), number(105)],
+            ],
+            result.rows
+        );
+
+        let result = run(&database, "select owner, name, row_number() over (partition by owner order by name), count(*) over (partition by owner) from pet order by owner, name").unwrap();
+        assert_eq!(
+            vec![
+                vec![varchar("Anna"), varchar("Polly"), number(1), number(2)],
+                vec![varchar("Anna"), varchar("Tiddles"), number(2), number(2)],
+                vec![varchar("Martin"), varchar("Rex"), number(1), number(1)],
+                vec![varchar("Nobody"), varchar("Nemo"), number(1), number(1)],
+            ],
+            result.rows
+        );
+
+        let result = run(&database, "select name, lag(name) over (order by age, name), lead(age, 2, 0) over (order by age, name) from person order by age, name").unwrap();
+        assert_eq!(
+            vec![
+                vec![varchar("Tom"), Value::Null, number(35)],
+                vec![varchar("Laura"), varchar("Tom"), number(35)],
+                vec![varchar("Anna"), varchar("Laura"), number(0)],
+                vec![varchar("Martin"), varchar("Anna"), number(0)],
+            ],
+            result.rows
+        );
+
+        // Window functions see the groups, not the rows grouped.
+        let result = run(&database, "select owner, rank() over (order by count(*) desc) from pet group by owner order by owner").unwrap();
+        assert_eq!(
+            vec![
+                vec![varchar("Anna"), number(1)],
+                vec![varchar("Martin"), number(2)],
+                vec![varchar("Nobody"), number(2)],
+            ],
+            result.rows
+        );
+
+        let result = run(&database, "select name, row_number() over () from person limit 2").unwrap();
+        assert_eq!(vec![vec![varchar("Martin"), number(1)], vec![varchar("Laura"), number(2)]], result.rows);
+
+        assert!(run(&database, "select name from person where rank() over (order by age) = 1").is_err());
+        assert!(run(&database, "select row_number(name) over () from person").is_err());
+        assert!(run(&database, "select lag() over (order by age) from person").is_err());
+        assert!(run(&database, "select sum(row_number() over ()) from person").is_err());
+        assert!(run(&database, "select owner, sum(age) over () from pet group by owner").is_err());
+    }
+
+    #[test]
+    fn test_inner_join() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = pet_database(&dir);
+
+        let result = run(&database, "select p.name, pet.name from person p join pet on p.name = pet.owner order by pet.name").unwrap();
+        assert_eq!(vec!["p.name", "pet.name"], result.column_names);
+        assert_eq!(
+            vec![
+                vec![varchar("Anna"), varchar("Polly")],
+                vec![varchar("Martin"), varchar("Rex")],
+                vec![varchar("Anna"), varchar("Tiddles")]
+            ],
+            result.rows
+        );
+
+        let result = run(&database, "select * from person join pet as animal on owner = person.name where age < 35 or animal.name = \"Rex\"").unwrap();
+        assert_eq!(vec!["name", "age", "name", "owner"], result.column_names);
+        assert_eq!(vec![vec![varchar("Martin"), Value::Number { value: 35 }, varchar("Rex"), varchar("Martin")]], result.rows);
+
+        // Not an equi-join, so every pair of rows is compared.
+        let result = run(&database, "select person.name from person join pet on age > 30 and owner = \"Nobody\"").unwrap();
+        assert_eq!(vec!["Martin", "Anna"], names(result));
+
+        let result = run(&database, "select a.name from person a join person b on a.age = b.age and a.name <> b.name").unwrap();
+        assert_eq!(vec!["Martin", "Anna"], names(result));
+
+        let result = run(&database, "select p.name, count(*) from person p join pet on p.name = owner group by p.name").unwrap();
+        assert_eq!(
+            vec![
+                vec![varchar("Martin"), Value::Number { value: 1 }],
+                vec![varchar("Anna"), Value::Number { value: 2 }]
+            ],
+            result.rows
+        );
+
+        assert!(run(&database, "select name from person join pet on person.name = owner").is_err());
+        assert!(run(&database, "select * from person join pet on name = owner").is_err());
+        assert!(run(&database, "select * from person join person on age = age").is_err());
+        assert!(run(&database, "select * from person p join pet on person.name = owner").is_err());
+        assert!(run(&database, "select * from person join nobody on age = 1").is_err());
+    }
+
+    #[test]
+    fn test_left_join() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = pet_database(&dir);
+
+        let result = run(&database, "select person.name, pet.name from person left join pet on person.name = owner and pet.name <> \"Polly\"").unwrap();
+        assert_eq!(
+            vec![
+                vec![varchar("Martin"), varchar("Rex")],
+                vec![varchar("Laura"), Value::Null],
+                vec![varchar("Anna"), varchar("Tiddles")],
+                vec![varchar("Tom"), Value::Null]
+            ],
+            result.rows
+        );
+
+        let result = run(&database, "select person.name from person left outer join pet on age > 30 and owner = \"Nobody\" where owner = \"Nobody\"").unwrap();
+        assert_eq!(vec!["Martin", "Anna"], names(result));
+
+        let result = run(&database, "select count(*), count(pet.name) from person left join pet on person.name = owner").unwrap();
+        assert_eq!(vec![vec![Value::Number { value: 5 }, Value::Number { value: 3 }]], result.rows);
+    }
+
+    #[test]
+    fn test_join_strategy() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = pet_database(&dir);
+        let person = Scope::table("person", &database.table("person").unwrap().column_specs);
+        let pet = Scope::table("pet", &database.table("pet").unwrap().column_specs);
+        let plan = |query: &str| match Statement::parse(query) {
+            Ok(("", Statement::Select(Query::Select(s)))) => JoinPlan::new(s.joins[0].kind, &s.joins[0].on, &person, &pet).unwrap(),
+            other => panic!("Not a select: {:?}", other),
+        };
+
+        assert!(plan("select * from person join pet on person.name = owner").is_hash_join());
+        assert!(plan("select * from person join pet on owner = person.name and age > 1").is_hash_join());
+        assert!(!plan("select * from person join pet on age + 1 = 1 + 1").is_hash_join());
+        assert!(!plan("select * from person join pet on person.name = owner or age > 1").is_hash_join());
+        assert!(!plan("select * from person join pet on person.name < owner").is_hash_join());
+    }
+
+    #[test]
+    fn test_text_and_date_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = date_database(&dir);
+        let date = Value::Date { value: Date::parse("2021-01-31").unwrap() };
+
+        // The `or` keeps the join from hashing, and both ways must agree.
+        for query in [
+            "select * from p join q on p.d = q.s",
+            "select * from p join q on p.d = q.s or 1 = 0",
+            "select * from p join q on q.s = p.d",
+        ] {
+            assert_eq!(vec![vec![date.clone(), varchar("2021-01-31")]], run(&database, query).unwrap().rows);
+        }
+
+        for query in [
+            "select * from p where d in (\"2021-01-31\")",
+            "select * from p where d in (select s from q)",
+            "select * from p where d not in (select s from q where s <> s)",
+        ] {
+            assert_eq!(vec![vec![date.clone()]], run(&database, query).unwrap().rows);
+        }
+        let result = run(&database, "select * from q where s in (select d from p)").unwrap();
+        assert_eq!(vec![vec![varchar("2021-01-31")]], result.rows);
+    }
+}
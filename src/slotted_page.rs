@@ -0,0 +1,199 @@
+//! A page of variable-length tuples. The page starts with a header holding
+//! the number of slots and where the tuples start, followed by the slot
+//! directory, which grows towards the end of the page. Each slot holds the
+//! offset and length of its tuple, or an offset of 0 if it's free. Tuples
+//! are written from the end of the page back towards the directory.
+//!
+//! Freeing a tuple leaves a hole, which is only reclaimed by compacting the
+//! page when a tuple doesn't otherwise fit. A tuple keeps its slot when the
+//! page is compacted, so the slot identifies it for as long as it lives.
+//!
+//! A page of zeros is an empty page, so new pages need no initializing.
+
+use crate::pager::PAGE_SIZE;
+
+const HEADER_LEN: usize = 4;
+const SLOT_LEN: usize = 4;
+
+/// The longest tuple that fits in an empty page.
+pub const MAX_TUPLE_LEN: usize = PAGE_SIZE - HEADER_LEN - SLOT_LEN;
+
+pub fn slot_count(page: &[u8]) -> usize {
+    read_u16(page, 0)
+}
+
+/// The tuple in `slot`, or `None` if the slot is free or doesn't exist.
+pub fn tuple(page: &[u8], slot: usize) -> Option<&[u8]> {
+    if slot >= slot_count(page) {
+        return None;
+    }
+    match slot_entry(page, slot) {
+        (0, _) => None,
+        (offset, len) => Some(&page[offset..offset + len]),
+    }
+}
+
+/// Whether `insert` would find room for a tuple of `len` bytes.
+pub fn has_room(page: &[u8], len: usize) -> bool {
+    let count = slot_count(page);
+    let new_slots = if free_slot(page).is_some() { count } else { count + 1 };
+    HEADER_LEN + new_slots * SLOT_LEN + live_len(page, None) + len <= PAGE_SIZE
+}
+
+/// Adds a tuple in the first free slot, or a new one, returning the slot.
+pub fn insert(page: &mut [u8], tuple: &[u8]) -> Option<usize> {
+    let count = slot_count(page);
+    let slot = free_slot(page).unwrap_or(count);
+    place(page, slot, count.max(slot + 1), tuple).then_some(slot)
+}
+
+/// Replaces the tuple in `slot`, returning false, with the page left as it
+/// was, if the new tuple doesn't fit.
+pub fn update(page: &mut [u8], slot: usize, tuple: &[u8]) -> bool {
+    place(page, slot, slot_count(page), tuple)
+}
+
+/// Frees the tuple in `slot`, returning whether there was one.
+pub fn delete(page: &mut [u8], slot: usize) -> bool {
+    let is_live = tuple(page, slot).is_some();
+    if is_live {
+        set_slot_entry(page, slot, 0, 0);
+    }
+    is_live
+}
+
+fn read_u16(page: &[u8], offset: usize) -> usize {
+    u16::from_be_bytes(page[offset..offset + 2].try_into().unwrap()) as usize
+}
+
+fn write_u16(page: &mut [u8], offset: usize, value: usize) {
+    page[offset..offset + 2].copy_from_slice(&(value as u16).to_be_bytes());
+}
+
+/// Where the tuples start. An empty page has none, so they start at its
+/// end.
+fn tuples_start(page: &[u8]) -> usize {
+    match read_u16(page, 2) {
+        0 => PAGE_SIZE,
+        start => start,
+    }
+}
+
+fn slot_entry(page: &[u8], slot: usize) -> (usize, usize) {
+    let entry = HEADER_LEN + slot * SLOT_LEN;
+    (read_u16(page, entry), read_u16(page, entry + 2))
+}
+
+fn set_slot_entry(page: &mut [u8], slot: usize, offset: usize, len: usize) {
+    let entry = HEADER_LEN + slot * SLOT_LEN;
+    write_u16(page, entry, offset);
+    write_u16(page, entry + 2, len);
+}
+
+fn free_slot(page: &[u8]) -> Option<usize> {
+    (0..slot_count(page)).find(|slot| slot_entry(page, *slot).0 == 0)
+}
+
+/// The bytes taken by every live tuple, except the one in `except`.
+fn live_len(page: &[u8], except: Option<usize>) -> usize {
+    (0..slot_count(page))
+        .filter(|slot| Some(*slot) != except)
+        .map(|slot| slot_entry(page, slot))
+        .filter(|(offset, _)| *offset != 0)
+        .map(|(_, len)| len)
+        .sum()
+}
+
+/// Writes `tuple` into `slot` of a page with `count` slots, compacting the
+/// page first if the free space between the directory and the tuples is
+/// too small.
+fn place(page: &mut [u8], slot: usize, count: usize, tuple: &[u8]) -> bool {
+    let directory_end = HEADER_LEN + count * SLOT_LEN;
+    if directory_end + live_len(page, Some(slot)) + tuple.len() > PAGE_SIZE {
+        return false;
+    }
+
+    if slot < slot_count(page) {
+        set_slot_entry(page, slot, 0, 0);
+    }
+    if tuples_start(page) < directory_end + tuple.len() {
+        compact(page);
+    }
+
+    let offset = tuples_start(page) - tuple.len();
+    page[offset..offset + tuple.len()].copy_from_slice(tuple);
+    write_u16(page, 0, count);
+    write_u16(page, 2, offset);
+    set_slot_entry(page, slot, offset, tuple.len());
+    true
+}
+
+/// Moves the live tuples to the end of the page, closing the holes left by
+/// freed ones.
+fn compact(page: &mut [u8]) {
+    let tuples: Vec<(usize, Vec<u8>)> = (0..slot_count(page))
+        .filter_map(|slot| tuple(page, slot).map(|tuple| (slot, tuple.to_vec())))
+        .collect();
+
+    let mut start = PAGE_SIZE;
+    for (slot, tuple) in tuples {
+        start -= tuple.len();
+        page[start..start + tuple.len()].copy_from_slice(&tuple);
+        set_slot_entry(page, slot, start, tuple.len());
+    }
+    write_u16(page, 2, start);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_delete_and_reuse() {
+        let mut page = vec![0; PAGE_SIZE];
+        assert_eq!(0, slot_count(&page));
+        assert_eq!(Some(0), insert(&mut page, b"first"));
+        assert_eq!(Some(1), insert(&mut page, b"second"));
+        assert_eq!(Some(2), insert(&mut page, b""));
+        assert_eq!(Some(&b"second"[..]), tuple(&page, 1));
+        assert_eq!(Some(&b""[..]), tuple(&page, 2));
+        assert_eq!(None, tuple(&page, 3));
+
+        assert!(delete(&mut page, 0));
+        assert!(!delete(&mut page, 0));
+        assert_eq!(None, tuple(&page, 0));
+        assert_eq!(Some(0), insert(&mut page, b"third"));
+        assert_eq!(Some(&b"third"[..]), tuple(&page, 0));
+        assert_eq!(3, slot_count(&page));
+    }
+
+    #[test]
+    fn test_compaction() {
+        let mut page = vec![0; PAGE_SIZE];
+        let big = vec![7; 1000];
+        for slot in 0..4 {
+            assert_eq!(Some(slot), insert(&mut page, &big));
+        }
+        assert!(!has_room(&page, 100));
+        assert_eq!(None, insert(&mut page, &[1; 100]));
+
+        // Only compacting the page makes room for a tuple this long.
+        assert!(delete(&mut page, 1));
+        assert!(delete(&mut page, 2));
+        assert!(has_room(&page, 1500));
+        assert_eq!(Some(1), insert(&mut page, &[1; 1500]));
+        assert_eq!(Some(&big[..]), tuple(&page, 0));
+        assert_eq!(Some(&big[..]), tuple(&page, 3));
+        assert_eq!(Some(&[1; 1500][..]), tuple(&page, 1));
+
+        // Updating can grow a tuple into the space it leaves behind.
+        assert!(update(&mut page, 1, &[2; 2000]));
+        assert!(!update(&mut page, 1, &[3; 2100]));
+        assert_eq!(Some(&[2; 2000][..]), tuple(&page, 1));
+        assert_eq!(Some(&big[..]), tuple(&page, 0));
+
+        let mut empty = vec![0; PAGE_SIZE];
+        assert_eq!(None, insert(&mut empty, &vec![0; MAX_TUPLE_LEN + 1]));
+        assert_eq!(Some(0), insert(&mut empty, &vec![0; MAX_TUPLE_LEN]));
+    }
+}
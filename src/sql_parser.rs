@@ -2,14 +2,20 @@ use std::collections::HashMap;
 
 use nom::{
     branch::alt,
-    bytes::complete::{tag, tag_no_case, take_until},
+    bytes::complete::{tag, tag_no_case, take_until, take_while},
     character::complete::{self, *},
     combinator::*,
-    multi::{many0, many1, separated_list1},
-    sequence::{preceded, terminated, tuple},
+    multi::{fold_many0, many0, many1, separated_list0, separated_list1},
+    sequence::{delimited, preceded, terminated, tuple},
     *,
 };
 
+use crate::{
+    datetime::{Date, Formats, Interval, Time, Timestamp},
+    decimal::Decimal,
+    table::parse_hex,
+};
+
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct CreateTable {
     pub table_name: String,
@@ -18,8 +24,284 @@ pub struct CreateTable {
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Select {
+    pub distinct: bool,
     pub column_refs: Vec<SelectColumnReference>,
-    pub table_name: String,
+    pub from: Option<TableReference>,
+    pub joins: Vec<Join>,
+    pub where_clause: Option<Expression>,
+    pub group_by: Vec<Expression>,
+    pub having: Option<Expression>,
+    pub order_by: Vec<OrderBy>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+impl Select {
+    /// Parses a select up to its `order by`, which along with `limit` and
+    /// `offset` belongs to the whole query when selects are combined.
+    fn parse(input: &str) -> IResult<&str, Select> {
+        let (input, _) = parse_keyword("select")(input)?;
+        let (input, distinct) = map(opt(parse_keyword("distinct")), |d| d.is_some())(input)?;
+        let (input, column_refs) = separated_list1(tag(","), SelectColumnReference::parse)(input)?;
+        let (input, from) = opt(preceded(
+            parse_keyword("from"),
+            tuple((TableReference::parse, many0(Join::parse))),
+        ))(input)?;
+        let (from, joins) = match from {
+            Some((from, joins)) => (Some(from), joins),
+            None => (None, Vec::new()),
+        };
+        let (input, where_clause) = parse_where_clause(input)?;
+        let (input, group_by) = parse_group_by_clause(input)?;
+        let (input, having) = opt(preceded(parse_keyword("having"), Expression::parse))(input)?;
+        Ok((
+            input,
+            Select {
+                distinct,
+                column_refs,
+                from,
+                joins,
+                where_clause,
+                group_by,
+                having,
+                order_by: Vec::new(),
+                limit: None,
+                offset: 0,
+            },
+        ))
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum SetOperator {
+    Union,
+    Intersect,
+    Except,
+}
+
+impl SetOperator {
+    fn parse_intersect(input: &str) -> IResult<&str, SetOperator> {
+        value(SetOperator::Intersect, parse_keyword("intersect"))(input)
+    }
+
+    fn parse_union_or_except(input: &str) -> IResult<&str, SetOperator> {
+        alt((
+            value(SetOperator::Union, parse_keyword("union")),
+            value(SetOperator::Except, parse_keyword("except")),
+        ))(input)
+    }
+}
+
+/// A named query in a `with` clause, which the rest of the query can select
+/// from like a table.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct CommonTableExpression {
+    pub name: String,
+    pub column_names: Vec<String>,
+    pub query: Query,
+}
+
+impl CommonTableExpression {
+    fn parse(input: &str) -> IResult<&str, CommonTableExpression> {
+        let (input, name) = parse_id(input)?;
+        let (input, column_names) = map(
+            opt(delimited(
+                parse_keyword("("),
+                separated_list1(tag(","), parse_id),
+                parse_keyword(")"),
+            )),
+            Option::unwrap_or_default,
+        )(input)?;
+        let (input, _) = parse_keyword("as")(input)?;
+        let (input, query) = parse_subquery(input)?;
+        Ok((
+            input,
+            CommonTableExpression {
+                name,
+                column_names,
+                query,
+            },
+        ))
+    }
+}
+
+/// A select, or selects combined by set operations. The `order by`,
+/// `limit` and `offset` of a single select are kept on the select, where
+/// they can refer to any column in scope, and those of combined selects on
+/// the set operation, where they refer to its output columns.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Query {
+    /// A query preceded by a `with` clause. In a recursive one, a table can
+    /// select from itself.
+    With {
+        recursive: bool,
+        tables: Vec<CommonTableExpression>,
+        query: Box<Query>,
+    },
+    Select(Box<Select>),
+    SetOperation {
+        operator: SetOperator,
+        all: bool,
+        left: Box<Query>,
+        right: Box<Query>,
+        order_by: Vec<OrderBy>,
+        limit: Option<usize>,
+        offset: usize,
+    },
+}
+
+/// Parses a left-associative chain of `operand`s joined by the set
+/// operators `operator` accepts.
+fn parse_set_operation_chain<'a>(
+    operand: fn(&'a str) -> IResult<&'a str, Query>,
+    operator: fn(&'a str) -> IResult<&'a str, SetOperator>,
+) -> impl Fn(&'a str) -> IResult<&'a str, Query> {
+    move |input| {
+        let (input, first) = operand(input)?;
+        fold_many0(
+            tuple((operator, map(opt(parse_keyword("all")), |a| a.is_some()), operand)),
+            move || first.clone(),
+            |left, (operator, all, right)| Query::SetOperation {
+                operator,
+                all,
+                left: Box::new(left),
+                right: Box::new(right),
+                order_by: Vec::new(),
+                limit: None,
+                offset: 0,
+            },
+        )(input)
+    }
+}
+
+impl Query {
+    fn parse_select(input: &str) -> IResult<&str, Query> {
+        map(Select::parse, |select| Query::Select(Box::new(select)))(input)
+    }
+
+    fn parse_intersections(input: &str) -> IResult<&str, Query> {
+        parse_set_operation_chain(Query::parse_select, SetOperator::parse_intersect)(input)
+    }
+
+    fn parse_with(input: &str) -> IResult<&str, Query> {
+        let (input, _) = parse_keyword("with")(input)?;
+        let (input, recursive) = map(opt(parse_keyword("recursive")), |r| r.is_some())(input)?;
+        let (input, tables) = separated_list1(tag(","), CommonTableExpression::parse)(input)?;
+        let (input, query) = Query::parse(input)?;
+        Ok((
+            input,
+            Query::With {
+                recursive,
+                tables,
+                query: Box::new(query),
+            },
+        ))
+    }
+
+    /// Parses a query. `intersect` binds tighter than `union` and `except`.
+    pub fn parse(input: &str) -> IResult<&str, Query> {
+        if let Ok(parsed) = Query::parse_with(input) {
+            return Ok(parsed);
+        }
+
+        let (input, mut query) =
+            parse_set_operation_chain(Query::parse_intersections, SetOperator::parse_union_or_except)(input)?;
+        let (input, order_by_clause) = parse_order_by_clause(input)?;
+        let (input, limit_clause) = opt(preceded(parse_keyword("limit"), parse_count))(input)?;
+        let (input, offset_clause) = opt(preceded(parse_keyword("offset"), parse_count))(input)?;
+
+        match &mut query {
+            Query::With { .. } => unreachable!("with is only parsed first"),
+            Query::Select(select) => {
+                select.order_by = order_by_clause;
+                select.limit = limit_clause;
+                select.offset = offset_clause.unwrap_or(0);
+            }
+            Query::SetOperation {
+                order_by,
+                limit,
+                offset,
+                ..
+            } => {
+                *order_by = order_by_clause;
+                *limit = limit_clause;
+                *offset = offset_clause.unwrap_or(0);
+            }
+        }
+        Ok((input, query))
+    }
+}
+
+/// Where a select or join reads its rows from: a table, or the result of
+/// a subquery, which must be given an alias.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum TableReference {
+    Table {
+        table_name: String,
+        alias: Option<String>,
+    },
+    Subquery {
+        query: Box<Query>,
+        alias: String,
+    },
+}
+
+impl TableReference {
+    /// The name the source's columns are qualified with.
+    pub fn name(&self) -> &String {
+        match self {
+            TableReference::Table { table_name, alias } => alias.as_ref().unwrap_or(table_name),
+            TableReference::Subquery { alias, .. } => alias,
+        }
+    }
+
+    fn parse(input: &str) -> IResult<&str, TableReference> {
+        alt((
+            map(
+                tuple((parse_subquery, map_opt(parse_alias, |alias| alias))),
+                |(query, alias)| TableReference::Subquery {
+                    query: Box::new(query),
+                    alias,
+                },
+            ),
+            map(tuple((parse_id, parse_alias)), |(table_name, alias)| {
+                TableReference::Table { table_name, alias }
+            }),
+        ))(input)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum JoinKind {
+    Inner,
+    Left,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Join {
+    pub kind: JoinKind,
+    pub table: TableReference,
+    pub on: Expression,
+}
+
+impl Join {
+    fn parse(input: &str) -> IResult<&str, Join> {
+        let (input, kind) = alt((
+            value(JoinKind::Left, tuple((parse_keyword("left"), opt(parse_keyword("outer"))))),
+            value(JoinKind::Inner, opt(parse_keyword("inner"))),
+        ))(input)?;
+        let (input, _) = parse_keyword("join")(input)?;
+        let (input, table) = TableReference::parse(input)?;
+        let (input, _) = parse_keyword("on")(input)?;
+        let (input, on) = Expression::parse(input)?;
+        Ok((input, Join { kind, table, on }))
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct OrderBy {
+    pub expression: Expression,
+    pub ascending: bool,
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -34,25 +316,54 @@ pub struct CsvImport {
     pub column_mapping: HashMap<String, String>,
     pub file_path: String,
     pub table_name: String,
-    pub with_truncate: bool
+    pub with_truncate: bool,
+    pub formats: Formats,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Delete {
+    pub table_name: String,
+    pub where_clause: Option<Expression>,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Update {
+    pub table_name: String,
+    pub assignments: Vec<(String, Expression)>,
+    pub where_clause: Option<Expression>,
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum Statement {
     CreateTable(CreateTable),
     ShowTables,
-    Select(Select),
+    Select(Query),
     Insert(Insert),
     CsvImport(CsvImport),
+    Delete(Delete),
+    Update(Update),
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum InsertValue {
     Varchar { value: String },
     Number { value: u64 },
+    Int { value: i64 },
+    Decimal { value: Decimal },
+    Double { value: f64 },
     Boolean { value: bool },
+    Date { value: Date },
+    Time { value: Time },
+    Timestamp { value: Timestamp },
+    Interval { value: Interval },
+    Blob { value: Vec<u8> },
+    Null,
 }
 
+/// A double written in a statement is never NaN, so it always equals
+/// itself.
+impl Eq for InsertValue {}
+
 fn parse_string(input: &str) -> IResult<&str, String> {
     let (input, _) = preceded(multispace0, tag("\""))(input)?;
     let (input, value) = take_until("\"")(input)?;
@@ -64,13 +375,44 @@ impl InsertValue {
     //TODO: allow escapes
     fn parse_varchar(input: &str) -> IResult<&str, InsertValue> {
         let (input, value) = parse_string(input)?;
-        Ok((input, InsertValue::Varchar { value: value }))
+        Ok((input, InsertValue::Varchar { value }))
+    }
+
+    /// Bytes written as hexadecimal digits, like `x"0aff"`.
+    fn parse_blob(input: &str) -> IResult<&str, InsertValue> {
+        let (input, _) = tuple((multispace0, tag_no_case("x"), peek(tag("\""))))(input)?;
+        map_opt(parse_string, |digits| parse_hex(&digits).map(|value| InsertValue::Blob { value }))(input)
     }
 
+    /// Whole numbers are unsigned unless they're negative. A number with a
+    /// decimal point is a decimal, and one with an exponent, like `1.5e3`,
+    /// a double.
     fn parse_number(input: &str) -> IResult<&str, InsertValue> {
-        map(
-            terminated(preceded(multispace0, complete::u64), multispace0),
-            |value| InsertValue::Number { value },
+        let exponent = tuple((one_of("eE"), opt(one_of("+-")), digit1));
+        map_opt(
+            terminated(
+                preceded(
+                    multispace0,
+                    recognize(tuple((
+                        opt(char('-')),
+                        digit1,
+                        opt(tuple((char('.'), digit1))),
+                        opt(exponent),
+                    ))),
+                ),
+                multispace0,
+            ),
+            |text: &str| {
+                if text.contains(['e', 'E']) {
+                    text.parse().ok().map(|value| InsertValue::Double { value })
+                } else if text.contains('.') {
+                    Decimal::parse(text).map(|value| InsertValue::Decimal { value })
+                } else if text.starts_with('-') {
+                    text.parse().ok().map(|value| InsertValue::Int { value })
+                } else {
+                    text.parse().ok().map(|value| InsertValue::Number { value })
+                }
+            },
         )(input)
     }
 
@@ -84,18 +426,592 @@ impl InsertValue {
         ))(input)
     }
 
+    /// A date or time written as its type followed by text in ISO-8601,
+    /// like `date "2017-01-31"`, or an interval like `interval "2 days"`.
+    fn parse_time(input: &str) -> IResult<&str, InsertValue> {
+        alt((
+            map_opt(preceded(parse_keyword("date"), parse_string), |text| {
+                Date::parse(&text).map(|value| InsertValue::Date { value })
+            }),
+            map_opt(preceded(parse_keyword("time"), parse_string), |text| {
+                Time::parse(&text).map(|value| InsertValue::Time { value })
+            }),
+            map_opt(preceded(parse_keyword("timestamp"), parse_string), |text| {
+                Timestamp::parse(&text).map(|value| InsertValue::Timestamp { value })
+            }),
+            map_opt(preceded(parse_keyword("interval"), parse_string), |text| {
+                Interval::parse(&text).map(|value| InsertValue::Interval { value })
+            }),
+        ))(input)
+    }
+
     fn parse(input: &str) -> IResult<&str, InsertValue> {
         alt((
             InsertValue::parse_varchar,
+            InsertValue::parse_blob,
             InsertValue::parse_number,
             InsertValue::parse_boolean,
+            InsertValue::parse_time,
+            value(InsertValue::Null, parse_keyword("null")),
+        ))(input)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ComparisonOperator {
+    Equal,
+    NotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+}
+
+impl ComparisonOperator {
+    fn parse(input: &str) -> IResult<&str, ComparisonOperator> {
+        alt((
+            value(ComparisonOperator::LessThanOrEqual, parse_keyword("<=")),
+            value(ComparisonOperator::GreaterThanOrEqual, parse_keyword(">=")),
+            value(ComparisonOperator::NotEqual, parse_keyword("<>")),
+            value(ComparisonOperator::NotEqual, parse_keyword("!=")),
+            value(ComparisonOperator::Equal, parse_keyword("=")),
+            value(ComparisonOperator::LessThan, parse_keyword("<")),
+            value(ComparisonOperator::GreaterThan, parse_keyword(">")),
+        ))(input)
+    }
+}
+
+/// How a pattern is matched: `like` and `ilike` patterns, in which `%` and
+/// `_` are wildcards, match a whole value and `ilike` ignores case.
+/// Regular expressions, written with `regexp` or `~`, can match part of it.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum PatternKind {
+    Like,
+    ILike,
+    Regex,
+}
+
+impl PatternKind {
+    /// Parses a pattern operator, returning whether it's negated too.
+    fn parse_operator(input: &str) -> IResult<&str, (PatternKind, bool)> {
+        alt((
+            map(
+                tuple((
+                    opt(parse_keyword("not")),
+                    alt((
+                        value(PatternKind::Like, parse_keyword("like")),
+                        value(PatternKind::ILike, parse_keyword("ilike")),
+                        value(PatternKind::Regex, parse_keyword("regexp")),
+                    )),
+                )),
+                |(not, kind)| (kind, not.is_some()),
+            ),
+            value((PatternKind::Regex, true), parse_keyword("!~")),
+            value((PatternKind::Regex, false), parse_keyword("~")),
+        ))(input)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ArithmeticOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    /// `||`, which joins its operands' text.
+    Concat,
+}
+
+impl ArithmeticOperator {
+    fn parse_additive(input: &str) -> IResult<&str, ArithmeticOperator> {
+        alt((
+            value(ArithmeticOperator::Add, parse_keyword("+")),
+            value(ArithmeticOperator::Subtract, parse_keyword("-")),
+        ))(input)
+    }
+
+    fn parse_multiplicative(input: &str) -> IResult<&str, ArithmeticOperator> {
+        alt((
+            value(ArithmeticOperator::Multiply, parse_keyword("*")),
+            value(ArithmeticOperator::Divide, parse_keyword("/")),
+        ))(input)
+    }
+
+    fn parse_concatenation(input: &str) -> IResult<&str, ArithmeticOperator> {
+        value(ArithmeticOperator::Concat, parse_keyword("||"))(input)
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+}
+
+impl AggregateFunction {
+    fn parse(input: &str) -> IResult<&str, AggregateFunction> {
+        alt((
+            value(AggregateFunction::Count, parse_keyword("count")),
+            value(AggregateFunction::Sum, parse_keyword("sum")),
+            value(AggregateFunction::Min, parse_keyword("min")),
+            value(AggregateFunction::Max, parse_keyword("max")),
+            value(AggregateFunction::Avg, parse_keyword("avg")),
+        ))(input)
+    }
+}
+
+/// Parses `function(expression)`, or `count(*)` which has no argument.
+fn parse_aggregate_call(input: &str) -> IResult<&str, (AggregateFunction, Option<Expression>)> {
+    let (input, function) = AggregateFunction::parse(input)?;
+    let (input, _) = parse_keyword("(")(input)?;
+    let (input, argument) = if function == AggregateFunction::Count {
+        alt((value(None, parse_keyword("*")), map(Expression::parse, Some)))(input)?
+    } else {
+        map(Expression::parse, Some)(input)?
+    };
+    let (input, _) = parse_keyword(")")(input)?;
+    Ok((input, (function, argument)))
+}
+
+/// A function computing a value for each row from the rows of its window:
+/// the rows in the same partition, in the window's order.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum WindowFunction {
+    RowNumber,
+    Rank,
+    DenseRank,
+    Lag,
+    Lead,
+    /// An aggregate over the rows up to the current one and any that sort
+    /// equal to it, or over the whole partition if the window has no order.
+    Aggregate(AggregateFunction),
+}
+
+impl WindowFunction {
+    fn parse(input: &str) -> IResult<&str, WindowFunction> {
+        alt((
+            value(WindowFunction::RowNumber, parse_keyword("row_number")),
+            value(WindowFunction::DenseRank, parse_keyword("dense_rank")),
+            value(WindowFunction::Rank, parse_keyword("rank")),
+            value(WindowFunction::Lag, parse_keyword("lag")),
+            value(WindowFunction::Lead, parse_keyword("lead")),
+        ))(input)
+    }
+}
+
+/// Parses `function(arguments) over ([partition by ...] [order by ...])`.
+fn parse_window_call(input: &str) -> IResult<&str, Expression> {
+    let (input, (function, arguments)) = alt((
+        map(parse_aggregate_call, |(function, argument)| {
+            (WindowFunction::Aggregate(function), argument.into_iter().collect())
+        }),
+        tuple((
+            WindowFunction::parse,
+            delimited(
+                parse_keyword("("),
+                separated_list0(tag(","), Expression::parse),
+                parse_keyword(")"),
+            ),
+        )),
+    ))(input)?;
+    let (input, _) = tuple((parse_keyword("over"), parse_keyword("(")))(input)?;
+    let (input, partition_by) = map(
+        opt(preceded(
+            tuple((parse_keyword("partition"), parse_keyword("by"))),
+            separated_list1(tag(","), Expression::parse),
+        )),
+        Option::unwrap_or_default,
+    )(input)?;
+    let (input, order_by) = parse_order_by_clause(input)?;
+    let (input, _) = parse_keyword(")")(input)?;
+    Ok((
+        input,
+        Expression::Window {
+            function,
+            arguments,
+            partition_by,
+            order_by,
+        },
+    ))
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Expression {
+    Column {
+        table_name: Option<String>,
+        column_name: String,
+    },
+    Literal {
+        value: InsertValue,
+    },
+    Comparison {
+        left: Box<Expression>,
+        operator: ComparisonOperator,
+        right: Box<Expression>,
+    },
+    Arithmetic {
+        left: Box<Expression>,
+        operator: ArithmeticOperator,
+        right: Box<Expression>,
+    },
+    And {
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    Or {
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    Not {
+        expression: Box<Expression>,
+    },
+    Aggregate {
+        function: AggregateFunction,
+        argument: Option<Box<Expression>>,
+    },
+    Function {
+        name: String,
+        arguments: Vec<Expression>,
+    },
+    Exists {
+        query: Box<Query>,
+    },
+    InSubquery {
+        expression: Box<Expression>,
+        query: Box<Query>,
+        negated: bool,
+    },
+    Window {
+        function: WindowFunction,
+        arguments: Vec<Expression>,
+        partition_by: Vec<Expression>,
+        order_by: Vec<OrderBy>,
+    },
+    PatternMatch {
+        expression: Box<Expression>,
+        kind: PatternKind,
+        pattern: Box<Expression>,
+        negated: bool,
+    },
+    InList {
+        expression: Box<Expression>,
+        list: Vec<Expression>,
+        negated: bool,
+    },
+    Between {
+        expression: Box<Expression>,
+        low: Box<Expression>,
+        high: Box<Expression>,
+        negated: bool,
+    },
+    IsNull {
+        expression: Box<Expression>,
+        negated: bool,
+    },
+    /// `case [operand] when ... then ... [else ...] end`.
+    Case {
+        operand: Option<Box<Expression>>,
+        branches: Vec<(Expression, Expression)>,
+        otherwise: Option<Box<Expression>>,
+    },
+}
+
+/// Parses a `case` expression. Without an operand, each `when` is a
+/// condition.
+fn parse_case(input: &str) -> IResult<&str, Expression> {
+    let (input, _) = parse_keyword("case")(input)?;
+    let (input, operand) = opt(preceded(not(parse_keyword("when")), Expression::parse))(input)?;
+    let (input, branches) = many1(tuple((
+        preceded(parse_keyword("when"), Expression::parse),
+        preceded(parse_keyword("then"), Expression::parse),
+    )))(input)?;
+    let (input, otherwise) = opt(preceded(parse_keyword("else"), Expression::parse))(input)?;
+    let (input, _) = parse_keyword("end")(input)?;
+    Ok((
+        input,
+        Expression::Case {
+            operand: operand.map(Box::new),
+            branches,
+            otherwise: otherwise.map(Box::new),
+        },
+    ))
+}
+
+/// Parses `extract(field from expression)` as a call to the `extract`
+/// function, with the field's name as its first argument.
+fn parse_extract(input: &str) -> IResult<&str, Expression> {
+    let (input, _) = tuple((parse_keyword("extract"), parse_keyword("(")))(input)?;
+    let (input, field) = parse_id(input)?;
+    let (input, expression) = preceded(parse_keyword("from"), Expression::parse)(input)?;
+    let (input, _) = parse_keyword(")")(input)?;
+    Ok((
+        input,
+        Expression::Function {
+            name: "extract".to_string(),
+            arguments: vec![
+                Expression::Literal {
+                    value: InsertValue::Varchar {
+                        value: field.to_lowercase(),
+                    },
+                },
+                expression,
+            ],
+        },
+    ))
+}
+
+/// Parses a left-associative chain of `operand`s joined by the operators
+/// `operator` accepts.
+fn parse_arithmetic_chain<'a>(
+    operand: fn(&'a str) -> IResult<&'a str, Expression>,
+    operator: fn(&'a str) -> IResult<&'a str, ArithmeticOperator>,
+) -> impl Fn(&'a str) -> IResult<&'a str, Expression> {
+    move |input| {
+        let (input, first) = operand(input)?;
+        fold_many0(
+            tuple((operator, operand)),
+            move || first.clone(),
+            |left, (operator, right)| Expression::Arithmetic {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            },
+        )(input)
+    }
+}
+
+impl Expression {
+    fn parse_operand(input: &str) -> IResult<&str, Expression> {
+        alt((
+            delimited(parse_keyword("("), Expression::parse, parse_keyword(")")),
+            parse_window_call,
+            map(parse_aggregate_call, |(function, argument)| Expression::Aggregate {
+                function,
+                argument: argument.map(Box::new),
+            }),
+            map(preceded(parse_keyword("exists"), parse_subquery), |query| Expression::Exists {
+                query: Box::new(query),
+            }),
+            map(InsertValue::parse, |value| Expression::Literal { value }),
+            parse_case,
+            parse_extract,
+            map(
+                tuple((
+                    parse_id,
+                    delimited(
+                        parse_keyword("("),
+                        separated_list0(tag(","), Expression::parse),
+                        parse_keyword(")"),
+                    ),
+                )),
+                |(name, arguments)| Expression::Function { name, arguments },
+            ),
+            map(parse_column_reference, |(table_name, column_name)| Expression::Column {
+                table_name,
+                column_name,
+            }),
+        ))(input)
+    }
+
+    fn parse_multiplicative(input: &str) -> IResult<&str, Expression> {
+        parse_arithmetic_chain(Expression::parse_operand, ArithmeticOperator::parse_multiplicative)(input)
+    }
+
+    fn parse_additive(input: &str) -> IResult<&str, Expression> {
+        parse_arithmetic_chain(Expression::parse_multiplicative, ArithmeticOperator::parse_additive)(input)
+    }
+
+    fn parse_concatenation(input: &str) -> IResult<&str, Expression> {
+        parse_arithmetic_chain(Expression::parse_additive, ArithmeticOperator::parse_concatenation)(input)
+    }
+
+    fn parse_comparison(input: &str) -> IResult<&str, Expression> {
+        let (input, left) = Expression::parse_concatenation(input)?;
+
+        let (input, in_subquery) = opt(tuple((
+            map(opt(parse_keyword("not")), |not| not.is_some()),
+            preceded(parse_keyword("in"), parse_subquery),
+        )))(input)?;
+        if let Some((negated, query)) = in_subquery {
+            return Ok((
+                input,
+                Expression::InSubquery {
+                    expression: Box::new(left),
+                    query: Box::new(query),
+                    negated,
+                },
+            ));
+        }
+
+        let (input, in_list) = opt(tuple((
+            map(opt(parse_keyword("not")), |not| not.is_some()),
+            preceded(
+                parse_keyword("in"),
+                delimited(
+                    parse_keyword("("),
+                    separated_list1(tag(","), Expression::parse),
+                    parse_keyword(")"),
+                ),
+            ),
+        )))(input)?;
+        if let Some((negated, list)) = in_list {
+            return Ok((
+                input,
+                Expression::InList {
+                    expression: Box::new(left),
+                    list,
+                    negated,
+                },
+            ));
+        }
+
+        // The bounds can't be any looser than `||`, or the `and` between
+        // them would be taken for a logical one.
+        let (input, between) = opt(tuple((
+            map(opt(parse_keyword("not")), |not| not.is_some()),
+            preceded(parse_keyword("between"), Expression::parse_concatenation),
+            preceded(parse_keyword("and"), Expression::parse_concatenation),
+        )))(input)?;
+        if let Some((negated, low, high)) = between {
+            return Ok((
+                input,
+                Expression::Between {
+                    expression: Box::new(left),
+                    low: Box::new(low),
+                    high: Box::new(high),
+                    negated,
+                },
+            ));
+        }
+
+        let (input, is_null) = opt(delimited(
+            parse_keyword("is"),
+            map(opt(parse_keyword("not")), |not| not.is_some()),
+            parse_keyword("null"),
+        ))(input)?;
+        if let Some(negated) = is_null {
+            return Ok((
+                input,
+                Expression::IsNull {
+                    expression: Box::new(left),
+                    negated,
+                },
+            ));
+        }
+
+        let (input, pattern) = opt(tuple((PatternKind::parse_operator, Expression::parse_concatenation)))(input)?;
+        if let Some(((kind, negated), pattern)) = pattern {
+            return Ok((
+                input,
+                Expression::PatternMatch {
+                    expression: Box::new(left),
+                    kind,
+                    pattern: Box::new(pattern),
+                    negated,
+                },
+            ));
+        }
+
+        let (input, right) = opt(tuple((ComparisonOperator::parse, Expression::parse_concatenation)))(input)?;
+        let expression = match right {
+            Some((operator, right)) => Expression::Comparison {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            },
+            None => left,
+        };
+        Ok((input, expression))
+    }
+
+    fn parse_not(input: &str) -> IResult<&str, Expression> {
+        alt((
+            map(preceded(parse_keyword("not"), Expression::parse_not), |expression| {
+                Expression::Not {
+                    expression: Box::new(expression),
+                }
+            }),
+            Expression::parse_comparison,
         ))(input)
     }
+
+    fn parse_and(input: &str) -> IResult<&str, Expression> {
+        let (input, first) = Expression::parse_not(input)?;
+        fold_many0(
+            preceded(parse_keyword("and"), Expression::parse_not),
+            move || first.clone(),
+            |left, right| Expression::And {
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+        )(input)
+    }
+
+    /// Parses an expression. From loosest to tightest binding the operators
+    /// are `or`, `and`, `not`, comparisons, `||`, `+ -` and `* /`.
+    pub fn parse(input: &str) -> IResult<&str, Expression> {
+        let (input, first) = Expression::parse_and(input)?;
+        fold_many0(
+            preceded(parse_keyword("or"), Expression::parse_and),
+            move || first.clone(),
+            |left, right| Expression::Or {
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+        )(input)
+    }
+}
+
+fn parse_where_clause(input: &str) -> IResult<&str, Option<Expression>> {
+    opt(preceded(parse_keyword("where"), Expression::parse))(input)
+}
+
+impl OrderBy {
+    fn parse(input: &str) -> IResult<&str, OrderBy> {
+        let (input, expression) = Expression::parse(input)?;
+        let (input, ascending) = opt(alt((
+            value(true, parse_keyword("asc")),
+            value(false, parse_keyword("desc")),
+        )))(input)?;
+        Ok((
+            input,
+            OrderBy {
+                expression,
+                ascending: ascending.unwrap_or(true),
+            },
+        ))
+    }
+}
+
+fn parse_group_by_clause(input: &str) -> IResult<&str, Vec<Expression>> {
+    map(
+        opt(preceded(
+            tuple((parse_keyword("group"), parse_keyword("by"))),
+            separated_list1(tag(","), Expression::parse),
+        )),
+        Option::unwrap_or_default,
+    )(input)
+}
+
+fn parse_order_by_clause(input: &str) -> IResult<&str, Vec<OrderBy>> {
+    map(
+        opt(preceded(
+            tuple((parse_keyword("order"), parse_keyword("by"))),
+            separated_list1(tag(","), OrderBy::parse),
+        )),
+        Option::unwrap_or_default,
+    )(input)
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum SelectColumnReference {
-    Named { column_name: String },
+    Expression {
+        expression: Expression,
+        alias: Option<String>,
+    },
     Wildcard,
 }
 
@@ -103,8 +1019,8 @@ impl SelectColumnReference {
     fn parse(input: &str) -> IResult<&str, SelectColumnReference> {
         alt((
             value(SelectColumnReference::Wildcard, parse_keyword("*")),
-            map(parse_id, |column_name| SelectColumnReference::Named {
-                column_name,
+            map(tuple((Expression::parse, parse_alias)), |(expression, alias)| {
+                SelectColumnReference::Expression { expression, alias }
             }),
         ))(input)
     }
@@ -129,17 +1045,7 @@ impl Statement {
     }
 
     fn parse_select(input: &str) -> IResult<&str, Statement> {
-        let (input, _) = parse_keyword("select")(input)?;
-        let (input, column_refs) = separated_list1(tag(","), SelectColumnReference::parse)(input)?;
-        let (input, _) = parse_keyword("from")(input)?;
-        let (input, table_name) = parse_id(input)?;
-        Ok((
-            input,
-            Statement::Select(Select {
-                column_refs,
-                table_name,
-            }),
-        ))
+        map(Query::parse, Statement::Select)(input)
     }
 
     fn parse_csv_column_mapping(input: &str) -> IResult<&str, (String, String)> {
@@ -162,6 +1068,20 @@ impl Statement {
             separated_list1(tag(","), Statement::parse_csv_column_mapping)(input)?;
         let (input, _) = parse_keyword(")")(input)?;
         let (input, with_truncate) = opt(parse_keyword("truncate"))(input)?;
+        let (input, format_clauses) = many0(tuple((
+            alt((parse_keyword("date"), parse_keyword("timestamp"), parse_keyword("time"))),
+            preceded(parse_keyword("format"), parse_string),
+        )))(input)?;
+
+        let mut formats = Formats::default();
+        for (column_type, format) in format_clauses {
+            let slot = match column_type.trim().to_lowercase().as_str() {
+                "date" => &mut formats.date,
+                "time" => &mut formats.time,
+                _ => &mut formats.timestamp,
+            };
+            *slot = Some(format);
+        }
 
         Ok((
             input,
@@ -169,7 +1089,8 @@ impl Statement {
                 column_mapping: column_mapping.into_iter().collect(),
                 file_path,
                 table_name,
-                with_truncate: with_truncate.is_some()
+                with_truncate: with_truncate.is_some(),
+                formats,
             }),
         ))
     }
@@ -196,6 +1117,45 @@ impl Statement {
         ))
     }
 
+    fn parse_delete(input: &str) -> IResult<&str, Statement> {
+        let (input, _) = parse_keyword("delete")(input)?;
+        let (input, _) = parse_keyword("from")(input)?;
+        let (input, table_name) = parse_id(input)?;
+        let (input, where_clause) = parse_where_clause(input)?;
+
+        Ok((
+            input,
+            Statement::Delete(Delete {
+                table_name,
+                where_clause,
+            }),
+        ))
+    }
+
+    fn parse_assignment(input: &str) -> IResult<&str, (String, Expression)> {
+        let (input, column_name) = parse_id(input)?;
+        let (input, _) = parse_keyword("=")(input)?;
+        let (input, expression) = Expression::parse(input)?;
+        Ok((input, (column_name, expression)))
+    }
+
+    fn parse_update(input: &str) -> IResult<&str, Statement> {
+        let (input, _) = parse_keyword("update")(input)?;
+        let (input, table_name) = parse_id(input)?;
+        let (input, _) = parse_keyword("set")(input)?;
+        let (input, assignments) = separated_list1(tag(","), Statement::parse_assignment)(input)?;
+        let (input, where_clause) = parse_where_clause(input)?;
+
+        Ok((
+            input,
+            Statement::Update(Update {
+                table_name,
+                assignments,
+                where_clause,
+            }),
+        ))
+    }
+
     fn parse_show_tables(input: &str) -> IResult<&str, Statement> {
         let (input, _) = parse_keyword("show")(input)?;
         value(Statement::ShowTables {}, parse_keyword("tables"))(input)
@@ -207,7 +1167,9 @@ impl Statement {
             Statement::parse_select,
             Statement::parse_insert,
             Statement::parse_show_tables,
-            Statement::parse_csv_import
+            Statement::parse_csv_import,
+            Statement::parse_delete,
+            Statement::parse_update,
         ))(input)
     }
 }
@@ -216,13 +1178,22 @@ impl Statement {
 pub struct ColumnSpec {
     pub name: String,
     pub column_type: ColumnType,
+    pub not_null: bool,
 }
 
 impl ColumnSpec {
     fn parse(input: &str) -> IResult<&str, ColumnSpec> {
         map(
-            tuple((parse_id, ColumnType::parse)),
-            |(name, column_type)| ColumnSpec { name, column_type },
+            tuple((
+                parse_id,
+                ColumnType::parse,
+                opt(tuple((parse_keyword("not"), parse_keyword("null")))),
+            )),
+            |(name, column_type, not_null)| ColumnSpec {
+                name,
+                column_type,
+                not_null: not_null.is_some(),
+            },
         )(input)
     }
 }
@@ -231,10 +1202,39 @@ impl ColumnSpec {
 pub enum ColumnType {
     Varchar { max_length: u32 },
     Number,
+    SmallInt,
+    Int,
+    Decimal { precision: u8, scale: u8 },
+    Double,
     Boolean,
+    Date,
+    Time,
+    Timestamp,
+    Interval,
+    Text,
+    Blob,
 }
 
 impl ColumnType {
+    /// `decimal(precision, scale)`, where the scale defaults to 0 and can't
+    /// be more than the precision.
+    fn parse_decimal(input: &str) -> IResult<&str, ColumnType> {
+        let (input, _) = alt((parse_keyword("decimal"), parse_keyword("numeric")))(input)?;
+        let (input, _) = parse_keyword("(")(input)?;
+        let (input, (precision, scale)) = verify(
+            tuple((
+                preceded(multispace0, terminated(u8, multispace0)),
+                map(
+                    opt(preceded(parse_keyword(","), terminated(u8, multispace0))),
+                    Option::unwrap_or_default,
+                ),
+            )),
+            |(precision, scale)| (1..=Decimal::MAX_PRECISION).contains(precision) && scale <= precision,
+        )(input)?;
+        let (input, _) = parse_keyword(")")(input)?;
+        Ok((input, ColumnType::Decimal { precision, scale }))
+    }
+
     fn parse_varchar(input: &str) -> IResult<&str, ColumnType> {
         let (input, _) = parse_keyword("varchar")(input)?;
         let (input, _) = parse_keyword("(")(input)?;
@@ -246,32 +1246,108 @@ impl ColumnType {
     fn parse(input: &str) -> IResult<&str, ColumnType> {
         alt((
             ColumnType::parse_varchar,
+            ColumnType::parse_decimal,
             value(ColumnType::Number, parse_keyword("number")),
-            value(ColumnType::Boolean, parse_keyword("boolean")),
-        ))(input)
+            value(ColumnType::SmallInt, parse_keyword("smallint")),
+            value(
+                ColumnType::Int,
+                alt((parse_keyword("int"), parse_keyword("integer"), parse_keyword("bigint"))),
+            ),
+            value(
+                ColumnType::Double,
+                alt((
+                    recognize(tuple((parse_keyword("double"), opt(parse_keyword("precision"))))),
+                    parse_keyword("real"),
+                )),
+            ),
+            value(ColumnType::Boolean, parse_keyword("boolean")),
+            value(ColumnType::Date, parse_keyword("date")),
+            value(ColumnType::Timestamp, parse_keyword("timestamp")),
+            value(ColumnType::Time, parse_keyword("time")),
+            value(ColumnType::Interval, parse_keyword("interval")),
+            value(ColumnType::Text, parse_keyword("text")),
+            value(ColumnType::Blob, parse_keyword("blob")),
+        ))(input)
     }
 }
 
 fn parse_keyword<'a>(expected_keyword: &'a str) -> impl Fn(&'a str) -> IResult<&'a str, &'a str> {
+    // Word keywords must not run into the next word, so that e.g. "and"
+    // doesn't match the start of "android".
+    let is_word = expected_keyword.ends_with(|c: char| c.is_alphanumeric());
     move |input| {
         recognize(preceded(
             multispace0,
-            terminated(tag_no_case(expected_keyword), multispace0),
+            terminated(
+                terminated(
+                    tag_no_case(expected_keyword),
+                    cond(is_word, not(satisfy(|c| c.is_alphanumeric() || c == '_'))),
+                ),
+                multispace0,
+            ),
         ))(input)
     }
 }
 
 
+/// Words that end a table reference or select item, so they can't be used
+/// as an alias.
+const RESERVED_WORDS: &[&str] = &[
+    "from", "where", "group", "having", "order", "limit", "offset", "join", "inner", "left",
+    "outer", "on", "as", "and", "or", "not", "union", "intersect", "except",
+];
+
+fn parse_alias(input: &str) -> IResult<&str, Option<String>> {
+    let unreserved = || verify(parse_id, |id: &String| !RESERVED_WORDS.contains(&id.to_lowercase().as_str()));
+    alt((
+        map(preceded(parse_keyword("as"), unreserved()), Some),
+        map(unreserved(), Some),
+        success(None),
+    ))(input)
+}
+
+/// Parses a query in parentheses.
+fn parse_subquery(input: &str) -> IResult<&str, Query> {
+    delimited(parse_keyword("("), Query::parse, parse_keyword(")"))(input)
+}
+
+/// Parses `column` or `table.column`.
+fn parse_column_reference(input: &str) -> IResult<&str, (Option<String>, String)> {
+    let (input, first) = parse_id(input)?;
+    let (input, second) = opt(preceded(tag("."), parse_id))(input)?;
+    match second {
+        Some(column_name) => Ok((input, (Some(first), column_name))),
+        None => Ok((input, (None, first))),
+    }
+}
+
+fn parse_count(input: &str) -> IResult<&str, usize> {
+    map(
+        preceded(multispace0, terminated(complete::u64, multispace0)),
+        |count| count as usize,
+    )(input)
+}
+
 fn parse_id(input: &str) -> IResult<&str, String> {
     map(
         tuple((
             preceded(multispace0, alpha1),
-            terminated(alphanumeric0, multispace0),
+            terminated(take_while(|c: char| c.is_alphanumeric() || c == '_'), multispace0),
         )),
         |(start, rest)| format!("{}{}", start, rest),
     )(input)
 }
 
+#[cfg(test)]
+impl TableReference {
+    pub fn named(table_name: &str) -> TableReference {
+        TableReference::Table {
+            table_name: table_name.to_string(),
+            alias: None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::vec;
@@ -293,6 +1369,18 @@ mod tests {
         assert_eq!("SELECT", matched);
     }
 
+    #[test]
+    fn test_keyword_boundary() {
+        assert!(parse_keyword("and")("android").is_err());
+        assert!(parse_keyword("and")("and_roid").is_err());
+
+        let (remaining, _) = parse_keyword("and")("and(").unwrap();
+        assert_eq!("(", remaining);
+
+        let (remaining, _) = parse_keyword("<")("<5").unwrap();
+        assert_eq!("5", remaining);
+    }
+
     #[test]
     fn test_id() {
         let (remaining, matched) = parse_id("foobar").unwrap();
@@ -312,6 +1400,12 @@ mod tests {
         let (remaining, matched) = parse_id("foobar  ").unwrap();
         assert_eq!("", remaining);
         assert_eq!("foobar", matched);
+
+        let (remaining, matched) = parse_id("double_rank2 ").unwrap();
+        assert_eq!("", remaining);
+        assert_eq!("double_rank2", matched);
+
+        assert!(parse_id("_foobar").is_err());
     }
 
     #[test]
@@ -326,15 +1420,18 @@ mod tests {
                 column_specs: vec![
                     ColumnSpec {
                         name: "name".to_string(),
-                        column_type: ColumnType::Varchar { max_length: 128 }
+                        column_type: ColumnType::Varchar { max_length: 128 },
+                        not_null: false,
                     },
                     ColumnSpec {
                         name: "age".to_string(),
-                        column_type: ColumnType::Number
+                        column_type: ColumnType::Number,
+                        not_null: false,
                     },
                     ColumnSpec {
                         name: "male".to_string(),
-                        column_type: ColumnType::Boolean
+                        column_type: ColumnType::Boolean,
+                        not_null: false,
                     },
                 ]
             }),
@@ -349,15 +1446,18 @@ mod tests {
                 column_specs: vec![
                     ColumnSpec {
                         name: "name".to_string(),
-                        column_type: ColumnType::Varchar { max_length: 255 }
+                        column_type: ColumnType::Varchar { max_length: 255 },
+                        not_null: false,
                     },
                     ColumnSpec {
                         name: "age".to_string(),
-                        column_type: ColumnType::Number
+                        column_type: ColumnType::Number,
+                        not_null: false,
                     },
                     ColumnSpec {
                         name: "male".to_string(),
-                        column_type: ColumnType::Boolean
+                        column_type: ColumnType::Boolean,
+                        not_null: false,
                     },
                 ]
             }),
@@ -365,34 +1465,879 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_create_table_number_types() {
+        let (remaining, matched) = Statement::parse(
+            "create table t(a smallint, b int, c integer, d bigint, e double, f double precision, g real, h number, \
+             i decimal(10, 2), j numeric(5))",
+        )
+        .unwrap();
+        assert_eq!("", remaining);
+        let Statement::CreateTable(create_table) = matched else {
+            panic!("Expected a create table statement");
+        };
+        let column_types: Vec<ColumnType> = create_table.column_specs.into_iter().map(|cs| cs.column_type).collect();
+        assert_eq!(
+            vec![
+                ColumnType::SmallInt,
+                ColumnType::Int,
+                ColumnType::Int,
+                ColumnType::Int,
+                ColumnType::Double,
+                ColumnType::Double,
+                ColumnType::Double,
+                ColumnType::Number,
+                ColumnType::Decimal { precision: 10, scale: 2 },
+                ColumnType::Decimal { precision: 5, scale: 0 },
+            ],
+            column_types
+        );
+        assert!(Statement::parse("create table t(a decimal(5, 6))").is_err());
+        assert!(Statement::parse("create table t(a decimal(0))").is_err());
+        assert!(Statement::parse("create table t(a decimal(39, 2))").is_err());
+    }
+
+    #[test]
+    fn test_number_literals() {
+        let literal = |input| InsertValue::parse(input).unwrap();
+        assert_eq!(("", InsertValue::Number { value: 42 }), literal("42"));
+        assert_eq!(("", InsertValue::Int { value: -42 }), literal(" -42 "));
+        let decimal = |text| InsertValue::Decimal {
+            value: Decimal::parse(text).unwrap(),
+        };
+        assert_eq!(("", decimal("4.25")), literal("4.25"));
+        assert_eq!(("", decimal("-0.50")), literal("-0.50"));
+        assert_eq!(("", InsertValue::Double { value: 1500.0 }), literal("1.5e3"));
+        assert_eq!(("", InsertValue::Double { value: -0.025 }), literal("-25E-3"));
+        assert_eq!((".", InsertValue::Number { value: 4 }), literal("4."));
+        assert!(InsertValue::parse("-").is_err());
+
+        let (remaining, matched) = Expression::parse("age -1").unwrap();
+        assert_eq!("", remaining);
+        assert!(matches!(
+            matched,
+            Expression::Arithmetic {
+                operator: ArithmeticOperator::Subtract,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_date_and_time_literals() {
+        let (remaining, matched) = Statement::parse("create table t(a date, b time, c timestamp, d interval)").unwrap();
+        assert_eq!("", remaining);
+        let Statement::CreateTable(create_table) = matched else {
+            panic!("Expected a create table statement");
+        };
+        let column_types: Vec<ColumnType> = create_table.column_specs.into_iter().map(|cs| cs.column_type).collect();
+        assert_eq!(
+            vec![ColumnType::Date, ColumnType::Time, ColumnType::Timestamp, ColumnType::Interval],
+            column_types
+        );
+
+        let literal = |input| InsertValue::parse(input).unwrap();
+        assert_eq!(
+            (
+                "",
+                InsertValue::Date {
+                    value: Date::parse("2017-01-31").unwrap()
+                }
+            ),
+            literal("date \"2017-01-31\"")
+        );
+        assert_eq!(
+            (
+                "",
+                InsertValue::Timestamp {
+                    value: Timestamp::parse("2017-01-31T08:15:00").unwrap()
+                }
+            ),
+            literal("TIMESTAMP \"2017-01-31 08:15\"")
+        );
+        assert_eq!(
+            (
+                "",
+                InsertValue::Interval {
+                    value: Interval::new(14, 3, 0)
+                }
+            ),
+            literal("interval \"1 year 2 months 3 days\"")
+        );
+        assert!(InsertValue::parse("date \"2017-02-30\"").is_err());
+        assert!(InsertValue::parse("time \"25:00\"").is_err());
+
+        let (remaining, matched) = Expression::parse("extract(YEAR from Date)").unwrap();
+        assert_eq!("", remaining);
+        assert_eq!(
+            Expression::Function {
+                name: "extract".to_string(),
+                arguments: vec![
+                    Expression::Literal {
+                        value: InsertValue::Varchar {
+                            value: "year".to_string()
+                        }
+                    },
+                    Expression::Column {
+                        table_name: None,
+                        column_name: "Date".to_string()
+                    },
+                ],
+            },
+            matched
+        );
+    }
+
+    #[test]
+    fn test_text_and_blob() {
+        let (remaining, matched) = Statement::parse("create table t(a text, b blob)").unwrap();
+        assert_eq!("", remaining);
+        let Statement::CreateTable(create_table) = matched else {
+            panic!("Expected a create table statement");
+        };
+        let column_types: Vec<ColumnType> = create_table.column_specs.into_iter().map(|cs| cs.column_type).collect();
+        assert_eq!(vec![ColumnType::Text, ColumnType::Blob], column_types);
+
+        let literal = |input| InsertValue::parse(input).unwrap();
+        assert_eq!(
+            (
+                "",
+                InsertValue::Blob {
+                    value: vec![0x0a, 0xff]
+                }
+            ),
+            literal("x\"0aFF\"")
+        );
+        assert_eq!(("", InsertValue::Blob { value: vec![] }), literal(" X\"\" "));
+        assert!(InsertValue::parse("x\"abc\"").is_err());
+        assert!(InsertValue::parse("x\"zz\"").is_err());
+    }
+
+    #[test]
+    fn test_create_table_not_null() {
+        let (remaining, matched) =
+            Statement::parse("create table person(name varchar(10) not null, age number NOT  NULL, male boolean)").unwrap();
+        assert_eq!("", remaining);
+        let Statement::CreateTable(create_table) = matched else {
+            panic!("Expected a create table statement");
+        };
+        let not_null: Vec<bool> = create_table.column_specs.iter().map(|cs| cs.not_null).collect();
+        assert_eq!(vec![true, true, false], not_null);
+
+        assert!(Statement::parse("create table person(name varchar(10) not)").is_err());
+    }
+
     #[test]
     fn test_select() {
         let (remaining, matched) = Statement::parse("select * from person").unwrap();
         assert_eq!("", remaining);
         assert_eq!(
-            Statement::Select(Select {
+            Statement::Select(Query::Select(Box::new(Select {
+                distinct: false,
                 column_refs: vec![SelectColumnReference::Wildcard],
-                table_name: "person".to_string()
-            }),
+                from: Some(TableReference::named("person")),
+                joins: vec![],
+                where_clause: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: 0
+            }))),
             matched
         );
 
         let (remaining, matched) = Statement::parse("select name, age from person").unwrap();
         assert_eq!("", remaining);
         assert_eq!(
-            Statement::Select(Select {
+            Statement::Select(Query::Select(Box::new(Select {
+                distinct: false,
                 column_refs: vec![
-                    SelectColumnReference::Named {
-                        column_name: "name".to_string()
+                    SelectColumnReference::Expression {
+                        expression: Expression::Column {
+                            table_name: None,
+                            column_name: "name".to_string()
+                        },
+                        alias: None
                     },
-                    SelectColumnReference::Named {
-                        column_name: "age".to_string()
+                    SelectColumnReference::Expression {
+                        expression: Expression::Column {
+                            table_name: None,
+                            column_name: "age".to_string()
+                        },
+                        alias: None
                     }
                 ],
-                table_name: "person".to_string()
+                from: Some(TableReference::named("person")),
+                joins: vec![],
+                where_clause: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: 0
+            }))),
+            matched
+        );
+
+        let (remaining, matched) =
+            Statement::parse("select * from person where not (age < 18 or male) and name = \"Laura\"").unwrap();
+        assert_eq!("", remaining);
+        assert_eq!(
+            Statement::Select(Query::Select(Box::new(Select {
+                distinct: false,
+                column_refs: vec![SelectColumnReference::Wildcard],
+                from: Some(TableReference::named("person")),
+                joins: vec![],
+                where_clause: Some(Expression::And {
+                    left: Box::new(Expression::Not {
+                        expression: Box::new(Expression::Or {
+                            left: Box::new(Expression::Comparison {
+                                left: Box::new(Expression::Column {
+                                    table_name: None,
+                                    column_name: "age".to_string()
+                                }),
+                                operator: ComparisonOperator::LessThan,
+                                right: Box::new(Expression::Literal {
+                                    value: InsertValue::Number { value: 18 }
+                                })
+                            }),
+                            right: Box::new(Expression::Column {
+                                table_name: None,
+                                column_name: "male".to_string()
+                            })
+                        })
+                    }),
+                    right: Box::new(Expression::Comparison {
+                        left: Box::new(Expression::Column {
+                            table_name: None,
+                            column_name: "name".to_string()
+                        }),
+                        operator: ComparisonOperator::Equal,
+                        right: Box::new(Expression::Literal {
+                            value: InsertValue::Varchar {
+                                value: "Laura".to_string()
+                            }
+                        })
+                    })
+                }),
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: 0
+            }))),
+            matched
+        );
+
+        let (remaining, matched) = Statement::parse("select * from person order by age desc, name").unwrap();
+        assert_eq!("", remaining);
+        assert_eq!(
+            Statement::Select(Query::Select(Box::new(Select {
+                distinct: false,
+                column_refs: vec![SelectColumnReference::Wildcard],
+                from: Some(TableReference::named("person")),
+                joins: vec![],
+                where_clause: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![
+                    OrderBy {
+                        expression: Expression::Column {
+                            table_name: None,
+                            column_name: "age".to_string()
+                        },
+                        ascending: false
+                    },
+                    OrderBy {
+                        expression: Expression::Column {
+                            table_name: None,
+                            column_name: "name".to_string()
+                        },
+                        ascending: true
+                    },
+                ],
+                limit: None,
+                offset: 0
+            }))),
+            matched
+        );
+
+        let (remaining, matched) = Statement::parse("select * from person limit 10 offset 20").unwrap();
+        assert_eq!("", remaining);
+        assert_eq!(
+            Statement::Select(Query::Select(Box::new(Select {
+                distinct: false,
+                column_refs: vec![SelectColumnReference::Wildcard],
+                from: Some(TableReference::named("person")),
+                joins: vec![],
+                where_clause: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: Some(10),
+                offset: 20
+            }))),
+            matched
+        );
+    }
+
+    #[test]
+    fn test_select_group_by() {
+        let column = |column_name: &str| Expression::Column {
+            table_name: None,
+            column_name: column_name.to_string(),
+        };
+
+        let (remaining, matched) = Statement::parse(
+            "select male, count(*), avg(age + 1) from person group by male having count(age) > 1 order by max(age)",
+        )
+        .unwrap();
+        assert_eq!("", remaining);
+        assert_eq!(
+            Statement::Select(Query::Select(Box::new(Select {
+                distinct: false,
+                column_refs: vec![
+                    SelectColumnReference::Expression {
+                        expression: column("male"),
+                        alias: None
+                    },
+                    SelectColumnReference::Expression {
+                        expression: Expression::Aggregate {
+                            function: AggregateFunction::Count,
+                            argument: None
+                        },
+                        alias: None
+                    },
+                    SelectColumnReference::Expression {
+                        expression: Expression::Aggregate {
+                            function: AggregateFunction::Avg,
+                            argument: Some(Box::new(Expression::Arithmetic {
+                                left: Box::new(column("age")),
+                                operator: ArithmeticOperator::Add,
+                                right: Box::new(Expression::Literal {
+                                    value: InsertValue::Number { value: 1 }
+                                })
+                            }))
+                        },
+                        alias: None
+                    },
+                ],
+                from: Some(TableReference::named("person")),
+                joins: vec![],
+                where_clause: None,
+                group_by: vec![column("male")],
+                having: Some(Expression::Comparison {
+                    left: Box::new(Expression::Aggregate {
+                        function: AggregateFunction::Count,
+                        argument: Some(Box::new(column("age")))
+                    }),
+                    operator: ComparisonOperator::GreaterThan,
+                    right: Box::new(Expression::Literal {
+                        value: InsertValue::Number { value: 1 }
+                    })
+                }),
+                order_by: vec![OrderBy {
+                    expression: Expression::Aggregate {
+                        function: AggregateFunction::Max,
+                        argument: Some(Box::new(column("age")))
+                    },
+                    ascending: true
+                }],
+                limit: None,
+                offset: 0
+            }))),
+            matched
+        );
+
+        let (remaining, _) = Statement::parse("select sum(*) from person").unwrap();
+        assert_eq!("(*) from person", remaining);
+    }
+
+    #[test]
+    fn test_select_expressions() {
+        let column = |column_name: &str| Expression::Column {
+            table_name: None,
+            column_name: column_name.to_string(),
+        };
+
+        let (remaining, matched) =
+            Statement::parse("select Rank * 2 as double_rank, upper(Title) t, concat(), Artist from music").unwrap();
+        assert_eq!("", remaining);
+        let column_refs = match matched {
+            Statement::Select(Query::Select(select)) => select.column_refs,
+            other => panic!("Not a select: {:?}", other),
+        };
+        assert_eq!(
+            vec![
+                SelectColumnReference::Expression {
+                    expression: Expression::Arithmetic {
+                        left: Box::new(column("Rank")),
+                        operator: ArithmeticOperator::Multiply,
+                        right: Box::new(Expression::Literal {
+                            value: InsertValue::Number { value: 2 }
+                        })
+                    },
+                    alias: Some("double_rank".to_string())
+                },
+                SelectColumnReference::Expression {
+                    expression: Expression::Function {
+                        name: "upper".to_string(),
+                        arguments: vec![column("Title")]
+                    },
+                    alias: Some("t".to_string())
+                },
+                SelectColumnReference::Expression {
+                    expression: Expression::Function {
+                        name: "concat".to_string(),
+                        arguments: vec![]
+                    },
+                    alias: None
+                },
+                SelectColumnReference::Expression {
+                    expression: column("Artist"),
+                    alias: None
+                },
+            ],
+            column_refs
+        );
+
+        let (remaining, _) = Statement::parse("select Rank as from music").unwrap();
+        assert_eq!("as from music", remaining);
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let select = |input: &str| match Query::parse(input) {
+            Ok(("", Query::Select(select))) => select,
+            other => panic!("Not a select: {:?}", other),
+        };
+
+        let (remaining, matched) = Statement::parse(
+            "select distinct a from x union select b from y intersect all select c from z order by a limit 1",
+        )
+        .unwrap();
+        assert_eq!("", remaining);
+        assert_eq!(
+            Statement::Select(Query::SetOperation {
+                operator: SetOperator::Union,
+                all: false,
+                left: Box::new(Query::Select(select("select distinct a from x"))),
+                right: Box::new(Query::SetOperation {
+                    operator: SetOperator::Intersect,
+                    all: true,
+                    left: Box::new(Query::Select(select("select b from y"))),
+                    right: Box::new(Query::Select(select("select c from z"))),
+                    order_by: vec![],
+                    limit: None,
+                    offset: 0
+                }),
+                order_by: vec![OrderBy {
+                    expression: Expression::Column {
+                        table_name: None,
+                        column_name: "a".to_string()
+                    },
+                    ascending: true
+                }],
+                limit: Some(1),
+                offset: 0
             }),
             matched
         );
+        assert!(select("select distinct a from x").distinct);
+
+        let (remaining, matched) = Query::parse("select a from x except select a from y union all select a from z").unwrap();
+        assert_eq!("", remaining);
+        match matched {
+            Query::SetOperation {
+                operator: SetOperator::Union,
+                all: true,
+                left,
+                ..
+            } => assert!(matches!(*left, Query::SetOperation { operator: SetOperator::Except, all: false, .. })),
+            other => panic!("Not a union: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_subqueries() {
+        let query = |input: &str| match Query::parse(input) {
+            Ok(("", query)) => Box::new(query),
+            other => panic!("Not a query: {:?}", other),
+        };
+        let column = |table_name: &str, column_name: &str| Expression::Column {
+            table_name: Some(table_name.to_string()),
+            column_name: column_name.to_string(),
+        };
+
+        let (remaining, matched) = Statement::parse(
+            "select * from (select name from person) as s where s.name not in (select owner from pet) and exists (select * from pet where owner = s.name)",
+        )
+        .unwrap();
+        assert_eq!("", remaining);
+        assert_eq!(
+            Statement::Select(Query::Select(Box::new(Select {
+                distinct: false,
+                column_refs: vec![SelectColumnReference::Wildcard],
+                from: Some(TableReference::Subquery {
+                    query: query("select name from person"),
+                    alias: "s".to_string()
+                }),
+                joins: vec![],
+                where_clause: Some(Expression::And {
+                    left: Box::new(Expression::InSubquery {
+                        expression: Box::new(column("s", "name")),
+                        query: query("select owner from pet"),
+                        negated: true
+                    }),
+                    right: Box::new(Expression::Exists {
+                        query: query("select * from pet where owner = s.name")
+                    })
+                }),
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: 0
+            }))),
+            matched
+        );
+
+        let (remaining, matched) = Statement::parse("select * from person p join (select owner from pet) o on p.name = o.owner").unwrap();
+        assert_eq!("", remaining);
+        match matched {
+            Statement::Select(Query::Select(select)) => assert_eq!(
+                TableReference::Subquery {
+                    query: query("select owner from pet"),
+                    alias: "o".to_string()
+                },
+                select.joins[0].table
+            ),
+            other => panic!("Not a select: {:?}", other),
+        }
+
+        let (remaining, _) = Statement::parse("select * from (select name from person)").unwrap();
+        assert_eq!("from (select name from person)", remaining);
+        let (remaining, _) = Statement::parse("select * from (select name from person) where name = 1").unwrap();
+        assert_eq!("from (select name from person) where name = 1", remaining);
+    }
+
+    #[test]
+    fn test_with() {
+        let query = |input: &str| match Query::parse(input) {
+            Ok(("", query)) => query,
+            other => panic!("Not a query: {:?}", other),
+        };
+
+        assert_eq!(
+            Query::With {
+                recursive: true,
+                tables: vec![
+                    CommonTableExpression {
+                        name: "t".to_string(),
+                        column_names: vec!["n".to_string()],
+                        query: query("select 1 union all select n + 1 from t where n < 5"),
+                    },
+                    CommonTableExpression {
+                        name: "u".to_string(),
+                        column_names: vec![],
+                        query: query("select n from t"),
+                    }
+                ],
+                query: Box::new(query("select * from u order by n")),
+            },
+            query("with recursive t(n) as (select 1 union all select n + 1 from t where n < 5), u as (select n from t) select * from u order by n")
+        );
+
+        match query("select 1 + 2") {
+            Query::Select(select) => assert_eq!(None, select.from),
+            other => panic!("Not a select: {:?}", other),
+        }
+
+        assert!(Query::parse("with t as select 1 select * from t").is_err());
+        assert!(Query::parse("with t as (select 1)").is_err());
+    }
+
+    #[test]
+    fn test_case_and_concatenation() {
+        let expression = |input: &str| match Expression::parse(input) {
+            Ok(("", expression)) => expression,
+            other => panic!("Not an expression: {:?}", other),
+        };
+
+        assert_eq!(
+            Expression::Case {
+                operand: None,
+                branches: vec![
+                    (expression("Rank = 1"), expression("\"top\"")),
+                    (expression("Rank <= 10"), expression("\"high\"")),
+                ],
+                otherwise: Some(Box::new(expression("\"low\""))),
+            },
+            expression("case when Rank = 1 then \"top\" when Rank <= 10 then \"high\" else \"low\" end")
+        );
+        assert_eq!(
+            Expression::Case {
+                operand: Some(Box::new(expression("Rank"))),
+                branches: vec![(expression("1"), expression("Title"))],
+                otherwise: None,
+            },
+            expression("CASE Rank WHEN 1 THEN Title END")
+        );
+
+        // `||` binds looser than `+` and tighter than comparisons.
+        assert_eq!(
+            Expression::Comparison {
+                left: Box::new(Expression::Arithmetic {
+                    left: Box::new(expression("Artist")),
+                    operator: ArithmeticOperator::Concat,
+                    right: Box::new(expression("Rank + 1")),
+                }),
+                operator: ComparisonOperator::Equal,
+                right: Box::new(expression("Title")),
+            },
+            expression("Artist || Rank + 1 = Title")
+        );
+
+        // What isn't a whole case expression is only a column named case.
+        assert!(!matches!(Expression::parse("case end"), Ok(("", _))));
+        assert!(!matches!(Expression::parse("case when Rank = 1 then 1"), Ok(("", _))));
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        let expression = |input: &str| match Expression::parse(input) {
+            Ok(("", expression)) => expression,
+            other => panic!("Not an expression: {:?}", other),
+        };
+        let pattern_match = |kind, pattern: &str, negated| Expression::PatternMatch {
+            expression: Box::new(expression("Title")),
+            kind,
+            pattern: Box::new(expression(pattern)),
+            negated,
+        };
+
+        assert_eq!(pattern_match(PatternKind::Like, "\"Love%\"", false), expression("Title like \"Love%\""));
+        assert_eq!(pattern_match(PatternKind::ILike, "\"%love%\"", true), expression("Title NOT ILIKE \"%love%\""));
+        assert_eq!(pattern_match(PatternKind::Regex, "\"^[0-9]+$\"", false), expression("Title regexp \"^[0-9]+$\""));
+        assert_eq!(pattern_match(PatternKind::Regex, "\"^[0-9]+$\"", false), expression("Title ~ \"^[0-9]+$\""));
+        assert_eq!(pattern_match(PatternKind::Regex, "Artist || \"!\"", true), expression("Title !~ Artist || \"!\""));
+
+        assert_eq!(
+            Expression::And {
+                left: Box::new(pattern_match(PatternKind::Like, "\"a%\"", false)),
+                right: Box::new(expression("Rank < 10")),
+            },
+            expression("Title like \"a%\" and Rank < 10")
+        );
+    }
+
+    #[test]
+    fn test_predicates() {
+        let expression = |input: &str| match Expression::parse(input) {
+            Ok(("", expression)) => expression,
+            other => panic!("Not an expression: {:?}", other),
+        };
+        let rank = || Box::new(expression("Rank"));
+
+        assert_eq!(
+            Expression::InList {
+                expression: Box::new(expression("Artist")),
+                list: vec![expression("\"a\""), expression("\"b\"")],
+                negated: false,
+            },
+            expression("Artist in (\"a\", \"b\")")
+        );
+        assert_eq!(
+            Expression::InList {
+                expression: rank(),
+                list: vec![expression("1 + 1")],
+                negated: true,
+            },
+            expression("Rank not in (1 + 1)")
+        );
+        assert_eq!(
+            Expression::And {
+                left: Box::new(Expression::Between {
+                    expression: rank(),
+                    low: Box::new(expression("1")),
+                    high: Box::new(expression("5 * 2")),
+                    negated: false,
+                }),
+                right: Box::new(expression("Rank <> 3")),
+            },
+            expression("Rank between 1 and 5 * 2 and Rank <> 3")
+        );
+        assert_eq!(
+            Expression::Between {
+                expression: rank(),
+                low: Box::new(expression("1")),
+                high: Box::new(expression("10")),
+                negated: true,
+            },
+            expression("Rank NOT BETWEEN 1 AND 10")
+        );
+        assert_eq!(
+            Expression::IsNull {
+                expression: rank(),
+                negated: false,
+            },
+            expression("Rank is null")
+        );
+        assert_eq!(
+            Expression::Not {
+                expression: Box::new(Expression::IsNull {
+                    expression: rank(),
+                    negated: true,
+                }),
+            },
+            expression("not Rank is not null")
+        );
+
+        assert!(!matches!(Expression::parse("Rank in ()"), Ok(("", _))));
+        assert!(!matches!(Expression::parse("Rank between 1"), Ok(("", _))));
+    }
+
+    #[test]
+    fn test_select_window() {
+        let column = |column_name: &str| Expression::Column {
+            table_name: None,
+            column_name: column_name.to_string(),
+        };
+        let expression = |input: &str| match Expression::parse(input) {
+            Ok(("", expression)) => expression,
+            other => panic!("Not an expression: {:?}", other),
+        };
+
+        assert_eq!(
+            Expression::Window {
+                function: WindowFunction::RowNumber,
+                arguments: vec![],
+                partition_by: vec![column("Date")],
+                order_by: vec![OrderBy {
+                    expression: column("Rank"),
+                    ascending: true
+                }],
+            },
+            expression("row_number() over (partition by Date order by Rank)")
+        );
+        assert_eq!(
+            Expression::Window {
+                function: WindowFunction::Lag,
+                arguments: vec![column("Rank"), expression("2")],
+                partition_by: vec![],
+                order_by: vec![OrderBy {
+                    expression: column("Date"),
+                    ascending: false
+                }],
+            },
+            expression("lag(Rank, 2) over (order by Date desc)")
+        );
+        assert_eq!(
+            Expression::Window {
+                function: WindowFunction::Aggregate(AggregateFunction::Count),
+                arguments: vec![],
+                partition_by: vec![column("Artist"), column("Date")],
+                order_by: vec![],
+            },
+            expression("count(*) over (partition by Artist, Date)")
+        );
+        assert_eq!(
+            Expression::Arithmetic {
+                left: Box::new(expression("sum(Rank) over (order by Date)")),
+                operator: ArithmeticOperator::Divide,
+                right: Box::new(expression("2")),
+            },
+            expression("sum(Rank) over (order by Date) / 2")
+        );
+
+        assert_eq!(
+            Expression::Function {
+                name: "rank".to_string(),
+                arguments: vec![]
+            },
+            expression("rank()")
+        );
+        let (remaining, _) = Expression::parse("rank() over order by Rank").unwrap();
+        assert_eq!("over order by Rank", remaining);
+    }
+
+    #[test]
+    fn test_select_join() {
+        let column = |table_name: &str, column_name: &str| Expression::Column {
+            table_name: Some(table_name.to_string()),
+            column_name: column_name.to_string(),
+        };
+        let on = |left: Expression, right: Expression| Expression::Comparison {
+            left: Box::new(left),
+            operator: ComparisonOperator::Equal,
+            right: Box::new(right),
+        };
+
+        let (remaining, matched) = Statement::parse(
+            "select p.name, city from person as p join city c on p.city = c.id left outer join country on c.country = country.id",
+        )
+        .unwrap();
+        assert_eq!("", remaining);
+        assert_eq!(
+            Statement::Select(Query::Select(Box::new(Select {
+                distinct: false,
+                column_refs: vec![
+                    SelectColumnReference::Expression {
+                        expression: column("p", "name"),
+                        alias: None
+                    },
+                    SelectColumnReference::Expression {
+                        expression: Expression::Column {
+                            table_name: None,
+                            column_name: "city".to_string()
+                        },
+                        alias: None
+                    },
+                ],
+                from: Some(TableReference::Table {
+                    table_name: "person".to_string(),
+                    alias: Some("p".to_string())
+                }),
+                joins: vec![
+                    Join {
+                        kind: JoinKind::Inner,
+                        table: TableReference::Table {
+                            table_name: "city".to_string(),
+                            alias: Some("c".to_string())
+                        },
+                        on: on(column("p", "city"), column("c", "id"))
+                    },
+                    Join {
+                        kind: JoinKind::Left,
+                        table: TableReference::named("country"),
+                        on: on(column("c", "country"), column("country", "id"))
+                    },
+                ],
+                where_clause: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: 0
+            }))),
+            matched
+        );
+
+        let (remaining, matched) = Statement::parse("select * from person inner join city on city = id where age > 1").unwrap();
+        assert_eq!("", remaining);
+        match matched {
+            Statement::Select(Query::Select(select)) => {
+                assert_eq!(Some(TableReference::named("person")), select.from);
+                assert_eq!(JoinKind::Inner, select.joins[0].kind);
+                assert_eq!(TableReference::named("city"), select.joins[0].table);
+                assert!(select.where_clause.is_some());
+            }
+            other => panic!("Not a select: {:?}", other),
+        }
+
+        let (remaining, _) = Statement::parse("select * from person join city").unwrap();
+        assert_eq!("join city", remaining);
     }
 
     #[test]
@@ -433,6 +2378,13 @@ mod tests {
             }),
             matched
         );
+
+        let (remaining, matched) = Statement::parse("insert into person(name, age) values (\"Martin\", null)").unwrap();
+        assert_eq!("", remaining);
+        assert!(matches!(
+            matched,
+            Statement::Insert(Insert { column_values, .. }) if column_values[1] == InsertValue::Null
+        ));
     }
 
     #[test]
@@ -452,7 +2404,8 @@ mod tests {
                     ("region".to_string(), "Region".to_string())
                 ]),
                 file_path: "/home/martinc/spotify.csv".to_string(),
-                with_truncate: false
+                with_truncate: false,
+                formats: Formats::default(),
             }),
             matched
         );
@@ -476,7 +2429,141 @@ mod tests {
                     ("region".to_string(), "Region".to_string())
                 ]),
                 file_path: "/home/martinc/spotify.csv".to_string(),
-                with_truncate: true
+                with_truncate: true,
+                formats: Formats::default(),
+            }),
+            matched
+        );
+    }
+
+    #[test]
+    fn test_csv_import_formats() {
+        let (remaining, matched) = Statement::parse(
+            "import csv from \"charts.csv\" into charts with (date=Date) date format \"%d/%m/%Y\" \
+             timestamp format \"%d/%m/%Y %H:%M\"",
+        )
+        .unwrap();
+        assert_eq!("", remaining);
+        let Statement::CsvImport(import) = matched else {
+            panic!("Expected an import statement");
+        };
+        assert_eq!(
+            Formats {
+                date: Some("%d/%m/%Y".to_string()),
+                time: None,
+                timestamp: Some("%d/%m/%Y %H:%M".to_string()),
+            },
+            import.formats
+        );
+    }
+
+    #[test]
+    fn test_delete() {
+        let (remaining, matched) = Statement::parse("delete from person").unwrap();
+        assert_eq!("", remaining);
+        assert_eq!(
+            Statement::Delete(Delete {
+                table_name: "person".to_string(),
+                where_clause: None
+            }),
+            matched
+        );
+
+        let (remaining, matched) =
+            Statement::parse("DELETE FROM person WHERE name = \"Martin\" and age>=35 AND 21 <> age")
+                .unwrap();
+        assert_eq!("", remaining);
+        assert_eq!(
+            Statement::Delete(Delete {
+                table_name: "person".to_string(),
+                where_clause: Some(Expression::And {
+                    left: Box::new(Expression::And {
+                        left: Box::new(Expression::Comparison {
+                            left: Box::new(Expression::Column {
+                                table_name: None,
+                                column_name: "name".to_string()
+                            }),
+                            operator: ComparisonOperator::Equal,
+                            right: Box::new(Expression::Literal {
+                                value: InsertValue::Varchar {
+                                    value: "Martin".to_string()
+                                }
+                            })
+                        }),
+                        right: Box::new(Expression::Comparison {
+                            left: Box::new(Expression::Column {
+                                table_name: None,
+                                column_name: "age".to_string()
+                            }),
+                            operator: ComparisonOperator::GreaterThanOrEqual,
+                            right: Box::new(Expression::Literal {
+                                value: InsertValue::Number { value: 35 }
+                            })
+                        })
+                    }),
+                    right: Box::new(Expression::Comparison {
+                        left: Box::new(Expression::Literal {
+                            value: InsertValue::Number { value: 21 }
+                        }),
+                        operator: ComparisonOperator::NotEqual,
+                        right: Box::new(Expression::Column {
+                            table_name: None,
+                            column_name: "age".to_string()
+                        })
+                    })
+                })
+            }),
+            matched
+        );
+    }
+
+    #[test]
+    fn test_update() {
+        let column = |column_name: &str| {
+            Box::new(Expression::Column {
+                table_name: None,
+                column_name: column_name.to_string(),
+            })
+        };
+        let number = |value| Box::new(Expression::Literal {
+            value: InsertValue::Number { value },
+        });
+
+        let (remaining, matched) =
+            Statement::parse("update person set age = age + 2 * 3 - 1, male = false where age < 18").unwrap();
+        assert_eq!("", remaining);
+        assert_eq!(
+            Statement::Update(Update {
+                table_name: "person".to_string(),
+                assignments: vec![
+                    (
+                        "age".to_string(),
+                        Expression::Arithmetic {
+                            left: Box::new(Expression::Arithmetic {
+                                left: column("age"),
+                                operator: ArithmeticOperator::Add,
+                                right: Box::new(Expression::Arithmetic {
+                                    left: number(2),
+                                    operator: ArithmeticOperator::Multiply,
+                                    right: number(3),
+                                }),
+                            }),
+                            operator: ArithmeticOperator::Subtract,
+                            right: number(1),
+                        }
+                    ),
+                    (
+                        "male".to_string(),
+                        Expression::Literal {
+                            value: InsertValue::Boolean { value: false }
+                        }
+                    ),
+                ],
+                where_clause: Some(Expression::Comparison {
+                    left: column("age"),
+                    operator: ComparisonOperator::LessThan,
+                    right: number(18),
+                }),
             }),
             matched
         );
@@ -0,0 +1,217 @@
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Ordering,
+    collections::HashSet,
+    fmt,
+    rc::Rc,
+};
+
+use crate::{
+    database::Database,
+    expression::{self, Scope},
+    query::{self, Context},
+    sql_parser::Query,
+    table::Value,
+};
+
+/// A row of an enclosing query, whose columns a correlated subquery can
+/// refer to.
+#[derive(Clone)]
+pub struct Frame {
+    pub scope: Scope,
+    pub row: Vec<Value>,
+    referenced: Rc<Cell<bool>>,
+}
+
+impl Frame {
+    fn new(scope: &Scope, row: &[Value]) -> Frame {
+        Frame {
+            scope: scope.clone(),
+            row: row.to_vec(),
+            referenced: Rc::new(Cell::new(false)),
+        }
+    }
+
+    /// The value of one of the frame's columns, noting that the subquery
+    /// being planned depends on this row.
+    pub fn value(&self, index: usize) -> Value {
+        self.referenced.set(true);
+        self.row[index].clone()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Kind {
+    Exists,
+    In,
+}
+
+/// What a subquery's result says, for one row of the enclosing query. An
+/// `in` subquery's values are looked up by key, but text equals the date or
+/// time it reads as, which keys don't show, so its text values and its
+/// dates and times are also kept to compare against.
+enum Answer {
+    Exists(bool),
+    In {
+        values: HashSet<Value>,
+        texts: Vec<Value>,
+        times: Vec<Value>,
+        has_null: bool,
+    },
+}
+
+struct Plan<'a> {
+    database: &'a Database,
+    query: Query,
+    kind: Kind,
+    scope: Scope,
+    context: Context,
+    cached: RefCell<Option<Rc<Answer>>>,
+}
+
+/// A subquery within an expression. It's planned and run again for every
+/// row of the enclosing query, with the row's values standing in for the
+/// columns it refers to, unless its first run shows it doesn't refer to
+/// any. Then that run's answer is kept for every other row.
+#[derive(Clone)]
+pub struct Subquery<'a> {
+    plan: Rc<Plan<'a>>,
+}
+
+impl<'a> Subquery<'a> {
+    /// A subquery run for rows of `scope`, within a query run in `context`.
+    pub fn exists(database: &'a Database, query: &Query, scope: &Scope, context: &Context) -> Subquery<'a> {
+        Subquery::new(database, query, Kind::Exists, scope, context)
+    }
+
+    pub fn in_values(database: &'a Database, query: &Query, scope: &Scope, context: &Context) -> Subquery<'a> {
+        Subquery::new(database, query, Kind::In, scope, context)
+    }
+
+    fn new(database: &'a Database, query: &Query, kind: Kind, scope: &Scope, context: &Context) -> Subquery<'a> {
+        Subquery {
+            plan: Rc::new(Plan {
+                database,
+                query: query.clone(),
+                kind,
+                scope: scope.clone(),
+                context: context.clone(),
+                cached: RefCell::new(None),
+            }),
+        }
+    }
+
+    fn run(&self, row: &[Value]) -> Result<Rc<Answer>, String> {
+        let plan = &self.plan;
+        if let Some(answer) = plan.cached.borrow().as_ref() {
+            return Ok(answer.clone());
+        }
+
+        let frame = Frame::new(&plan.scope, row);
+        let referenced = frame.referenced.clone();
+        let result = query::execute_in(plan.database, &plan.query, &plan.context.with_frame(frame))?;
+        let answer = Rc::new(match plan.kind {
+            Kind::Exists => Answer::Exists(!result.rows.is_empty()),
+            Kind::In => {
+                if result.column_names.len() != 1 {
+                    return Err(format!(
+                        "A subquery used with in must select one column, but this one selects {}",
+                        result.column_names.len()
+                    ));
+                }
+                let values: HashSet<Value> = result.rows.into_iter().flatten().collect();
+                let (texts, times) = values
+                    .iter()
+                    .filter(|value| matches!(value, Value::Varchar { .. }) || is_time(value))
+                    .cloned()
+                    .partition(|value| matches!(value, Value::Varchar { .. }));
+                let has_null = values.contains(&Value::Null);
+                Answer::In {
+                    values: values.into_iter().map(Value::key).collect(),
+                    texts,
+                    times,
+                    has_null,
+                }
+            }
+        });
+
+        if !referenced.get() {
+            plan.cached.replace(Some(answer.clone()));
+        }
+        Ok(answer)
+    }
+
+    /// Whether the subquery returns any rows.
+    pub fn any_rows(&self, row: &[Value]) -> Result<Value, String> {
+        match *self.run(row)? {
+            Answer::Exists(value) => Ok(Value::Boolean { value }),
+            Answer::In { .. } => unreachable!("planned as an in subquery"),
+        }
+    }
+
+    /// Whether `value` is among the subquery's values. Like a chain of `=`
+    /// comparisons joined by `or`, the answer is null rather than false
+    /// when `value` is null or the subquery returns a null.
+    pub fn contains(&self, value: &Value, row: &[Value]) -> Result<Value, String> {
+        match &*self.run(row)? {
+            Answer::In {
+                values,
+                texts,
+                times,
+                has_null,
+            } => {
+                let others: &[Value] = match value {
+                    Value::Varchar { .. } => times,
+                    value if is_time(value) => texts,
+                    _ => &[],
+                };
+                if *value == Value::Null {
+                    Ok(Value::Null)
+                } else if values.contains(&value.clone().key()) || reads_as_any(value, others)? {
+                    Ok(Value::Boolean { value: true })
+                } else if *has_null {
+                    Ok(Value::Null)
+                } else {
+                    Ok(Value::Boolean { value: false })
+                }
+            }
+            Answer::Exists(_) => unreachable!("planned as an exists subquery"),
+        }
+    }
+}
+
+fn is_time(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Date { .. } | Value::Time { .. } | Value::Timestamp { .. } | Value::Interval { .. }
+    )
+}
+
+/// Whether `value` equals any of `others` once text is read as a date or
+/// time.
+fn reads_as_any(value: &Value, others: &[Value]) -> Result<bool, String> {
+    for other in others {
+        if expression::compare(value, other)? == Ordering::Equal {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Two subqueries are only equal if they're the same plan.
+impl PartialEq for Subquery<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.plan, &other.plan)
+    }
+}
+
+impl Eq for Subquery<'_> {}
+
+impl fmt::Debug for Subquery<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subquery")
+            .field("query", &self.plan.query)
+            .field("kind", &self.plan.kind)
+            .finish()
+    }
+}
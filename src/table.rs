@@ -1,77 +1,430 @@
 use std::{
+    cmp::Ordering,
     collections::{HashMap, HashSet},
-    fs::File,
-    io::{self, BufRead, BufReader},
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::Path,
 };
 
 use nom::InputTake;
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+use crate::{
+    buffer_pool::{FileId, SharedBufferPool},
+    datetime::{Date, Formats, Interval, Time, Timestamp},
+    decimal::Decimal,
+    overflow::Overflow,
+    pager::{self, Pager},
+    slotted_page,
+};
+
+/// `Null` is a missing value. Queries produce it too, such as for an
+/// aggregate over no rows, and any column not declared `not null` can hold
+/// it.
+///
+/// There are four kinds of number: `Number` is unsigned, `Int` is signed,
+/// `Decimal` is exact with a fixed number of decimal places and `Double` is
+/// floating point. Smallint columns hold `Int`s too.
+///
+/// Dates, times of day, timestamps and intervals hold their own types from
+/// `datetime`.
+///
+/// Text columns hold `Varchar`s of any length, and blob columns hold bytes.
+#[derive(Clone, Debug)]
 pub enum Value {
     Varchar { value: String },
+    Blob { value: Vec<u8> },
     Number { value: u64 },
+    Int { value: i64 },
+    Decimal { value: Decimal },
+    Double { value: f64 },
     Boolean { value: bool },
+    Date { value: Date },
+    Time { value: Time },
+    Timestamp { value: Timestamp },
+    Interval { value: Interval },
+    Null,
+}
+
+impl Value {
+    fn type_order(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Boolean { value: _ } => 1,
+            Value::Number { value: _ } => 2,
+            Value::Int { value: _ } => 3,
+            Value::Decimal { value: _ } => 4,
+            Value::Double { value: _ } => 5,
+            Value::Varchar { value: _ } => 6,
+            Value::Date { value: _ } => 7,
+            Value::Timestamp { value: _ } => 8,
+            Value::Time { value: _ } => 9,
+            Value::Interval { value: _ } => 10,
+            Value::Blob { value: _ } => 11,
+        }
+    }
+
+    /// A whole number's value, whichever type holds it.
+    pub fn whole(&self) -> Option<i128> {
+        match self {
+            Value::Number { value } => Some(*value as i128),
+            Value::Int { value } => Some(*value as i128),
+            _ => None,
+        }
+    }
+
+    /// A whole number's or a decimal's value as a decimal.
+    pub fn decimal(&self) -> Option<Decimal> {
+        match self {
+            Value::Decimal { value } => Some(*value),
+            other => other.whole().map(Decimal::from_whole),
+        }
+    }
+
+    /// A number's value as a double, which may lose precision.
+    pub fn double(&self) -> Option<f64> {
+        match self {
+            Value::Double { value } => Some(*value),
+            other => other.decimal().map(|value| value.to_f64()),
+        }
+    }
+
+    /// A whole number as a `Number` or an `Int`, or `None` if it doesn't
+    /// fit.
+    pub fn from_whole(value: i128, unsigned: bool) -> Option<Value> {
+        match unsigned {
+            true => u64::try_from(value).ok().map(|value| Value::Number { value }),
+            false => i64::try_from(value).ok().map(|value| Value::Int { value }),
+        }
+    }
+
+    pub fn is_number(&self) -> bool {
+        self.double().is_some()
+    }
+
+    /// The value to hash on in place of this one, so values that are equal
+    /// under `=` have the same key. Numbers of different types are equal if
+    /// their values are, so a key holds every whole number as the same
+    /// type, and every other number that a decimal can hold as a decimal. A
+    /// date equals the timestamp of its midnight, so it's held as that.
+    pub fn key(self) -> Value {
+        let decimal = match self {
+            Value::Date { value } => {
+                return Value::Timestamp {
+                    value: value.at_midnight(),
+                }
+            }
+            Value::Double { value } if value.is_finite() => Decimal::parse(&value.to_string()),
+            ref other => other.decimal(),
+        };
+        let Some(decimal) = decimal else {
+            return self;
+        };
+        let whole = decimal.rescale(0).filter(|whole| *whole == decimal);
+        match whole.and_then(|whole| Value::from_whole(whole.units(), whole.units() >= 0)) {
+            Some(whole) => whole,
+            None => Value::Decimal { value: decimal },
+        }
+    }
+
+    /// Orders numbers of any type by their value. NaN comes after every
+    /// other number.
+    pub fn compare_numbers(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Double { value: l }, Value::Double { value: r }) => {
+                Some(l.partial_cmp(r).unwrap_or_else(|| l.is_nan().cmp(&r.is_nan())))
+            }
+            (Value::Double { value: l }, r) => r.decimal().map(|r| r.compare_to_double(*l).reverse()),
+            (l, Value::Double { value: r }) => l.decimal().map(|l| l.compare_to_double(*r)),
+            (l, r) => Some(l.decimal()?.cmp(&r.decimal()?)),
+        }
+    }
+
+    /// Orders dates, times and intervals by when or how long they are. A
+    /// date compares with a timestamp as its midnight.
+    pub fn compare_times(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Date { value: l }, Value::Date { value: r }) => Some(l.cmp(r)),
+            (Value::Timestamp { value: l }, Value::Timestamp { value: r }) => Some(l.cmp(r)),
+            (Value::Date { value: l }, Value::Timestamp { value: r }) => Some(l.at_midnight().cmp(r)),
+            (Value::Timestamp { value: l }, Value::Date { value: r }) => Some(l.cmp(&r.at_midnight())),
+            (Value::Time { value: l }, Value::Time { value: r }) => Some(l.cmp(r)),
+            (Value::Interval { value: l }, Value::Interval { value: r }) => Some(l.cmp(r)),
+            _ => None,
+        }
+    }
+}
+
+/// Values of the same type compare naturally, and numbers of different
+/// types by their value, as do dates and timestamps. Otherwise values of
+/// different types, which only meet when sorting, order nulls before
+/// booleans before numbers before varchars before dates and times before
+/// blobs.
+///
+/// Values are only equal if they have the same type, so numbers with the
+/// same value are told apart by type. Decimals are equal whatever their
+/// scale, but doubles only if their bits are, which makes NaN equal itself.
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Varchar { value: l }, Value::Varchar { value: r }) => l.cmp(r),
+            (Value::Blob { value: l }, Value::Blob { value: r }) => l.cmp(r),
+            (Value::Boolean { value: l }, Value::Boolean { value: r }) => l.cmp(r),
+            (l, r) => match l.compare_numbers(r).or_else(|| l.compare_times(r)) {
+                Some(ordering) => ordering.then_with(|| l.type_order().cmp(&r.type_order())).then_with(|| {
+                    match (l, r) {
+                        (Value::Double { value: l }, Value::Double { value: r }) => l.total_cmp(r),
+                        _ => Ordering::Equal,
+                    }
+                }),
+                None => l.type_order().cmp(&r.type_order()),
+            },
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.type_order().hash(state);
+        match self {
+            Value::Varchar { value } => value.hash(state),
+            Value::Blob { value } => value.hash(state),
+            Value::Number { value } => value.hash(state),
+            Value::Int { value } => value.hash(state),
+            Value::Decimal { value } => value.hash(state),
+            Value::Double { value } => value.to_bits().hash(state),
+            Value::Boolean { value } => value.hash(state),
+            Value::Date { value } => value.hash(state),
+            Value::Time { value } => value.hash(state),
+            Value::Timestamp { value } => value.hash(state),
+            Value::Interval { value } => value.hash(state),
+            Value::Null => {}
+        }
+    }
 }
 
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Identifies a row by the page it's on and its slot within the page.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct RowId {
+    pub page: usize,
+    pub slot: usize,
+}
+
+/// A table is stored in its own file as slotted pages, each holding as
+/// many rows as fit, so a row is found by its page and slot. Deleting a row
+/// frees its slot and space, which are reused by later inserts.
+///
+/// A row starts with a bitmap holding a bit for each column, set when the
+/// column is null, followed by the values of the other columns. Varchars
+/// take up only their length and their bytes.
+///
+/// Text and blob values have no maximum length. A row longer than
+/// `MAX_INLINE_ROW_LEN` has its longest such values moved to overflow
+/// pages, leaving the row holding only where each starts. Only tables with
+/// such columns have an overflow file.
+///
+/// Pages are accessed through the shared buffer pool. The page and row
+/// counts are kept by the catalog, which is what lets a table file be
+/// reopened.
 pub struct Table {
     pub column_specs: Vec<ColumnSpec>,
-    pages: Vec<Vec<u8>>,
-    row_size: usize,
-    rows_per_page: usize,
+    pool: SharedBufferPool,
+    file_id: FileId,
+    overflow: Option<Overflow>,
+    page_count: usize,
     pub row_count: usize,
+    flushed_counts: (usize, usize),
+    first_free_page: usize,
 }
 
 impl Table {
-    const PAGE_SIZE: usize = 4096;
-
-    pub fn new(column_specs: &Vec<ColumnSpec>) -> Table {
-        let row_size: usize = column_specs
+    const PAGE_SIZE: usize = pager::PAGE_SIZE;
+    const MAX_INLINE_ROW_LEN: usize = Table::PAGE_SIZE / 4;
+    /// Set in a varchar's, text's or blob's length when the value is in
+    /// overflow pages.
+    const OVERFLOWED: u32 = 1 << 31;
+
+    fn with_pager(
+        pool: &SharedBufferPool,
+        path: &Path,
+        pager: Pager,
+        column_specs: &[ColumnSpec],
+        page_count: usize,
+        row_count: usize,
+    ) -> io::Result<Table> {
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        let file_id = pool.borrow_mut().add_file(&file_name, pager);
+        let has_long_values = column_specs
             .iter()
-            .map(|c| c.column_type.bytes_len())
-            .sum();
-        let rows_per_page = Table::PAGE_SIZE / row_size;
-        Table {
-            column_specs: column_specs.clone(),
-            pages: Vec::new(),
-            row_size,
-            rows_per_page,
-            row_count: 0,
+            .any(|c| matches!(c.column_type, ColumnType::Text | ColumnType::Blob));
+        let overflow = match has_long_values {
+            true => Some(Overflow::open(pool, &path.with_extension("ovf"))?),
+            false => None,
+        };
+        Ok(Table {
+            column_specs: column_specs.to_vec(),
+            pool: pool.clone(),
+            file_id,
+            overflow,
+            page_count,
+            row_count,
+            flushed_counts: (page_count, row_count),
+            first_free_page: 0,
+        })
+    }
+
+    pub fn create(pool: &SharedBufferPool, path: &Path, column_specs: &[ColumnSpec]) -> io::Result<Table> {
+        let max_row_len = max_row_len(column_specs);
+        if max_row_len > slotted_page::MAX_TUPLE_LEN {
+            return Err(io::Error::other(format!(
+                "Rows of up to {} bytes don't fit in a {} byte page. Long values can be kept in text columns.",
+                max_row_len,
+                Table::PAGE_SIZE
+            )));
         }
+        let pager = Pager::create(path)?;
+        Table::with_pager(pool, path, pager, column_specs, 0, 0).inspect_err(|_| {
+            // The file is new, so nothing is lost. Should removing it fail,
+            // opening the database removes it.
+            let _ = fs::remove_file(path);
+        })
     }
 
-    fn page_and_offset<'a>(&self, i: usize) -> (usize, usize) {
-        let page_no = i / self.rows_per_page;
-        let offset = i % self.rows_per_page;
-        (page_no, offset)
+    pub fn open(
+        pool: &SharedBufferPool,
+        path: &Path,
+        column_specs: &[ColumnSpec],
+        page_count: usize,
+        row_count: usize,
+    ) -> io::Result<Table> {
+        Table::with_pager(pool, path, Pager::open(path)?, column_specs, page_count, row_count)
     }
 
-    pub fn insert(&mut self, row: &Row) {
-        let (page_no, offset) = self.page_and_offset(self.row_count);
-        self.row_count += 1;
+    pub fn page_count(&self) -> usize {
+        self.page_count
+    }
 
-        let page = match self.pages.get_mut(page_no) {
-            Some(page) => page,
-            None => {
-                let page = vec![0; Table::PAGE_SIZE];
-                self.pages.resize(self.pages.len() + 1, page);
-                &mut self.pages[page_no]
+    /// Writes the table's modified pages back to its file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.pool.borrow_mut().flush_file(self.file_id)?;
+        if let Some(overflow) = self.overflow.as_mut() {
+            overflow.flush()?;
+        }
+        self.flushed_counts = (self.page_count, self.row_count);
+        Ok(())
+    }
+
+    /// Forgets every change made since the last flush. The pages themselves
+    /// are restored by rolling back the buffer pool.
+    pub fn rollback(&mut self) {
+        (self.page_count, self.row_count) = self.flushed_counts;
+        self.first_free_page = 0;
+    }
+
+    /// Puts a tuple on the first page with room for it, searching from the
+    /// lowest page that may have some, or else on a new page at the end of
+    /// the table.
+    fn place(&mut self, tuple: &[u8]) -> io::Result<RowId> {
+        let mut pool = self.pool.borrow_mut();
+        for page_no in self.first_free_page..self.page_count + 1 {
+            let frame_id = pool.fetch(self.file_id, page_no)?;
+            let slot = match slotted_page::has_room(pool.page(frame_id), tuple.len()) {
+                true => slotted_page::insert(pool.page_mut(frame_id), tuple),
+                false => None,
+            };
+            pool.unpin(frame_id);
+
+            if let Some(slot) = slot {
+                self.first_free_page = page_no;
+                self.page_count = self.page_count.max(page_no + 1);
+                return Ok(RowId { page: page_no, slot });
             }
+        }
+        Err(io::Error::other(format!(
+            "A row of {} bytes doesn't fit in a page",
+            tuple.len()
+        )))
+    }
+
+    /// Inserts the row, returning where it was put.
+    pub fn insert(&mut self, row: &Row) -> io::Result<RowId> {
+        let tuple = self.encode(row)?;
+        let row_id = self.place(&tuple)?;
+        self.row_count += 1;
+        Ok(row_id)
+    }
+
+    /// Overwrites the live row `row_id`, returning where the row is now. It
+    /// stays where it is if it still fits in its page, and otherwise moves.
+    pub fn update(&mut self, row_id: RowId, row: &Row) -> io::Result<RowId> {
+        let Some(old_tuple) = self.tuple(row_id)? else {
+            return Err(io::Error::other(format!(
+                "Slot {} of page {} holds no row to update",
+                row_id.slot, row_id.page
+            )));
+        };
+        self.free_overflow(&old_tuple)?;
+        let tuple = self.encode(row)?;
+
+        let mut pool = self.pool.borrow_mut();
+        let frame_id = pool.fetch(self.file_id, row_id.page)?;
+        let page = pool.page_mut(frame_id);
+        let updated = slotted_page::update(page, row_id.slot, &tuple);
+        if !updated {
+            slotted_page::delete(page, row_id.slot);
+        }
+        pool.unpin(frame_id);
+        drop(pool);
+
+        if updated {
+            return Ok(row_id);
+        }
+        self.first_free_page = self.first_free_page.min(row_id.page);
+        self.place(&tuple)
+    }
+
+    pub fn delete(&mut self, row_id: RowId) -> io::Result<()> {
+        let Some(old_tuple) = self.tuple(row_id)? else {
+            return Ok(());
         };
+        self.free_overflow(&old_tuple)?;
 
-        row.write(page, offset);
+        let mut pool = self.pool.borrow_mut();
+        let frame_id = pool.fetch(self.file_id, row_id.page)?;
+        slotted_page::delete(pool.page_mut(frame_id), row_id.slot);
+        pool.unpin(frame_id);
+
+        self.row_count -= 1;
+        self.first_free_page = self.first_free_page.min(row_id.page);
+        Ok(())
     }
 
     pub fn csv_import(
         &mut self,
         csv_path: &String,
         column_mapping: &HashMap<String, String>,
-        with_truncate: bool
+        with_truncate: bool,
+        formats: &Formats,
     ) -> io::Result<()> {
         let mut reader = csv::Reader::from_path(csv_path)?;
         
         let cs = self.column_specs.clone();
 
-        let header: Result<Vec<(usize, &ColumnSpec)>, String> = reader.headers().map_err(|e| todo!()).and_then(|header_map| {cs.iter().map(|cs| {
+        let header: Result<Vec<(usize, &ColumnSpec)>, String> = reader.headers().map_err(|e| e.to_string()).and_then(|header_map| {cs.iter().map(|cs| {
             column_mapping
                 .get(&cs.column_name)
                 .ok_or(format!("Incomplete CSV import mapping. No mapping for table column '{}'",
@@ -90,7 +443,7 @@ impl Table {
         let mut result: io::Result<()> = Ok(());
         for (i, record_result) in reader.records().enumerate() {
             let values: io::Result<HashMap<String, Value>> = 
-                record_result.map_err(|e| io::Error::other(e)).and_then(|r| {
+                record_result.map_err(io::Error::other).and_then(|r| {
                 header
                 .iter()
                 .map(|(csv_index, cs)| {
@@ -101,8 +454,11 @@ impl Table {
                             i, cs.column_name
                         )))
                         .and_then(|string_value| {
+                            if string_value.is_empty() && !cs.not_null {
+                                return Ok(Value::Null);
+                            }
                             cs.column_type
-                                .parse(&string_value, with_truncate)
+                                .parse(string_value, with_truncate, formats)
                                 .ok_or(io::Error::other(format!(
                                     "Row {} failed to parse value for table column '{}' '{}' into {:?}.", i, cs.column_name, string_value, cs.column_type
                                 )))
@@ -117,10 +473,8 @@ impl Table {
                     .map_err(|rb| io::Error::other(format!("Failed to build row {}: {:?}", i, rb)))
             });
 
-            match row {
-                Ok(row) => {
-                    self.insert(&row);
-                }
+            match row.and_then(|row| self.insert(&row)) {
+                Ok(_) => {}
                 Err(err) => {
                     result = Err(err);
                     break;
@@ -130,57 +484,226 @@ impl Table {
         result
     }
 
-    fn read(buffer: &Vec<u8>, column_specs: &Vec<ColumnSpec>, base: usize) -> Vec<Value> {
+    /// Encodes the row as a tuple. Its longest text and blob values are
+    /// moved to overflow pages until it's no longer than
+    /// `MAX_INLINE_ROW_LEN`, or there are none left to move.
+    fn encode(&mut self, row: &Row) -> io::Result<Vec<u8>> {
+        let mut overflow_pages = vec![None; row.values.len()];
+        loop {
+            let tuple = row.encode(&overflow_pages);
+            let longest = row
+                .values
+                .iter()
+                .zip(self.column_specs.iter())
+                .enumerate()
+                .filter(|(index, (_, cs))| {
+                    overflow_pages[*index].is_none() && matches!(cs.column_type, ColumnType::Text | ColumnType::Blob)
+                })
+                .filter_map(|(index, ((value, _), _))| variable_bytes(value).map(|bytes| (bytes.len(), index)))
+                // A reference to overflow pages takes 8 bytes.
+                .filter(|(len, _)| *len > 8)
+                .max();
+
+            match (longest, self.overflow.as_mut()) {
+                (Some((_, index)), Some(overflow)) if tuple.len() > Table::MAX_INLINE_ROW_LEN => {
+                    let bytes = variable_bytes(&row.values[index].0).unwrap();
+                    overflow_pages[index] = Some(overflow.write(bytes)?);
+                }
+                _ => return Ok(tuple),
+            }
+        }
+    }
+
+    /// Frees the overflow pages of a row's long values.
+    fn free_overflow(&mut self, tuple: &[u8]) -> io::Result<()> {
+        if let Some(overflow) = self.overflow.as_mut() {
+            for page in overflow_pages(tuple, &self.column_specs) {
+                overflow.free(page)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes a row. Long text and blob values are read from the overflow
+    /// pages the row refers to, so `overflow` is needed if it has any.
+    fn read(tuple: &[u8], column_specs: &[ColumnSpec], overflow: Option<&Overflow>) -> io::Result<Vec<Value>> {
         let mut res = Vec::new();
-        let mut offset: usize = 0;
-        for cs in column_specs {
-            let len = cs.column_type.bytes_len();
-            let bytes = &buffer[(base + offset)..(base + offset + len)];
-
-            let value = match cs.column_type {
-                ColumnType::Varchar { max_len: _ } => {
-                    let str_len_bytes: [u8; 8] = bytes[0..8].try_into().unwrap();
-                    let str_len = usize::from_be_bytes(str_len_bytes);
-                    let str_bytes = &bytes[8..8 + str_len];
-                    Value::Varchar {
-                        value: String::from_utf8(Vec::from(str_bytes)).unwrap(),
+        for (cs, bytes) in column_specs.iter().zip(split_tuple(tuple, column_specs)) {
+            let value = match (cs.column_type, bytes) {
+                (_, None) => Value::Null,
+                (ColumnType::Varchar { .. } | ColumnType::Text | ColumnType::Blob, Some(bytes)) => {
+                    let (len, overflowed) = read_length(bytes);
+                    let content = if overflowed {
+                        let page = u64::from_be_bytes(bytes[4..12].try_into().unwrap()) as usize;
+                        overflow
+                            .ok_or_else(|| io::Error::other("Row refers to overflow pages, but there are none"))?
+                            .read(page, len)?
+                    } else {
+                        bytes[4..4 + len].to_vec()
+                    };
+                    match cs.column_type {
+                        ColumnType::Blob => Value::Blob { value: content },
+                        _ => Value::Varchar {
+                            value: String::from_utf8(content).map_err(io::Error::other)?,
+                        },
                     }
                 }
-                ColumnType::Number => {
+                (ColumnType::Number, Some(bytes)) => {
                     let fixed_bytes: [u8; 8] = bytes.try_into().unwrap();
                     Value::Number {
                         value: u64::from_be_bytes(fixed_bytes),
                     }
                 }
-                ColumnType::Boolean => Value::Boolean {
+                (ColumnType::SmallInt, Some(bytes)) => {
+                    let fixed_bytes: [u8; 2] = bytes.try_into().unwrap();
+                    Value::Int {
+                        value: i16::from_be_bytes(fixed_bytes) as i64,
+                    }
+                }
+                (ColumnType::Int, Some(bytes)) => {
+                    let fixed_bytes: [u8; 8] = bytes.try_into().unwrap();
+                    Value::Int {
+                        value: i64::from_be_bytes(fixed_bytes),
+                    }
+                }
+                (ColumnType::Double, Some(bytes)) => {
+                    let fixed_bytes: [u8; 8] = bytes.try_into().unwrap();
+                    Value::Double {
+                        value: f64::from_be_bytes(fixed_bytes),
+                    }
+                }
+                (ColumnType::Decimal { scale, .. }, Some(bytes)) => {
+                    let fixed_bytes: [u8; 16] = bytes.try_into().unwrap();
+                    Value::Decimal {
+                        value: Decimal::new(i128::from_be_bytes(fixed_bytes), scale),
+                    }
+                }
+                (ColumnType::Boolean, Some(bytes)) => Value::Boolean {
                     value: bytes[0] == 1,
                 },
+                (ColumnType::Date, Some(bytes)) => {
+                    let fixed_bytes: [u8; 4] = bytes.try_into().unwrap();
+                    Value::Date {
+                        value: Date::from_days(i32::from_be_bytes(fixed_bytes) as i64).unwrap(),
+                    }
+                }
+                (ColumnType::Time, Some(bytes)) => {
+                    let fixed_bytes: [u8; 8] = bytes.try_into().unwrap();
+                    Value::Time {
+                        value: Time::from_micros(i64::from_be_bytes(fixed_bytes)).unwrap(),
+                    }
+                }
+                (ColumnType::Timestamp, Some(bytes)) => {
+                    let fixed_bytes: [u8; 8] = bytes.try_into().unwrap();
+                    Value::Timestamp {
+                        value: Timestamp::from_micros(i64::from_be_bytes(fixed_bytes)).unwrap(),
+                    }
+                }
+                (ColumnType::Interval, Some(bytes)) => Value::Interval {
+                    value: Interval::new(
+                        i32::from_be_bytes(bytes[0..4].try_into().unwrap()),
+                        i32::from_be_bytes(bytes[4..8].try_into().unwrap()),
+                        i64::from_be_bytes(bytes[8..16].try_into().unwrap()),
+                    ),
+                },
             };
-
             res.push(value);
-            offset += len;
         }
 
-        res
+        Ok(res)
     }
 
-    pub fn get(&mut self, i: usize) -> Result<Row, RowBuildError> {
-        let (page_no, offset) = self.page_and_offset(i);
-        let page = match self.pages.get_mut(page_no) {
-            Some(page) => page,
-            None => {
-                let page = vec![0; Table::PAGE_SIZE];
-                self.pages.resize(self.pages.len() + 1, page);
-                &mut self.pages[page_no]
-            }
+    /// A copy of the row `row_id`, or `None` if there's no such row.
+    /// Reading its long values fetches more pages, which is best done once
+    /// its own page is let go.
+    fn tuple(&self, row_id: RowId) -> io::Result<Option<Vec<u8>>> {
+        if row_id.page >= self.page_count {
+            return Ok(None);
+        }
+
+        let mut pool = self.pool.borrow_mut();
+        let frame_id = pool.fetch(self.file_id, row_id.page)?;
+        let tuple = slotted_page::tuple(pool.page(frame_id), row_id.slot).map(<[u8]>::to_vec);
+        pool.unpin(frame_id);
+        Ok(tuple)
+    }
+
+    /// The row `row_id`, or `None` if there's no such row.
+    pub fn get(&self, row_id: RowId) -> io::Result<Option<Row>> {
+        let values = match self.tuple(row_id)? {
+            Some(tuple) => Some(Table::read(&tuple, &self.column_specs, self.overflow.as_ref())?),
+            None => None,
         };
 
-        let values = Table::read(page, &self.column_specs, offset);
-        let column_values = self.column_specs.iter().zip(values).map(|(cs, v)| {
-            (cs.column_name.clone(), v)
-        }).collect();
+        values.map(|values| {
+            let column_values = self.column_specs.iter().zip(values).map(|(cs, v)| {
+                (cs.column_name.clone(), v)
+            }).collect();
+
+            Row::new(&column_values, &self.column_specs)
+                .map_err(|rb| io::Error::other(format!("Failed to read row {:?}: {:?}", row_id, rb)))
+        }).transpose()
+    }
+
+    /// Every live row along with its id, in page and slot order.
+    pub fn scan(&self) -> TableScan<'_> {
+        TableScan {
+            table: self,
+            page: 0,
+            slot: 0,
+            slot_count: None,
+        }
+    }
+
+    /// The number of slots on a page, live or not.
+    fn slot_count(&self, page_no: usize) -> io::Result<usize> {
+        let mut pool = self.pool.borrow_mut();
+        let frame_id = pool.fetch(self.file_id, page_no)?;
+        let slot_count = slotted_page::slot_count(pool.page(frame_id));
+        pool.unpin(frame_id);
+        Ok(slot_count)
+    }
+}
+
+pub struct TableScan<'a> {
+    table: &'a Table,
+    page: usize,
+    slot: usize,
+    /// The current page's number of slots, once it's been read.
+    slot_count: Option<usize>,
+}
+
+impl Iterator for TableScan<'_> {
+    type Item = io::Result<(RowId, Row)>;
 
-        Row::new(&column_values, &self.column_specs)
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.page < self.table.page_count {
+            let slot_count = match self.slot_count {
+                Some(slot_count) => slot_count,
+                None => match self.table.slot_count(self.page) {
+                    Ok(slot_count) => *self.slot_count.insert(slot_count),
+                    Err(err) => return Some(Err(err)),
+                },
+            };
+            if self.slot >= slot_count {
+                self.page += 1;
+                self.slot = 0;
+                self.slot_count = None;
+                continue;
+            }
+
+            let row_id = RowId {
+                page: self.page,
+                slot: self.slot,
+            };
+            self.slot += 1;
+            match self.table.get(row_id) {
+                Ok(Some(row)) => return Some(Ok((row_id, row))),
+                Ok(None) => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        None
     }
 }
 
@@ -188,25 +711,90 @@ impl Table {
 pub struct ColumnSpec {
     pub column_name: String,
     pub column_type: ColumnType,
+    pub not_null: bool,
 }
 
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
 pub enum ColumnType {
     Varchar { max_len: usize },
     Number,
+    SmallInt,
+    Int,
+    Decimal { precision: u8, scale: u8 },
+    Double,
     Boolean,
+    Date,
+    Time,
+    Timestamp,
+    Interval,
+    Text,
+    Blob,
 }
 
 impl ColumnType {
     fn bytes_len(&self) -> usize {
         match self {
-            ColumnType::Varchar { max_len } => 8 + max_len,
-            ColumnType::Number => 8,
+            // The length, then the value.
+            ColumnType::Varchar { max_len } => 4 + max_len,
+            // The length, then the page the value overflows to, since
+            // shorter values are kept in the row as they are.
+            ColumnType::Text | ColumnType::Blob => 4 + 8,
+            ColumnType::Number | ColumnType::Int | ColumnType::Double => 8,
+            ColumnType::SmallInt => 2,
+            // A decimal is stored as its units at the column's scale.
+            ColumnType::Decimal { .. } => 16,
             ColumnType::Boolean => 1,
+            // Days since 1970-01-01.
+            ColumnType::Date => 4,
+            // Microseconds since midnight, or since 1970-01-01 00:00:00.
+            ColumnType::Time | ColumnType::Timestamp => 8,
+            // Months, days and microseconds.
+            ColumnType::Interval => 16,
         }
     }
 
-    fn parse(&self, s: &str, with_truncate: bool) -> Option<Value> {
+    /// Reads text as a date or time if that's what the column holds, so a
+    /// date can be given as `"2017-01-31"`, and widens a date to a
+    /// timestamp. Anything else is left as it is.
+    fn convert_time(&self, value: &Value) -> Value {
+        let converted = match (self, value) {
+            (ColumnType::Date, Value::Varchar { value }) => Date::parse(value).map(|value| Value::Date { value }),
+            (ColumnType::Time, Value::Varchar { value }) => Time::parse(value).map(|value| Value::Time { value }),
+            (ColumnType::Timestamp, Value::Varchar { value }) => {
+                Timestamp::parse(value).map(|value| Value::Timestamp { value })
+            }
+            (ColumnType::Interval, Value::Varchar { value }) => {
+                Interval::parse(value).map(|value| Value::Interval { value })
+            }
+            (ColumnType::Timestamp, Value::Date { value }) => Some(Value::Timestamp {
+                value: value.at_midnight(),
+            }),
+            _ => None,
+        };
+        converted.unwrap_or_else(|| value.clone())
+    }
+
+    /// Converts a number to this type of number, or `None` if it's out of
+    /// range. Whole numbers convert to any type of number, decimals to
+    /// decimals and doubles, and doubles only to doubles. A decimal is
+    /// rounded to the column's scale. Anything else is left as it is.
+    fn convert_number(&self, value: &Value) -> Option<Value> {
+        match (self, value.whole(), value.decimal()) {
+            (ColumnType::Number, Some(whole), _) => u64::try_from(whole).ok().map(|value| Value::Number { value }),
+            (ColumnType::Int, Some(whole), _) => i64::try_from(whole).ok().map(|value| Value::Int { value }),
+            (ColumnType::SmallInt, Some(whole), _) => i16::try_from(whole).ok().map(|value| Value::Int {
+                value: value as i64,
+            }),
+            (ColumnType::Decimal { precision, scale }, _, Some(decimal)) => decimal
+                .rescale(*scale)
+                .filter(|decimal| decimal.precision() <= *precision)
+                .map(|value| Value::Decimal { value }),
+            (ColumnType::Double, _, Some(_)) => value.double().map(|value| Value::Double { value }),
+            _ => Some(value.clone()),
+        }
+    }
+
+    fn parse(&self, s: &str, with_truncate: bool, formats: &Formats) -> Option<Value> {
         match self {
             ColumnType::Varchar { max_len } if s.len() <= *max_len => Some(Value::Varchar {
                 value: s.to_string(),
@@ -216,13 +804,56 @@ impl ColumnType {
             }),
             ColumnType::Varchar { max_len: _ } => None,
 
-            ColumnType::Number => u64::from_str_radix(s, 10)
+            ColumnType::Text => Some(Value::Varchar { value: s.to_string() }),
+
+            ColumnType::Blob => parse_hex(s).map(|b| Value::Blob { value: b }),
+
+            ColumnType::Number => s
+                .parse::<u64>()
                 .ok()
                 .map(|i| Value::Number { value: i }),
 
+            ColumnType::SmallInt => s
+                .parse::<i16>()
+                .ok()
+                .map(|i| Value::Int { value: i as i64 }),
+
+            ColumnType::Int => s
+                .parse::<i64>()
+                .ok()
+                .map(|i| Value::Int { value: i }),
+
+            ColumnType::Double => s
+                .parse::<f64>()
+                .ok()
+                .map(|d| Value::Double { value: d }),
+
+            // Rounded to the column's scale when the row is built.
+            ColumnType::Decimal { .. } => Decimal::parse(s).map(|d| Value::Decimal { value: d }),
+
             ColumnType::Boolean if s == "true" => Some(Value::Boolean { value: true }),
             ColumnType::Boolean if s == "false" => Some(Value::Boolean { value: false }),
             ColumnType::Boolean => None,
+
+            ColumnType::Date => match &formats.date {
+                Some(format) => Date::parse_with(s, format),
+                None => Date::parse(s),
+            }
+            .map(|d| Value::Date { value: d }),
+
+            ColumnType::Time => match &formats.time {
+                Some(format) => Time::parse_with(s, format),
+                None => Time::parse(s),
+            }
+            .map(|t| Value::Time { value: t }),
+
+            ColumnType::Timestamp => match &formats.timestamp {
+                Some(format) => Timestamp::parse_with(s, format),
+                None => Timestamp::parse(s),
+            }
+            .map(|t| Value::Timestamp { value: t }),
+
+            ColumnType::Interval => Interval::parse(s).map(|i| Value::Interval { value: i }),
         }
     }
 }
@@ -243,27 +874,61 @@ pub enum RowBuildError {
         expected: ColumnType,
         actual: ColumnType,
     },
+    NullValue {
+        column_name: String,
+    },
+    ValueOutOfRange {
+        column_name: String,
+        column_type: ColumnType,
+    },
 }
 
 impl Row {
+    /// Builds a row from values named by column. Columns without a value
+    /// are null, which is only allowed if they weren't declared `not null`.
     pub fn new(
         column_values: &HashMap<String, Value>,
-        column_specs: &Vec<ColumnSpec>,
+        column_specs: &[ColumnSpec],
     ) -> Result<Row, RowBuildError> {
         let expected: HashSet<String> =
             column_specs.iter().map(|c| c.column_name.clone()).collect();
         let actual: HashSet<String> = column_values.keys().cloned().collect();
 
-        if actual == expected {
+        if actual.is_subset(&expected) {
             let mut res = Vec::new();
             for cs in column_specs {
-                let value = column_values.get(&cs.column_name).unwrap();
+                let value = column_values.get(&cs.column_name).unwrap_or(&Value::Null);
+                let value = &cs.column_type.convert_time(value);
+                let value = &cs.column_type.convert_number(value).ok_or_else(|| RowBuildError::ValueOutOfRange {
+                    column_name: cs.column_name.clone(),
+                    column_type: cs.column_type,
+                })?;
                 let value_type = match value {
                     Value::Varchar { value } => ColumnType::Varchar {
                         max_len: value.len(),
                     },
+                    Value::Blob { value: _ } => ColumnType::Blob,
                     Value::Number { value: _ } => ColumnType::Number,
+                    Value::Int { value: _ } => ColumnType::Int,
+                    Value::Decimal { value } => ColumnType::Decimal {
+                        precision: value.precision(),
+                        scale: value.scale(),
+                    },
+                    Value::Double { value: _ } => ColumnType::Double,
                     Value::Boolean { value: _ } => ColumnType::Boolean,
+                    Value::Date { value: _ } => ColumnType::Date,
+                    Value::Time { value: _ } => ColumnType::Time,
+                    Value::Timestamp { value: _ } => ColumnType::Timestamp,
+                    Value::Interval { value: _ } => ColumnType::Interval,
+                    Value::Null if cs.not_null => {
+                        return Err(RowBuildError::NullValue {
+                            column_name: cs.column_name.clone(),
+                        })
+                    }
+                    Value::Null => {
+                        res.push((Value::Null, cs.column_type.bytes_len()));
+                        continue;
+                    }
                 };
 
                 let type_matches = match (&cs.column_type, value_type) {
@@ -271,6 +936,10 @@ impl Row {
                         ColumnType::Varchar { max_len: max },
                         ColumnType::Varchar { max_len: actual },
                     ) => actual <= *max,
+                    (ColumnType::Text, ColumnType::Varchar { .. }) => true,
+                    // Converted above, so it's in range.
+                    (ColumnType::SmallInt, ColumnType::Int) => true,
+                    (ColumnType::Decimal { .. }, ColumnType::Decimal { .. }) => true,
                     (t1, t2) => *t1 == t2,
                 };
 
@@ -290,50 +959,140 @@ impl Row {
         }
     }
 
-    fn write(&self, buffer: &mut Vec<u8>, base: usize) {
-        let mut offset: usize = 0;
-
-        let mut write_byte = |b: u8| {
-            buffer[base + offset] = b;
-            offset += 1;
-        };
+    pub fn into_values(self) -> Vec<Value> {
+        self.values.into_iter().map(|(value, _)| value).collect()
+    }
 
-        for (value, bytes_len) in self.values.iter() {
+    /// Encodes the row as a tuple, with the long values in the given
+    /// overflow pages referred to by their first page. Null values take up
+    /// no space beyond their bit.
+    fn encode(&self, overflow_pages: &[Option<usize>]) -> Vec<u8> {
+        let mut tuple = vec![0; null_bitmap_len(self.values.len())];
+        for (index, (value, bytes_len)) in self.values.iter().enumerate() {
             match value {
-                Value::Varchar { value } => {
-                    let bytes = value.as_bytes();
-
-                    for b in bytes.len().to_be_bytes() {
-                        write_byte(b);
-                    }
-
-                    for b in bytes {
-                        write_byte(*b);
-                    }
-                    for _ in 0..bytes_len - 8 - bytes.len() {
-                        write_byte(0);
+                Value::Varchar { value: _ } | Value::Blob { value: _ } => {
+                    let bytes = variable_bytes(value).unwrap();
+                    match overflow_pages.get(index).copied().flatten() {
+                        Some(page) => {
+                            tuple.extend((bytes.len() as u32 | Table::OVERFLOWED).to_be_bytes());
+                            tuple.extend((page as u64).to_be_bytes());
+                        }
+                        None => {
+                            tuple.extend((bytes.len() as u32).to_be_bytes());
+                            tuple.extend(bytes);
+                        }
                     }
                 }
-                Value::Number { value } => {
-                    for b in value.to_be_bytes() {
-                        write_byte(b);
-                    }
-                }
-                Value::Boolean { value } if *value => {
-                    write_byte(1);
-                }
-                Value::Boolean { value: _ } => {
-                    write_byte(0);
+                Value::Number { value } => tuple.extend(value.to_be_bytes()),
+                // Smallints keep the low bytes, which is all of a value in
+                // range.
+                Value::Int { value } => tuple.extend(&value.to_be_bytes()[8 - bytes_len..]),
+                Value::Double { value } => tuple.extend(value.to_be_bytes()),
+                Value::Decimal { value } => tuple.extend(value.units().to_be_bytes()),
+                Value::Boolean { value } => tuple.push(*value as u8),
+                Value::Date { value } => tuple.extend(value.days().to_be_bytes()),
+                Value::Time { value } => tuple.extend(value.micros().to_be_bytes()),
+                Value::Timestamp { value } => tuple.extend(value.micros().to_be_bytes()),
+                Value::Interval { value } => {
+                    tuple.extend(value.months().to_be_bytes());
+                    tuple.extend(value.days().to_be_bytes());
+                    tuple.extend(value.micros().to_be_bytes());
                 }
+                Value::Null => tuple[index / 8] |= 1 << (index % 8),
             }
         }
+        tuple
     }
+}
+
+/// The bytes needed for a bit for each of a row's columns.
+fn null_bitmap_len(column_count: usize) -> usize {
+    column_count.div_ceil(8)
+}
+
+/// The longest a row can be, with its long values in overflow pages.
+fn max_row_len(column_specs: &[ColumnSpec]) -> usize {
+    null_bitmap_len(column_specs.len()) + column_specs.iter().map(|c| c.column_type.bytes_len()).sum::<usize>()
+}
+
+/// The bytes of a varchar or blob.
+fn variable_bytes(value: &Value) -> Option<&[u8]> {
+    match value {
+        Value::Varchar { value } => Some(value.as_bytes()),
+        Value::Blob { value } => Some(value),
+        _ => None,
+    }
+}
 
+/// The length of the varchar, text or blob at the start of `bytes`, and
+/// whether it's in overflow pages.
+fn read_length(bytes: &[u8]) -> (usize, bool) {
+    let header = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
+    ((header & !Table::OVERFLOWED) as usize, header & Table::OVERFLOWED != 0)
+}
+
+/// Splits a row into the bytes of each of its columns, or `None` for a null
+/// column.
+fn split_tuple<'a>(tuple: &'a [u8], column_specs: &[ColumnSpec]) -> Vec<Option<&'a [u8]>> {
+    let mut offset = null_bitmap_len(column_specs.len());
+    column_specs
+        .iter()
+        .enumerate()
+        .map(|(index, cs)| {
+            if tuple[index / 8] & (1 << (index % 8)) != 0 {
+                return None;
+            }
+            let len = match cs.column_type {
+                ColumnType::Varchar { .. } | ColumnType::Text | ColumnType::Blob => match read_length(&tuple[offset..]) {
+                    (_, true) => 4 + 8,
+                    (len, false) => 4 + len,
+                },
+                column_type => column_type.bytes_len(),
+            };
+            offset += len;
+            Some(&tuple[offset - len..offset])
+        })
+        .collect()
+}
+
+/// The first overflow page of each of a row's values that are kept in
+/// overflow pages.
+fn overflow_pages(tuple: &[u8], column_specs: &[ColumnSpec]) -> Vec<usize> {
+    column_specs
+        .iter()
+        .zip(split_tuple(tuple, column_specs))
+        .filter_map(|(cs, bytes)| match (cs.column_type, bytes) {
+            (ColumnType::Text | ColumnType::Blob, Some(bytes)) if read_length(bytes).1 => {
+                Some(u64::from_be_bytes(bytes[4..12].try_into().unwrap()) as usize)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Reads bytes written as hexadecimal digits, optionally after `\x`.
+pub fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    let digits = s.strip_prefix("\\x").unwrap_or(s);
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok())
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
+    use std::fs;
+
     use super::*;
+    use crate::{buffer_pool::BufferPool, wal::Wal};
+
+    fn test_pool(dir: &tempfile::TempDir, capacity: usize) -> SharedBufferPool {
+        let wal = Wal::open(&dir.path().join("wal")).unwrap();
+        BufferPool::new(capacity, wal).shared()
+    }
 
     #[test]
     fn test_row_build_missing_values() {
@@ -341,20 +1100,40 @@ mod tests {
             ColumnSpec {
                 column_name: "foo".to_string(),
                 column_type: ColumnType::Boolean,
+                not_null: true,
             },
             ColumnSpec {
                 column_name: "bar".to_string(),
                 column_type: ColumnType::Boolean,
+                not_null: false,
             },
             ColumnSpec {
                 column_name: "baz".to_string(),
                 column_type: ColumnType::Boolean,
+                not_null: false,
             },
         ];
-        let column_values = HashMap::from([("bar".to_string(), Value::Boolean { value: true })]);
+        let mut column_values = HashMap::from([("bar".to_string(), Value::Boolean { value: true })]);
 
         let result = Row::new(&column_values, &column_specs).err();
+        let expected_error = RowBuildError::NullValue {
+            column_name: "foo".to_string(),
+        };
+        assert_eq!(Some(expected_error), result);
 
+        column_values.insert("foo".to_string(), Value::Boolean { value: false });
+        let result = Row::new(&column_values, &column_specs).map(Row::into_values);
+        assert_eq!(
+            Ok(vec![
+                Value::Boolean { value: false },
+                Value::Boolean { value: true },
+                Value::Null
+            ]),
+            result
+        );
+
+        column_values.insert("qux".to_string(), Value::Boolean { value: false });
+        let result = Row::new(&column_values, &column_specs).err();
         let expected_error = RowBuildError::ColumnNameMismatch {
             actual: column_values.keys().cloned().collect(),
             expected: HashSet::from(["foo".to_string(), "bar".to_string(), "baz".to_string()]),
@@ -367,6 +1146,7 @@ mod tests {
         let column_specs = vec![ColumnSpec {
             column_name: "foo".to_string(),
             column_type: ColumnType::Boolean,
+            not_null: false,
         }];
         let column_values = HashMap::from([("foo".to_string(), Value::Number { value: 42 })]);
 
@@ -385,6 +1165,7 @@ mod tests {
         let column_specs = vec![ColumnSpec {
             column_name: "foo".to_string(),
             column_type: ColumnType::Varchar { max_len: 4 },
+            not_null: false,
         }];
         let column_values = HashMap::from([(
             "foo".to_string(),
@@ -409,14 +1190,17 @@ mod tests {
             ColumnSpec {
                 column_name: "foo".to_string(),
                 column_type: ColumnType::Boolean,
+                not_null: false,
             },
             ColumnSpec {
                 column_name: "bar".to_string(),
                 column_type: ColumnType::Varchar { max_len: 5 },
+                not_null: false,
             },
             ColumnSpec {
                 column_name: "baz".to_string(),
                 column_type: ColumnType::Number,
+                not_null: false,
             },
         ];
         let column_values = HashMap::from([
@@ -439,7 +1223,7 @@ mod tests {
                     Value::Varchar {
                         value: "hello".to_string(),
                     },
-                    8 + 5,
+                    4 + 5,
                 ),
                 (Value::Number { value: 42 }, 8),
             ],
@@ -448,25 +1232,64 @@ mod tests {
     }
 
     #[test]
-    fn test_table_row_size() {
+    fn test_table_row_len() {
         let column_specs = vec![
             ColumnSpec {
                 column_name: "foo".to_string(),
                 column_type: ColumnType::Boolean,
+                not_null: false,
             },
             ColumnSpec {
                 column_name: "bar".to_string(),
                 column_type: ColumnType::Varchar { max_len: 5 },
+                not_null: false,
             },
             ColumnSpec {
                 column_name: "baz".to_string(),
                 column_type: ColumnType::Number,
+                not_null: false,
             },
         ];
 
-        let table = Table::new(&column_specs);
+        assert_eq!(1 + 1 + (4 + 5) + 8, max_row_len(&column_specs));
+
+        // A varchar only takes up its length and its bytes.
+        let column_values = HashMap::from([
+            ("foo".to_string(), Value::Boolean { value: true }),
+            ("bar".to_string(), Value::Varchar { value: "ab".to_string() }),
+            ("baz".to_string(), Value::Null),
+        ]);
+        let row = Row::new(&column_values, &column_specs).unwrap();
+        assert_eq!(1 + 1 + (4 + 2), row.encode(&[]).len());
+    }
+
+    #[test]
+    fn test_row_roundtrip_nulls() {
+        // Enough columns for the null bitmap to take two bytes.
+        let column_specs: Vec<ColumnSpec> = (0..10)
+            .map(|i| ColumnSpec {
+                column_name: format!("column{}", i),
+                column_type: ColumnType::Varchar { max_len: 5 },
+                not_null: false,
+            })
+            .collect();
+        let values: Vec<Value> = (0..10)
+            .map(|i| match i % 3 {
+                0 => Value::Null,
+                _ => Value::Varchar { value: i.to_string() },
+            })
+            .collect();
+        let column_values = column_specs
+            .iter()
+            .map(|c| c.column_name.clone())
+            .zip(values.iter().cloned())
+            .collect();
 
-        assert_eq!(table.row_size, 1 + (8 + 5) + 8);
+        let row = Row::new(&column_values, &column_specs).unwrap();
+        let tuple = row.encode(&[]);
+        assert_eq!([0b0100_1001, 0b0000_0010], tuple[0..2]);
+        assert_eq!(2 + 6 * (4 + 1), tuple.len());
+        assert_eq!(values, Table::read(&tuple, &column_specs, None).unwrap());
     }
 
     #[test]
@@ -475,14 +1298,17 @@ mod tests {
             ColumnSpec {
                 column_name: "foo".to_string(),
                 column_type: ColumnType::Boolean,
+                not_null: false,
             },
             ColumnSpec {
                 column_name: "bar".to_string(),
                 column_type: ColumnType::Varchar { max_len: 5 },
+                not_null: false,
             },
             ColumnSpec {
                 column_name: "baz".to_string(),
                 column_type: ColumnType::Number,
+                not_null: false,
             },
         ];
         let values = vec![
@@ -499,9 +1325,7 @@ mod tests {
             .collect();
 
         let row = Row::new(&column_values, &column_specs).unwrap();
-        let mut buffer: Vec<u8> = vec![0; Table::PAGE_SIZE];
-        row.write(&mut buffer, 0);
-        let result = Table::read(&buffer, &column_specs, 0);
+        let result = Table::read(&row.encode(&[]), &column_specs, None).unwrap();
 
         assert_eq!(values, result);
     }
@@ -513,17 +1337,20 @@ mod tests {
             ColumnSpec {
                 column_name: "foo".to_string(),
                 column_type: ColumnType::Boolean,
+                not_null: false,
             },
             ColumnSpec {
                 column_name: "bar".to_string(),
                 column_type: ColumnType::Varchar { max_len: 5 },
+                not_null: false,
             },
             ColumnSpec {
                 column_name: "baz".to_string(),
                 column_type: ColumnType::Number,
+                not_null: false,
             },
         ];
-        let values = vec![
+        let values = [
             Value::Boolean { value: true },
             Value::Varchar {
                 value: "foo".to_string(),
@@ -536,11 +1363,15 @@ mod tests {
             .zip(values.iter().cloned())
             .collect();
 
-        let mut table = Table::new(&column_specs);
+        let dir = tempfile::tempdir().unwrap();
+        let mut table = Table::create(&test_pool(&dir, 4), &dir.path().join("table"), &column_specs).unwrap();
         let row = Row::new(&column_values, &column_specs).unwrap();
-        table.insert(&row);
+        let row_id = table.insert(&row).unwrap();
 
-        assert_eq!(Ok(row), table.get(0));
+        assert_eq!(RowId { page: 0, slot: 0 }, row_id);
+        assert_eq!(Some(row), table.get(row_id).unwrap());
+        assert_eq!(None, table.get(RowId { page: 0, slot: 1 }).unwrap());
+        assert_eq!(None, table.get(RowId { page: 1, slot: 0 }).unwrap());
     }
 
     #[test]
@@ -550,17 +1381,20 @@ mod tests {
             ColumnSpec {
                 column_name: "foo".to_string(),
                 column_type: ColumnType::Boolean,
+                not_null: false,
             },
             ColumnSpec {
                 column_name: "bar".to_string(),
                 column_type: ColumnType::Varchar { max_len: 5 },
+                not_null: false,
             },
             ColumnSpec {
                 column_name: "baz".to_string(),
                 column_type: ColumnType::Number,
+                not_null: false,
             },
         ];
-        let values1 = vec![
+        let values1 = [
             Value::Boolean { value: true },
             Value::Varchar {
                 value: "foo".to_string(),
@@ -572,7 +1406,7 @@ mod tests {
             .map(|c| c.column_name.clone())
             .zip(values1.iter().cloned())
             .collect();
-        let values2 = vec![
+        let values2 = [
             Value::Boolean { value: false },
             Value::Varchar {
                 value: "Bar".to_string(),
@@ -585,13 +1419,477 @@ mod tests {
             .zip(values2.iter().cloned())
             .collect();
 
-        let mut table = Table::new(&column_specs);
+        let dir = tempfile::tempdir().unwrap();
+        let mut table = Table::create(&test_pool(&dir, 4), &dir.path().join("table"), &column_specs).unwrap();
         let row1 = Row::new(&column_values1, &column_specs).unwrap();
-        table.insert(&row1);
+        let row_id1 = table.insert(&row1).unwrap();
         let row2 = Row::new(&column_values2, &column_specs).unwrap();
-        table.insert(&row2);
+        let row_id2 = table.insert(&row2).unwrap();
+
+        assert_eq!(Some(row1), table.get(row_id1).unwrap());
+        assert_eq!(Some(row2), table.get(row_id2).unwrap());
+    }
+
+    #[test]
+    fn test_table_reopen() {
+        let column_specs = vec![
+            ColumnSpec {
+                column_name: "foo".to_string(),
+                column_type: ColumnType::Varchar { max_len: 100 },
+                not_null: false,
+            },
+            ColumnSpec {
+                column_name: "bar".to_string(),
+                column_type: ColumnType::Number,
+                not_null: false,
+            },
+        ];
+        let rows: Vec<Row> = (0..500)
+            .map(|i| {
+                let column_values = HashMap::from([
+                    (
+                        "foo".to_string(),
+                        Value::Varchar {
+                            value: format!("row {}", i),
+                        },
+                    ),
+                    ("bar".to_string(), Value::Number { value: i }),
+                ]);
+                Row::new(&column_values, &column_specs).unwrap()
+            })
+            .collect();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table");
+
+        let mut table = Table::create(&test_pool(&dir, 2), &path, &column_specs).unwrap();
+        let row_ids: Vec<RowId> = rows.iter().map(|row| table.insert(row).unwrap()).collect();
+        table.flush().unwrap();
+        drop(table);
+
+        // The short varchars leave room for many more rows per page than
+        // their maximum length would.
+        let table = Table::open(&test_pool(&dir, 2), &path, &column_specs, 3, 500).unwrap();
+        assert_eq!(3, table.page_count());
+        for (row_id, row) in row_ids.iter().zip(rows.iter()) {
+            assert_eq!(Some(row), table.get(*row_id).unwrap().as_ref());
+        }
+        let scanned: Vec<RowId> = table.scan().map(|r| r.unwrap().0).collect();
+        assert_eq!(row_ids, scanned);
+    }
+
+    #[test]
+    fn test_table_rollback() {
+        let column_specs = vec![ColumnSpec {
+            column_name: "foo".to_string(),
+            column_type: ColumnType::Number,
+            not_null: false,
+        }];
+        let row = |i| {
+            Row::new(
+                &HashMap::from([("foo".to_string(), Value::Number { value: i })]),
+                &column_specs,
+            )
+            .unwrap()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let pool = test_pool(&dir, 4);
+        let mut table = Table::create(&pool, &dir.path().join("table"), &column_specs).unwrap();
+        let row_id = table.insert(&row(1)).unwrap();
+        table.flush().unwrap();
 
-        assert_eq!(Ok(row1), table.get(0));
-        assert_eq!(Ok(row2), table.get(1));
+        pool.borrow_mut().begin().unwrap();
+        table.insert(&row(2)).unwrap();
+        table.insert(&row(3)).unwrap();
+        pool.borrow_mut().rollback().unwrap();
+        table.rollback();
+
+        assert_eq!(1, table.row_count);
+        assert_eq!(Some(row(1)), table.get(row_id).unwrap());
+
+        let row_id = table.insert(&row(4)).unwrap();
+        assert_eq!(RowId { page: 0, slot: 1 }, row_id);
+        assert_eq!(Some(row(4)), table.get(row_id).unwrap());
+    }
+
+    #[test]
+    fn test_table_delete_reuses_slots() {
+        let column_specs = vec![ColumnSpec {
+            column_name: "foo".to_string(),
+            column_type: ColumnType::Varchar { max_len: 1000 },
+            not_null: false,
+        }];
+        // Long enough for four rows to a page.
+        let row = |i: u64| {
+            Row::new(
+                &HashMap::from([(
+                    "foo".to_string(),
+                    Value::Varchar {
+                        value: format!("{:0>1000}", i),
+                    },
+                )]),
+                &column_specs,
+            )
+            .unwrap()
+        };
+        let row_id = |i: usize| RowId { page: i / 4, slot: i % 4 };
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut table = Table::create(&test_pool(&dir, 2), &dir.path().join("table"), &column_specs).unwrap();
+        for i in 0..10 {
+            assert_eq!(row_id(i as usize), table.insert(&row(i)).unwrap());
+        }
+        assert_eq!(3, table.page_count());
+
+        table.delete(row_id(7)).unwrap();
+        table.delete(row_id(2)).unwrap();
+        table.delete(row_id(2)).unwrap();
+        assert_eq!(8, table.row_count);
+        assert_eq!(None, table.get(row_id(2)).unwrap());
+
+        let scanned: Vec<RowId> = table.scan().map(|r| r.unwrap().0).collect();
+        assert_eq!([0, 1, 3, 4, 5, 6, 8, 9].map(row_id).to_vec(), scanned);
+
+        assert_eq!(row_id(2), table.insert(&row(10)).unwrap());
+        assert_eq!(row_id(7), table.insert(&row(11)).unwrap());
+        assert_eq!(row_id(10), table.insert(&row(12)).unwrap());
+        assert_eq!((3, 11), (table.page_count(), table.row_count));
+        assert_eq!(Some(row(11)), table.get(row_id(7)).unwrap());
+    }
+
+    #[test]
+    fn test_table_update() {
+        let column_specs = vec![ColumnSpec {
+            column_name: "foo".to_string(),
+            column_type: ColumnType::Varchar { max_len: 1000 },
+            not_null: false,
+        }];
+        let row = |len: usize| {
+            let value = Value::Varchar { value: "x".repeat(len) };
+            Row::new(&HashMap::from([("foo".to_string(), value)]), &column_specs).unwrap()
+        };
+        let row_id = |page, slot| RowId { page, slot };
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut table = Table::create(&test_pool(&dir, 2), &dir.path().join("table"), &column_specs).unwrap();
+        for _ in 0..5 {
+            table.insert(&row(800)).unwrap();
+        }
+
+        // A row stays in its slot as long as it fits in its page.
+        assert_eq!(row_id(0, 1), table.update(row_id(0, 1), &row(10)).unwrap());
+        assert_eq!(row_id(0, 1), table.update(row_id(0, 1), &row(840)).unwrap());
+        assert_eq!(Some(row(840)), table.get(row_id(0, 1)).unwrap());
+        assert_eq!(1, table.page_count());
+
+        // Otherwise it moves.
+        assert_eq!(row_id(1, 0), table.update(row_id(0, 2), &row(1000)).unwrap());
+        assert_eq!(None, table.get(row_id(0, 2)).unwrap());
+        assert_eq!(Some(row(1000)), table.get(row_id(1, 0)).unwrap());
+        assert_eq!(Some(row(800)), table.get(row_id(0, 3)).unwrap());
+        assert_eq!((2, 5), (table.page_count(), table.row_count));
+
+        table.delete(row_id(0, 4)).unwrap();
+        assert!(table.update(row_id(0, 4), &row(1)).is_err());
+        assert_eq!(4, table.row_count);
+    }
+
+    #[test]
+    fn test_table_long_values() {
+        let column_specs = vec![
+            ColumnSpec {
+                column_name: "body".to_string(),
+                column_type: ColumnType::Text,
+                not_null: true,
+            },
+            ColumnSpec {
+                column_name: "data".to_string(),
+                column_type: ColumnType::Blob,
+                not_null: false,
+            },
+        ];
+        let row = |body: String, data: Option<Vec<u8>>| {
+            let data = data.map_or(Value::Null, |value| Value::Blob { value });
+            let values = HashMap::from([
+                ("body".to_string(), Value::Varchar { value: body }),
+                ("data".to_string(), data),
+            ]);
+            Row::new(&values, &column_specs).unwrap()
+        };
+        let long_row = row("a".repeat(10_000), Some((0..=255).cycle().take(300).collect()));
+        let short_row = row("hi".to_string(), Some(vec![0xde, 0xad]));
+
+        let dir = tempfile::tempdir().unwrap();
+        let pool = test_pool(&dir, 4);
+        let path = dir.path().join("table.tbl");
+        let mut table = Table::create(&pool, &path, &column_specs).unwrap();
+        let long_id = table.insert(&long_row).unwrap();
+        let short_id = table.insert(&short_row).unwrap();
+        assert_eq!(Some(long_row), table.get(long_id).unwrap());
+        assert_eq!(Some(short_row), table.get(short_id).unwrap());
+
+        // The old value's overflow pages are reused by the new one.
+        let updated_row = row("b".repeat(10_000), None);
+        assert_eq!(long_id, table.update(long_id, &updated_row).unwrap());
+        table.delete(short_id).unwrap();
+        table.flush().unwrap();
+        assert_eq!(4 * Table::PAGE_SIZE as u64, fs::metadata(path.with_extension("ovf")).unwrap().len());
+        drop(table);
+
+        let table = Table::open(&pool, &path, &column_specs, 1, 1).unwrap();
+        assert_eq!(Some(updated_row), table.get(long_id).unwrap());
+        assert_eq!(None, table.get(short_id).unwrap());
+
+        // Values short enough for the row to fit in a quarter of a page are
+        // kept in it.
+        let medium_row = row("c".repeat(900), Some(vec![1; 100]));
+        let tuple = Row::encode(&medium_row, &[]);
+        assert_eq!(1 + (4 + 900) + (4 + 100), tuple.len());
+        assert!(overflow_pages(&tuple, &column_specs).is_empty());
+
+        let wide = vec![ColumnSpec {
+            column_name: "wide".to_string(),
+            column_type: ColumnType::Varchar {
+                max_len: Table::PAGE_SIZE,
+            },
+            not_null: false,
+        }];
+        assert!(Table::create(&pool, &dir.path().join("wide.tbl"), &wide).is_err());
+    }
+
+    #[test]
+    fn test_row_roundtrip_signed_numbers() {
+        let column_types = [ColumnType::SmallInt, ColumnType::Int, ColumnType::Double];
+        let column_specs: Vec<ColumnSpec> = column_types
+            .iter()
+            .enumerate()
+            .map(|(i, column_type)| ColumnSpec {
+                column_name: format!("column{}", i),
+                column_type: *column_type,
+                not_null: false,
+            })
+            .collect();
+        let build = |values: [Value; 3]| {
+            let column_values = column_specs.iter().map(|c| c.column_name.clone()).zip(values).collect();
+            Row::new(&column_values, &column_specs)
+        };
+
+        let row = build([
+            Value::Int { value: -300 },
+            Value::Int { value: i64::MIN },
+            Value::Double { value: -2.5 },
+        ])
+        .unwrap();
+        assert_eq!(2 + 8 + 8, row.values.iter().map(|(_, len)| len).sum::<usize>());
+        let tuple = row.encode(&[]);
+        assert_eq!(row.into_values(), Table::read(&tuple, &column_specs, None).unwrap());
+
+        // Whole numbers convert to the column's type of number if they fit.
+        let row = build([
+            Value::Number { value: 7 },
+            Value::Number { value: 8 },
+            Value::Number { value: 9 },
+        ]);
+        assert_eq!(
+            Ok(vec![Value::Int { value: 7 }, Value::Int { value: 8 }, Value::Double { value: 9.0 }]),
+            row.map(Row::into_values)
+        );
+        let row = build([Value::Int { value: 40000 }, Value::Null, Value::Null]);
+        assert_eq!(
+            Err(RowBuildError::ValueOutOfRange {
+                column_name: "column0".to_string(),
+                column_type: ColumnType::SmallInt
+            }),
+            row
+        );
+        let row = build([Value::Null, Value::Double { value: 1.0 }, Value::Null]);
+        assert!(matches!(row, Err(RowBuildError::ValueTypeMismatch { .. })));
+    }
+
+    #[test]
+    fn test_row_roundtrip_decimals() {
+        let column_specs = vec![ColumnSpec {
+            column_name: "price".to_string(),
+            column_type: ColumnType::Decimal { precision: 5, scale: 2 },
+            not_null: false,
+        }];
+        let build = |value: Value| Row::new(&HashMap::from([("price".to_string(), value)]), &column_specs);
+        let decimal = |text| Value::Decimal {
+            value: Decimal::parse(text).unwrap(),
+        };
+
+        let row = build(decimal("-123.45")).unwrap();
+        assert_eq!(16, row.values.iter().map(|(_, len)| len).sum::<usize>());
+        let values = Table::read(&row.encode(&[]), &column_specs, None).unwrap();
+        assert_eq!(vec![decimal("-123.45")], values);
+        assert_eq!("-123.45", values[0].to_string());
+
+        // Values are rounded to the column's scale, and must then fit its
+        // precision.
+        let value = build(decimal("1.005")).unwrap().into_values();
+        assert_eq!("1.01", value[0].to_string());
+        assert_eq!("7.00", build(Value::Int { value: 7 }).unwrap().into_values()[0].to_string());
+        assert_eq!(
+            Err(RowBuildError::ValueOutOfRange {
+                column_name: "price".to_string(),
+                column_type: ColumnType::Decimal { precision: 5, scale: 2 }
+            }),
+            build(decimal("999.995"))
+        );
+        assert!(build(Value::Number { value: 1000 }).is_err());
+        assert!(matches!(
+            build(Value::Double { value: 1.5 }),
+            Err(RowBuildError::ValueTypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_csv_parse_signed_numbers() {
+        let parse = |column_type: ColumnType, s| column_type.parse(s, false, &Formats::default());
+        assert_eq!(Some(Value::Int { value: -12 }), parse(ColumnType::SmallInt, "-12"));
+        assert_eq!(None, parse(ColumnType::SmallInt, "40000"));
+        assert_eq!(Some(Value::Int { value: -4000000000 }), parse(ColumnType::Int, "-4000000000"));
+        assert_eq!(Some(Value::Double { value: 0.25 }), parse(ColumnType::Double, "0.25"));
+        assert_eq!(None, parse(ColumnType::Number, "-1"));
+
+        // Rounding to the column's scale is left to building the row.
+        let money = ColumnType::Decimal { precision: 6, scale: 2 };
+        assert_eq!(Decimal::parse("-0.5").map(|value| Value::Decimal { value }), parse(money, "-.5"));
+        assert_eq!("12.345", parse(money, "12.345").unwrap().to_string());
+        assert_eq!(None, parse(money, "1e3"));
+    }
+
+    #[test]
+    fn test_csv_parse_dates() {
+        let iso = Formats::default();
+        let parse = |column_type: ColumnType, s, formats: &Formats| column_type.parse(s, false, formats);
+        let date = |s| Date::parse(s).map(|value| Value::Date { value });
+        assert_eq!(date("2017-01-31"), parse(ColumnType::Date, "2017-01-31", &iso));
+        assert_eq!(None, parse(ColumnType::Date, "31/01/2017", &iso));
+        let formats = Formats {
+            date: Some("%d/%m/%Y".to_string()),
+            time: Some("%Hh%M".to_string()),
+            timestamp: Some("%Y%m%d %H%M%S".to_string()),
+        };
+        assert_eq!(date("2017-01-31"), parse(ColumnType::Date, "31/01/2017", &formats));
+        assert_eq!("08:15:00", parse(ColumnType::Time, "8h15", &formats).unwrap().to_string());
+        assert_eq!("12:30:01.5", parse(ColumnType::Time, "12:30:01.5", &iso).unwrap().to_string());
+        assert_eq!(
+            "2017-01-31 08:15:30",
+            parse(ColumnType::Timestamp, "20170131 081530", &formats).unwrap().to_string()
+        );
+        assert_eq!("3 days", parse(ColumnType::Interval, "3 days", &iso).unwrap().to_string());
+    }
+
+    #[test]
+    fn test_row_roundtrip_dates() {
+        let column_types = [ColumnType::Date, ColumnType::Time, ColumnType::Timestamp, ColumnType::Interval];
+        let column_specs: Vec<ColumnSpec> = column_types
+            .iter()
+            .enumerate()
+            .map(|(i, column_type)| ColumnSpec {
+                column_name: format!("column{}", i),
+                column_type: *column_type,
+                not_null: false,
+            })
+            .collect();
+        let build = |values: [Value; 4]| {
+            let column_values = column_specs.iter().map(|c| c.column_name.clone()).zip(values).collect();
+            Row::new(&column_values, &column_specs)
+        };
+
+        let row = build([
+            Value::Date {
+                value: Date::parse("1969-07-20").unwrap(),
+            },
+            Value::Time {
+                value: Time::parse("20:17:40.5").unwrap(),
+            },
+            Value::Timestamp {
+                value: Timestamp::parse("2017-01-31 08:15").unwrap(),
+            },
+            Value::Interval {
+                value: Interval::parse("-1 year 2 days 03:00:00").unwrap(),
+            },
+        ])
+        .unwrap();
+        assert_eq!(4 + 8 + 8 + 16, row.values.iter().map(|(_, len)| len).sum::<usize>());
+        let values: Vec<String> =
+            Table::read(&row.encode(&[]), &column_specs, None).unwrap().iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            vec!["1969-07-20", "20:17:40.5", "2017-01-31 08:15:00", "-1 year 2 days 03:00:00"],
+            values
+        );
+
+        // Text in ISO-8601 is read as a date or time, and a date fills a
+        // timestamp column as its midnight.
+        let varchar = |value: &str| Value::Varchar {
+            value: value.to_string(),
+        };
+        let row = build([
+            varchar("2017-01-31"),
+            varchar("08:15"),
+            Value::Date {
+                value: Date::parse("2017-01-31").unwrap(),
+            },
+            varchar("1 week"),
+        ]);
+        let values: Vec<String> = row.unwrap().into_values().iter().map(|v| v.to_string()).collect();
+        assert_eq!(vec!["2017-01-31", "08:15:00", "2017-01-31 00:00:00", "7 days"], values);
+        let row = build([varchar("2017-02-30"), Value::Null, Value::Null, Value::Null]);
+        assert!(matches!(row, Err(RowBuildError::ValueTypeMismatch { .. })));
+    }
+
+    #[test]
+    fn test_value_ordering() {
+        let mut values = vec![
+            Value::Varchar {
+                value: "a".to_string(),
+            },
+            Value::Number { value: 2 },
+            Value::Boolean { value: true },
+            Value::Number { value: 1 },
+            Value::Boolean { value: false },
+        ];
+        values.sort();
+        assert_eq!(
+            vec![
+                Value::Boolean { value: false },
+                Value::Boolean { value: true },
+                Value::Number { value: 1 },
+                Value::Number { value: 2 },
+                Value::Varchar {
+                    value: "a".to_string()
+                },
+            ],
+            values
+        );
+
+        // Numbers of different types sort by value.
+        let mut numbers = vec![
+            Value::Double { value: f64::NAN },
+            Value::Number { value: 2 },
+            Value::Int { value: -1 },
+            Value::Double { value: 1.5 },
+            Value::Int { value: 2 },
+            Value::Number { value: u64::MAX },
+            Value::Double { value: -1.5 },
+        ];
+        numbers.sort();
+        assert_eq!(
+            vec![
+                Value::Double { value: -1.5 },
+                Value::Int { value: -1 },
+                Value::Double { value: 1.5 },
+                Value::Number { value: 2 },
+                Value::Int { value: 2 },
+                Value::Number { value: u64::MAX },
+                Value::Double { value: f64::NAN },
+            ],
+            numbers
+        );
+        assert_ne!(Value::Number { value: 2 }, Value::Int { value: 2 });
+        assert_eq!(Value::Double { value: f64::NAN }, Value::Double { value: f64::NAN });
     }
 }
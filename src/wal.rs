@@ -0,0 +1,459 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    encoding::{checksum, Decoder, Encoder},
+    pager::Pager,
+};
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum LogRecord {
+    Begin {
+        tx_id: u64,
+    },
+    PageWrite {
+        tx_id: u64,
+        file_name: String,
+        page_no: usize,
+        page: Vec<u8>,
+    },
+    /// The contents a page had before a transaction wrote it to its data file
+    /// ahead of committing.
+    PageUndo {
+        tx_id: u64,
+        file_name: String,
+        page_no: usize,
+        page: Vec<u8>,
+    },
+    Commit {
+        tx_id: u64,
+    },
+}
+
+impl LogRecord {
+    pub fn tx_id(&self) -> u64 {
+        match self {
+            LogRecord::Begin { tx_id }
+            | LogRecord::PageWrite { tx_id, .. }
+            | LogRecord::PageUndo { tx_id, .. }
+            | LogRecord::Commit { tx_id } => *tx_id,
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut encoder = Encoder::default();
+        match self {
+            LogRecord::Begin { tx_id } => {
+                encoder.u8(0);
+                encoder.u64(*tx_id);
+            }
+            LogRecord::PageWrite {
+                tx_id,
+                file_name,
+                page_no,
+                page,
+            } => {
+                encoder.u8(1);
+                encoder.u64(*tx_id);
+                encoder.string(file_name);
+                encoder.u64(*page_no as u64);
+                encoder.bytes(page);
+            }
+            LogRecord::PageUndo {
+                tx_id,
+                file_name,
+                page_no,
+                page,
+            } => {
+                encoder.u8(3);
+                encoder.u64(*tx_id);
+                encoder.string(file_name);
+                encoder.u64(*page_no as u64);
+                encoder.bytes(page);
+            }
+            LogRecord::Commit { tx_id } => {
+                encoder.u8(2);
+                encoder.u64(*tx_id);
+            }
+        }
+        encoder.bytes
+    }
+
+    fn decode(bytes: &[u8]) -> io::Result<LogRecord> {
+        let mut decoder = Decoder::new(bytes);
+        match decoder.u8()? {
+            0 => Ok(LogRecord::Begin {
+                tx_id: decoder.u64()?,
+            }),
+            1 => Ok(LogRecord::PageWrite {
+                tx_id: decoder.u64()?,
+                file_name: decoder.string()?,
+                page_no: decoder.u64()? as usize,
+                page: decoder.bytes()?.to_vec(),
+            }),
+            2 => Ok(LogRecord::Commit {
+                tx_id: decoder.u64()?,
+            }),
+            3 => Ok(LogRecord::PageUndo {
+                tx_id: decoder.u64()?,
+                file_name: decoder.string()?,
+                page_no: decoder.u64()? as usize,
+                page: decoder.bytes()?.to_vec(),
+            }),
+            tag => Err(io::Error::other(format!(
+                "Unknown log record type {}",
+                tag
+            ))),
+        }
+    }
+}
+
+/// The write-ahead log. Every page a transaction changes is appended here,
+/// and the log is synced once the commit record is written, before any of
+/// those pages reach their data files. Once the data files are synced the log
+/// is truncated again, so on startup it only holds work that may not have
+/// made it to disk.
+///
+/// The buffer pool may have to write a page before its transaction commits.
+/// It logs the page's previous contents as a `PageUndo` first, so the write
+/// can be reverted if the transaction never commits.
+///
+/// Each record is framed as its length, a checksum and then its body, so a
+/// record torn by a crash is recognised and ignored along with everything
+/// after it.
+pub struct Wal {
+    path: PathBuf,
+    file: File,
+    next_tx_id: u64,
+}
+
+impl Wal {
+    /// Opens the log, creating it if it doesn't exist. Transaction ids carry
+    /// on from the highest one already in the log, so new transactions are
+    /// never confused with those it still holds.
+    pub fn open(path: &Path) -> io::Result<Wal> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let mut wal = Wal {
+            path: path.to_path_buf(),
+            file,
+            next_tx_id: 1,
+        };
+        wal.next_tx_id = wal.records()?.iter().map(|r| r.tx_id() + 1).max().unwrap_or(1);
+        Ok(wal)
+    }
+
+    pub fn append(&mut self, record: &LogRecord) -> io::Result<()> {
+        self.file.write_all(&frame(record))
+    }
+
+    pub fn begin(&mut self) -> io::Result<u64> {
+        let tx_id = self.next_tx_id;
+        self.next_tx_id += 1;
+        self.append(&LogRecord::Begin { tx_id })?;
+        Ok(tx_id)
+    }
+
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+
+    /// Writes the commit record and syncs the log. Once this returns the
+    /// transaction will survive a crash.
+    pub fn commit(&mut self, tx_id: u64) -> io::Result<()> {
+        self.append(&LogRecord::Commit { tx_id })?;
+        self.file.sync_data()
+    }
+
+    /// Discards the log. Only safe once every committed page has been synced
+    /// to its data file.
+    pub fn checkpoint(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.file.sync_data()
+    }
+
+    /// Removes a transaction's records, keeping those of every other
+    /// transaction. A committed transaction whose pages haven't all reached
+    /// their files yet still needs its records to be recovered. The log is
+    /// rewritten to a new file that then replaces it, so a crash leaves
+    /// either the old log or the new one.
+    pub fn discard(&mut self, tx_id: u64) -> io::Result<()> {
+        let records = self.records()?;
+        if records.iter().all(|r| r.tx_id() == tx_id) {
+            return self.checkpoint();
+        }
+
+        let new_path = self.path.with_extension("new");
+        let mut new_file = File::create(&new_path)?;
+        for record in records.iter().filter(|r| r.tx_id() != tx_id) {
+            new_file.write_all(&frame(record))?;
+        }
+        new_file.sync_data()?;
+        fs::rename(&new_path, &self.path)?;
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        Ok(())
+    }
+
+    /// Reads every intact record, stopping at the first torn one.
+    pub fn records(&self) -> io::Result<Vec<LogRecord>> {
+        let bytes = fs::read(&self.path)?;
+        let mut decoder = Decoder::new(&bytes);
+        let mut records = Vec::new();
+
+        while !decoder.is_empty() {
+            let frame = (|| -> io::Result<(u64, &[u8])> {
+                let len = decoder.u64()? as usize;
+                let expected_checksum = decoder.u64()?;
+                Ok((expected_checksum, decoder.take(len)?))
+            })();
+
+            match frame {
+                Ok((expected_checksum, body)) if checksum(body) == expected_checksum => {
+                    records.push(LogRecord::decode(body)?)
+                }
+                _ => break,
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Replays the page writes of every committed transaction into the files
+    /// under `data_dir` and reverts any page an incomplete transaction wrote
+    /// early, then truncates the log. Returns the number of transactions
+    /// replayed.
+    pub fn recover(&mut self, data_dir: &Path) -> io::Result<usize> {
+        let records = self.records()?;
+
+        let committed: HashSet<u64> = records
+            .iter()
+            .filter_map(|r| match r {
+                LogRecord::Commit { tx_id } => Some(*tx_id),
+                _ => None,
+            })
+            .collect();
+
+        let redo = records.iter().filter_map(|r| match r {
+            LogRecord::PageWrite {
+                tx_id,
+                file_name,
+                page_no,
+                page,
+            } if committed.contains(tx_id) => Some((file_name, *page_no, page)),
+            _ => None,
+        });
+        let undo = Wal::undo_records(&records, |tx_id| !committed.contains(&tx_id));
+
+        let mut pagers: HashMap<String, Pager> = HashMap::new();
+        for (file_name, page_no, page) in redo.chain(undo) {
+            if !pagers.contains_key(file_name) {
+                let path = data_dir.join(file_name);
+                let pager = if path.exists() {
+                    Pager::open(&path)?
+                } else {
+                    Pager::create(&path)?
+                };
+                pagers.insert(file_name.clone(), pager);
+            }
+
+            pagers
+                .get_mut(file_name)
+                .unwrap()
+                .write_page(page_no, page)?;
+        }
+
+        for pager in pagers.values_mut() {
+            pager.sync()?;
+        }
+
+        self.checkpoint()?;
+        Ok(committed.len())
+    }
+
+    /// The `PageUndo` records of the matching transactions, newest first, so
+    /// applying them in order leaves each page as it was before the
+    /// transaction started.
+    pub fn undo_records<F>(
+        records: &[LogRecord],
+        matches: F,
+    ) -> impl Iterator<Item = (&String, usize, &Vec<u8>)>
+    where
+        F: Fn(u64) -> bool,
+    {
+        records.iter().rev().filter_map(move |r| match r {
+            LogRecord::PageUndo {
+                tx_id,
+                file_name,
+                page_no,
+                page,
+            } if matches(*tx_id) => Some((file_name, *page_no, page)),
+            _ => None,
+        })
+    }
+}
+
+/// A record framed as its length, its checksum and then its body.
+fn frame(record: &LogRecord) -> Vec<u8> {
+    let body = record.encode();
+    let mut frame = Vec::with_capacity(16 + body.len());
+    frame.extend((body.len() as u64).to_be_bytes());
+    frame.extend(checksum(&body).to_be_bytes());
+    frame.extend(body);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pager::PAGE_SIZE;
+
+    fn page_of(b: u8) -> Vec<u8> {
+        vec![b; PAGE_SIZE]
+    }
+
+    #[test]
+    fn test_records_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut wal = Wal::open(&dir.path().join("wal")).unwrap();
+
+        let records = vec![
+            LogRecord::Begin { tx_id: 1 },
+            LogRecord::PageWrite {
+                tx_id: 1,
+                file_name: "foo.tbl".to_string(),
+                page_no: 3,
+                page: page_of(7),
+            },
+            LogRecord::Commit { tx_id: 1 },
+        ];
+        for record in records.iter() {
+            wal.append(record).unwrap();
+        }
+
+        assert_eq!(records, wal.records().unwrap());
+    }
+
+    #[test]
+    fn test_torn_record_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wal");
+        let mut wal = Wal::open(&path).unwrap();
+
+        wal.append(&LogRecord::Begin { tx_id: 1 }).unwrap();
+        wal.append(&LogRecord::PageWrite {
+            tx_id: 1,
+            file_name: "foo.tbl".to_string(),
+            page_no: 0,
+            page: page_of(7),
+        })
+        .unwrap();
+
+        let len = fs::metadata(&path).unwrap().len();
+        wal.file.set_len(len - 100).unwrap();
+
+        assert_eq!(vec![LogRecord::Begin { tx_id: 1 }], wal.records().unwrap());
+    }
+
+    #[test]
+    fn test_recover() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut wal = Wal::open(&dir.path().join("wal")).unwrap();
+
+        let committed = wal.begin().unwrap();
+        wal.append(&LogRecord::PageWrite {
+            tx_id: committed,
+            file_name: "foo.tbl".to_string(),
+            page_no: 1,
+            page: page_of(1),
+        })
+        .unwrap();
+        wal.commit(committed).unwrap();
+
+        let incomplete = wal.begin().unwrap();
+        wal.append(&LogRecord::PageWrite {
+            tx_id: incomplete,
+            file_name: "foo.tbl".to_string(),
+            page_no: 0,
+            page: page_of(2),
+        })
+        .unwrap();
+        wal.append(&LogRecord::PageWrite {
+            tx_id: incomplete,
+            file_name: "bar.tbl".to_string(),
+            page_no: 0,
+            page: page_of(2),
+        })
+        .unwrap();
+
+        assert_eq!(1, wal.recover(dir.path()).unwrap());
+        assert!(wal.records().unwrap().is_empty());
+        assert!(!dir.path().join("bar.tbl").exists());
+
+        let mut pager = Pager::open(&dir.path().join("foo.tbl")).unwrap();
+        assert_eq!(2, pager.page_count());
+        assert_eq!(page_of(0), pager.read_page(0).unwrap());
+        assert_eq!(page_of(1), pager.read_page(1).unwrap());
+    }
+
+    #[test]
+    fn test_recover_undoes_incomplete_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut pager = Pager::create(&dir.path().join("foo.tbl")).unwrap();
+        pager.write_page(0, &page_of(1)).unwrap();
+
+        let mut wal = Wal::open(&dir.path().join("wal")).unwrap();
+        let incomplete = wal.begin().unwrap();
+        for (before, after) in [(1, 2), (2, 3)] {
+            wal.append(&LogRecord::PageUndo {
+                tx_id: incomplete,
+                file_name: "foo.tbl".to_string(),
+                page_no: 0,
+                page: page_of(before),
+            })
+            .unwrap();
+            pager.write_page(0, &page_of(after)).unwrap();
+        }
+
+        assert_eq!(0, wal.recover(dir.path()).unwrap());
+        assert_eq!(page_of(1), pager.read_page(0).unwrap());
+    }
+
+    #[test]
+    fn test_discard_keeps_other_transactions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wal");
+        let mut wal = Wal::open(&path).unwrap();
+
+        let committed = wal.begin().unwrap();
+        let write = LogRecord::PageWrite {
+            tx_id: committed,
+            file_name: "foo.tbl".to_string(),
+            page_no: 0,
+            page: page_of(1),
+        };
+        wal.append(&write).unwrap();
+        wal.commit(committed).unwrap();
+        let discarded = wal.begin().unwrap();
+        wal.discard(discarded).unwrap();
+        drop(wal);
+
+        // Ids carry on from the log's, rather than reusing the committed
+        // transaction's.
+        let mut wal = Wal::open(&path).unwrap();
+        assert_eq!(2, wal.begin().unwrap());
+        let expected = vec![
+            LogRecord::Begin { tx_id: committed },
+            write,
+            LogRecord::Commit { tx_id: committed },
+            LogRecord::Begin { tx_id: 2 },
+        ];
+        assert_eq!(expected, wal.records().unwrap());
+
+        wal.discard(2).unwrap();
+        wal.discard(committed).unwrap();
+        assert!(wal.records().unwrap().is_empty());
+    }
+}
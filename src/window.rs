@@ -0,0 +1,272 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use crate::{
+    aggregate::{result_type, Accumulator},
+    expression::{BoundExpression, DataType},
+    query::{self, Rows},
+    sql_parser::{Expression, WindowFunction},
+    table::Value,
+};
+
+struct BoundWindow<'a> {
+    function: WindowFunction,
+    arguments: Vec<BoundExpression<'a>>,
+    partition_by: Vec<BoundExpression<'a>>,
+    order_by: Vec<(BoundExpression<'a>, bool)>,
+}
+
+/// Evaluates window functions. Unlike an aggregation, which turns a group
+/// of rows into one, every row is kept and gets the value of each window
+/// function added after its own values, so `bind` binds the rest of the
+/// query against rows `width` values wide followed by those.
+///
+/// Rows are split into partitions on the `partition by` values and each
+/// partition is sorted on the window's `order by` keys. Rows that sort equal
+/// are peers: they share a rank and a running aggregate.
+pub struct Windows<'a> {
+    width: usize,
+    windows: Vec<(Expression, BoundWindow<'a>)>,
+}
+
+impl<'a> Windows<'a> {
+    /// Plans every window function found in `expressions`, binding what
+    /// they work on with `bind`.
+    pub fn new<F>(expressions: &[&Expression], width: usize, bind: F) -> Result<Windows<'a>, String>
+    where
+        F: Fn(&Expression) -> Result<BoundExpression<'a>, String>,
+    {
+        let mut windows: Vec<(Expression, BoundWindow)> = Vec::new();
+        for expression in expressions {
+            for window in find_windows(expression) {
+                if windows.iter().any(|(planned, _)| planned == window) {
+                    continue;
+                }
+                let Expression::Window {
+                    function,
+                    arguments,
+                    partition_by,
+                    order_by,
+                } = window
+                else {
+                    unreachable!("found as a window function")
+                };
+                check_argument_count(*function, arguments.len())?;
+
+                let bound = BoundWindow {
+                    function: *function,
+                    arguments: arguments.iter().map(&bind).collect::<Result<_, String>>()?,
+                    partition_by: partition_by.iter().map(&bind).collect::<Result<_, String>>()?,
+                    order_by: order_by
+                        .iter()
+                        .map(|o| Ok((bind(&o.expression)?, o.ascending)))
+                        .collect::<Result<_, String>>()?,
+                };
+                windows.push((window.clone(), bound));
+            }
+        }
+        Ok(Windows { width, windows })
+    }
+
+    /// Binds an expression over the rows `run` returns, giving `resolve` a
+    /// go at whatever isn't a planned window function.
+    pub fn bind<F>(&self, expression: &Expression, resolve: &F) -> Result<BoundExpression<'a>, String>
+    where
+        F: Fn(&Expression) -> Result<Option<BoundExpression<'a>>, String>,
+    {
+        BoundExpression::bind_with(expression, &|e| {
+            match self.windows.iter().position(|(planned, _)| planned == e) {
+                Some(index) => Ok(Some(BoundExpression::Column {
+                    index: self.width + index,
+                    data_type: self.windows[index].1.data_type(),
+                })),
+                None => resolve(e),
+            }
+        })
+    }
+
+    /// Adds the window functions' values to every row. Without any window
+    /// functions the rows pass straight through, but otherwise they're all
+    /// read before the first comes out.
+    pub fn run<'r>(&self, rows: Rows<'r>) -> Result<Rows<'r>, String> {
+        if self.windows.is_empty() {
+            return Ok(rows);
+        }
+
+        let mut rows = rows.collect::<Result<Vec<Vec<Value>>, String>>()?;
+        let values = self
+            .windows
+            .iter()
+            .map(|(_, window)| window.evaluate(&rows))
+            .collect::<Result<Vec<Vec<Value>>, String>>()?;
+        for (index, row) in rows.iter_mut().enumerate() {
+            row.extend(values.iter().map(|v| v[index].clone()));
+        }
+        Ok(Box::new(rows.into_iter().map(Ok)))
+    }
+}
+
+impl BoundWindow<'_> {
+    fn data_type(&self) -> Option<DataType> {
+        match self.function {
+            WindowFunction::RowNumber | WindowFunction::Rank | WindowFunction::DenseRank => Some(DataType::Number),
+            WindowFunction::Lag | WindowFunction::Lead => self.arguments[0].data_type(),
+            WindowFunction::Aggregate(function) => result_type(function, self.arguments.first()),
+        }
+    }
+
+    /// The function's value for every row, in the order of `rows`.
+    fn evaluate(&self, rows: &[Vec<Value>]) -> Result<Vec<Value>, String> {
+        let evaluate_all = |expressions: &mut dyn Iterator<Item = &BoundExpression>, row: &[Value]| {
+            expressions
+                .map(|e| e.evaluate(row))
+                .collect::<Result<Vec<Value>, String>>()
+        };
+
+        let mut partitions: Vec<Vec<(Vec<Value>, usize)>> = Vec::new();
+        let mut partition_indices: HashMap<Vec<Value>, usize> = HashMap::new();
+        for (index, row) in rows.iter().enumerate() {
+            let key = evaluate_all(&mut self.partition_by.iter(), row)?;
+            let sort_key = evaluate_all(&mut self.order_by.iter().map(|(e, _)| e), row)?;
+            let partition = *partition_indices.entry(query::row_key(&key)).or_insert_with(|| {
+                partitions.push(Vec::new());
+                partitions.len() - 1
+            });
+            partitions[partition].push((sort_key, index));
+        }
+
+        let mut values = vec![Value::Null; rows.len()];
+        for mut partition in partitions {
+            // A stable sort, so peers keep the order they came in.
+            partition.sort_by(|(left, _), (right, _)| self.compare(left, right));
+            let indices: Vec<usize> = partition.iter().map(|(_, index)| *index).collect();
+
+            let mut peers_start = 0;
+            let mut dense_rank = 0;
+            let mut accumulator = match self.function {
+                WindowFunction::Aggregate(function) => Some(Accumulator::new(function)),
+                _ => None,
+            };
+            for position in 0..partition.len() {
+                let is_first_peer = position == 0 || partition[position - 1].0 != partition[position].0;
+                if is_first_peer {
+                    peers_start = position;
+                    dense_rank += 1;
+                    if let Some(accumulator) = accumulator.as_mut() {
+                        let peers_end = (position..partition.len())
+                            .find(|p| partition[*p].0 != partition[position].0)
+                            .unwrap_or(partition.len());
+                        for &index in &indices[position..peers_end] {
+                            let argument = self.arguments.first().map(|a| a.evaluate(&rows[index])).transpose()?;
+                            accumulator.add(argument)?;
+                        }
+                    }
+                }
+
+                values[indices[position]] = match self.function {
+                    WindowFunction::RowNumber => Value::Number {
+                        value: position as u64 + 1,
+                    },
+                    WindowFunction::Rank => Value::Number {
+                        value: peers_start as u64 + 1,
+                    },
+                    WindowFunction::DenseRank => Value::Number { value: dense_rank },
+                    WindowFunction::Lag | WindowFunction::Lead => self.offset_value(&indices, position, rows)?,
+                    WindowFunction::Aggregate(_) => accumulator.clone().map_or(Ok(Value::Null), Accumulator::finish)?,
+                };
+            }
+        }
+        Ok(values)
+    }
+
+    fn compare(&self, left: &[Value], right: &[Value]) -> Ordering {
+        self.order_by
+            .iter()
+            .zip(left.iter().zip(right.iter()))
+            .map(|((_, ascending), (l, r))| if *ascending { l.cmp(r) } else { r.cmp(l) })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+
+    /// `lag(expression, offset, default)` is `expression` for the row
+    /// `offset` rows before the current one in the partition, or `default`
+    /// if there's no such row. `lead` looks after it. The offset defaults
+    /// to 1 and the default to null.
+    fn offset_value(&self, indices: &[usize], position: usize, rows: &[Vec<Value>]) -> Result<Value, String> {
+        let row = &rows[indices[position]];
+        let offset = match self.arguments.get(1).map(|a| a.evaluate(row)).transpose()? {
+            None => 1,
+            Some(Value::Number { value }) => value as usize,
+            Some(Value::Int { value }) if value >= 0 => value as usize,
+            Some(other) => return Err(format!("{} expects a number of rows but was given {}", self.function, other)),
+        };
+        let target = match self.function {
+            WindowFunction::Lag => position.checked_sub(offset),
+            _ => position.checked_add(offset).filter(|t| *t < indices.len()),
+        };
+        match target {
+            Some(target) => self.arguments[0].evaluate(&rows[indices[target]]),
+            None => self.arguments.get(2).map_or(Ok(Value::Null), |d| d.evaluate(row)),
+        }
+    }
+}
+
+fn check_argument_count(function: WindowFunction, count: usize) -> Result<(), String> {
+    let (min, max) = match function {
+        WindowFunction::RowNumber | WindowFunction::Rank | WindowFunction::DenseRank => (0, 0),
+        WindowFunction::Lag | WindowFunction::Lead => (1, 3),
+        // The parser only accepts aggregates with the right arguments.
+        WindowFunction::Aggregate(_) => (0, 1),
+    };
+    if (min..=max).contains(&count) {
+        Ok(())
+    } else if min == max {
+        Err(format!("{} takes {} argument(s) but was given {}", function, min, count))
+    } else {
+        Err(format!("{} takes {} to {} arguments but was given {}", function, min, max, count))
+    }
+}
+
+/// The window function calls within an expression.
+pub fn find_windows(expression: &Expression) -> Vec<&Expression> {
+    match expression {
+        Expression::Window { .. } => vec![expression],
+        Expression::Column { .. } | Expression::Literal { .. } | Expression::Aggregate { .. } => Vec::new(),
+        Expression::Comparison { left, right, .. }
+        | Expression::Arithmetic { left, right, .. }
+        | Expression::And { left, right }
+        | Expression::Or { left, right }
+        | Expression::PatternMatch {
+            expression: left,
+            pattern: right,
+            ..
+        } => {
+            let mut windows = find_windows(left);
+            windows.extend(find_windows(right));
+            windows
+        }
+        Expression::Not { expression }
+        | Expression::InSubquery { expression, .. }
+        | Expression::IsNull { expression, .. } => find_windows(expression),
+        Expression::InList { expression, list, .. } => std::iter::once(expression.as_ref())
+            .chain(list.iter())
+            .flat_map(find_windows)
+            .collect(),
+        Expression::Between {
+            expression, low, high, ..
+        } => [expression, low, high].into_iter().flat_map(|e| find_windows(e)).collect(),
+        Expression::Case {
+            operand,
+            branches,
+            otherwise,
+        } => operand
+            .iter()
+            .chain(otherwise.iter())
+            .map(Box::as_ref)
+            .chain(branches.iter().flat_map(|(when, then)| [when, then]))
+            .flat_map(find_windows)
+            .collect(),
+        Expression::Function { arguments, .. } => arguments.iter().flat_map(find_windows).collect(),
+        // A subquery's window functions belong to the subquery.
+        Expression::Exists { .. } => Vec::new(),
+    }
+}